@@ -0,0 +1,255 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    csv_reader::{BadValuePolicy, CsvColumns},
+    Trace,
+};
+use arrow_array::{
+    cast::AsArray,
+    types::{Float32Type, Int64Type},
+    Array, RecordBatch,
+};
+use arrow_cast::cast::cast;
+use arrow_ipc::reader::{FileReader, StreamReader};
+use arrow_schema::{ArrowError, DataType, Schema, TimeUnit};
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask};
+use std::{fmt::Display, fs::File, path::Path};
+
+/*******************************
+ * Arrow Format
+ *******************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Columnar file formats that are read using Apache Arrow
+pub enum ArrowFormat {
+    /// Apache Parquet file
+    Parquet,
+    /// Arrow IPC file or stream, also known as Feather
+    Ipc,
+}
+
+impl ArrowFormat {
+    /// Returns the format given the extension of the file, i.e., .parquet or .pq for Parquet and .arrow, .arrows,
+    /// .ipc, or .feather for Arrow IPC
+    pub fn from_path(file_name: &str) -> Option<ArrowFormat> {
+        let extension = Path::new(file_name).extension()?.to_str()?;
+        match extension.to_lowercase().as_str() {
+            "parquet" | "pq" => Some(ArrowFormat::Parquet),
+            "arrow" | "arrows" | "ipc" | "feather" => Some(ArrowFormat::Ipc),
+            _ => None,
+        }
+    }
+}
+
+/*******************************
+ * Reader
+ *******************************/
+/// Reads several columns of a Parquet or Arrow IPC file into a trace, values are cast to f32.
+/// Only the selected columns are read from Parquet files.
+/// # Arguments
+/// * `file_name` - Location of the file, the format is given by its extension, see ArrowFormat::from_path()
+/// * `columns` - Columns that are read and their signal names, AllNumeric reads all columns with a numeric type
+/// * `prefix` - Prefix of all signal names, e.g., ``uas_``
+/// * `time_column` - Column used as time axis, numbers are seconds and timestamps are converted to seconds since the first row
+/// * `number_skipped_entries` - used for subsampling ie only every n-th row is read
+/// * `policy` - Defines how null values and values that cannot be cast to f32 are handled
+pub fn read_arrow_trace(
+    file_name: &str,
+    columns: CsvColumns,
+    prefix: &str,
+    time_column: Option<&str>,
+    number_skipped_entries: usize,
+    policy: BadValuePolicy,
+) -> Result<Trace, String> {
+    let format = ArrowFormat::from_path(file_name).ok_or(format!(
+        "{file_name} is neither a Parquet nor an Arrow IPC file"
+    ))?;
+    let error = |e: &dyn Display| format!("Could not read {file_name}: {e}");
+    let file = File::open(file_name).map_err(|e| error(&e))?;
+    // (column name, signal name)
+    let (selected, batches): (Vec<(String, String)>, Box<dyn Iterator<Item = _>>) = match format {
+        ArrowFormat::Parquet => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file).map_err(|e| error(&e))?;
+            let selected =
+                select_columns(builder.schema(), columns, prefix, time_column, file_name)?;
+            let indices = selected
+                .iter()
+                .map(|(column, _)| column.as_str())
+                .chain(time_column)
+                .map(|column| builder.schema().index_of(column).map_err(|e| error(&e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+            let reader = builder
+                .with_projection(mask)
+                .build()
+                .map_err(|e| error(&e))?;
+            (selected, Box::new(reader))
+        }
+        // Arrow IPC files start with a magic number, otherwise the file is read as Arrow IPC stream
+        ArrowFormat::Ipc => match FileReader::try_new(file, None) {
+            Ok(reader) => (
+                select_columns(&reader.schema(), columns, prefix, time_column, file_name)?,
+                Box::new(reader),
+            ),
+            Err(_) => {
+                let file = File::open(file_name).map_err(|e| error(&e))?;
+                let reader = StreamReader::try_new(file, None).map_err(|e| error(&e))?;
+                (
+                    select_columns(&reader.schema(), columns, prefix, time_column, file_name)?,
+                    Box::new(reader),
+                )
+            }
+        },
+    };
+    read_batches(
+        batches,
+        &selected,
+        time_column,
+        file_name,
+        number_skipped_entries,
+        policy,
+    )
+}
+
+/// Returns the selected columns and their signal names, each column (and the time column) must be part of the schema
+fn select_columns(
+    schema: &Schema,
+    columns: CsvColumns,
+    prefix: &str,
+    time_column: Option<&str>,
+    file_name: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let missing = |column: &str| format!("Column '{column}' not found in schema of {file_name}");
+    if let Some(time_column) = time_column {
+        schema
+            .field_with_name(time_column)
+            .map_err(|_| missing(time_column))?;
+    }
+    match columns {
+        CsvColumns::Selected(columns) => columns
+            .iter()
+            .map(|(column, signal)| match schema.field_with_name(column) {
+                Ok(_) => Ok((column.to_string(), format!("{prefix}{signal}"))),
+                Err(_) => Err(missing(column)),
+            })
+            .collect(),
+        CsvColumns::AllNumeric => Ok(schema
+            .fields()
+            .iter()
+            .filter(|field| {
+                field.data_type().is_numeric() && Some(field.name().as_str()) != time_column
+            })
+            .map(|field| (field.name().clone(), format!("{prefix}{}", field.name())))
+            .collect()),
+    }
+}
+
+/// Collects the values of the selected columns and the time axis from the record batches
+fn read_batches(
+    batches: Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>>>,
+    selected: &[(String, String)],
+    time_column: Option<&str>,
+    file_name: &str,
+    number_skipped_entries: usize,
+    policy: BadValuePolicy,
+) -> Result<Trace, String> {
+    // The time column is the last column if present
+    let names: Vec<&str> = selected
+        .iter()
+        .map(|(column, _)| column.as_str())
+        .chain(time_column)
+        .collect();
+    let mut values = vec![Vec::<f32>::new(); names.len()];
+    let mut last_values = vec![None; names.len()];
+    let mut row_values = vec![0.0; names.len()];
+    let mut first_timestamp = None;
+    let (mut number_rows, mut number_samples) = (0, 0);
+    for batch in batches {
+        let batch = batch.map_err(|e| format!("Could not read {file_name}: {e}"))?;
+        let mut arrays = vec![];
+        for (i, name) in names.iter().enumerate() {
+            let array = batch
+                .column_by_name(name)
+                .ok_or(format!("Column '{name}' not found in {file_name}"))?;
+            let array = if time_column.is_some() && i == names.len() - 1 {
+                to_seconds(array.as_ref(), &mut first_timestamp)
+            } else {
+                to_f32(array.as_ref())
+            };
+            arrays.push(array.map_err(|e| format!("Column '{name}' of {file_name}: {e}"))?);
+        }
+        'rows: for row in 0..batch.num_rows() {
+            number_rows += 1;
+            for (i, (name, array)) in names.iter().zip(&arrays).enumerate() {
+                row_values[i] = match (array[row], policy, last_values[i]) {
+                    (Some(f), _, _) => f,
+                    // Skipped rows must be skipped for all columns
+                    (None, BadValuePolicy::SkipRow, _) => continue 'rows,
+                    (None, BadValuePolicy::ForwardFill, Some(last_value)) => last_value,
+                    (None, BadValuePolicy::Nan, _) => f32::NAN,
+                    (None, BadValuePolicy::Fail, _) | (None, BadValuePolicy::ForwardFill, None) => {
+                        return Err(format!(
+                            "Could not read value of column '{name}' in {file_name} at row {number_rows}: null or not a number"
+                        ))
+                    }
+                };
+            }
+            for (last_value, value) in last_values.iter_mut().zip(&row_values) {
+                *last_value = Some(*value);
+            }
+            if number_samples % number_skipped_entries.max(1) == 0 {
+                for (column_values, value) in values.iter_mut().zip(&row_values) {
+                    column_values.push(*value);
+                }
+            }
+            number_samples += 1;
+        }
+    }
+    let time = time_column.and_then(|_| values.pop());
+    let trace = Trace::new(
+        selected
+            .iter()
+            .map(|(_, signal)| signal.clone())
+            .zip(values)
+            .collect(),
+    )?;
+    match time {
+        Some(time) => trace
+            .with_time(time)
+            .map_err(|e| format!("{file_name}: {e}")),
+        None => Ok(trace),
+    }
+}
+
+/// Casts the values of a column to f32, values that cannot be cast are None
+fn to_f32(array: &dyn Array) -> Result<Vec<Option<f32>>, ArrowError> {
+    Ok(cast(array, &DataType::Float32)?
+        .as_primitive::<Float32Type>()
+        .iter()
+        .collect())
+}
+
+/// Converts a column to seconds, timestamps are relative to the first timestamp of the file since f32 cannot
+/// represent the time since the epoch precisely
+fn to_seconds(
+    array: &dyn Array,
+    first_timestamp: &mut Option<i64>,
+) -> Result<Vec<Option<f32>>, ArrowError> {
+    let units_per_second = match array.data_type() {
+        DataType::Timestamp(TimeUnit::Second, _) => 1.0,
+        DataType::Timestamp(TimeUnit::Millisecond, _) => 1e3,
+        DataType::Timestamp(TimeUnit::Microsecond, _) => 1e6,
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => 1e9,
+        _ => return to_f32(array),
+    };
+    Ok(cast(array, &DataType::Int64)?
+        .as_primitive::<Int64Type>()
+        .iter()
+        .map(|timestamp| {
+            timestamp.map(|timestamp| {
+                let first = *first_timestamp.get_or_insert(timestamp);
+                ((timestamp - first) as f64 / units_per_second) as f32
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,276 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    behaviortree::Tbt, check_memory_limit, command_line_parser::CommandLineArguments,
+    estimate_table_memory, evaluate, evaluation_result::Segment, get_best_number_skipped_entries,
+    get_tbt_and_trace, read_trace, reporter::SampleMapping, ProvidesTraceAndTree,
+};
+use rayon::prelude::*;
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/*******************************
+ * Batch Setting
+ *******************************/
+/// Defines the logs that are evaluated by the batch command
+/// # Arguments
+/// * `patterns` - Directories of log folders or glob patterns of log folders, see find_logs()
+/// * `jobs` - Number of logs that are evaluated in parallel, 0 uses all cores
+pub struct BatchSetting {
+    pub patterns: Vec<String>,
+    pub jobs: usize,
+}
+
+/// Returns the sorted log folders given directories or glob patterns. A pattern may contain the
+/// wildcards * and ? in its last component, e.g., res/logs_wind_*, and each match is a log folder.
+/// A directory without wildcards is a log folder if it has no subdirectories, otherwise each
+/// subdirectory is a log folder. Log folders end with a slash since logfiles are appended.
+/// # Arguments
+/// * `patterns` - Directories or glob patterns
+pub fn find_logs(patterns: &[String]) -> Result<Vec<String>, String> {
+    let mut logs = vec![];
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        let mut found = if name.contains(['*', '?']) {
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            subdirectories(parent)?
+                .into_iter()
+                .filter(|directory| {
+                    directory
+                        .file_name()
+                        .and_then(|directory| directory.to_str())
+                        .is_some_and(|directory| matches_pattern(name, directory))
+                })
+                .collect()
+        } else if path.is_dir() {
+            let subdirectories = subdirectories(path)?;
+            if subdirectories.is_empty() {
+                vec![path.to_path_buf()]
+            } else {
+                subdirectories
+            }
+        } else {
+            return Err(format!("{pattern} is not a directory"));
+        };
+        if found.is_empty() {
+            return Err(format!("No log folder matches {pattern}"));
+        }
+        found.sort();
+        logs.extend(
+            found
+                .iter()
+                .map(|log| format!("{}/", log.display().to_string().trim_end_matches('/'))),
+        );
+    }
+    Ok(logs)
+}
+
+/// Returns the subdirectories of a directory
+fn subdirectories(directory: &Path) -> Result<Vec<std::path::PathBuf>, String> {
+    let entries = std::fs::read_dir(directory)
+        .map_err(|e| format!("Could not read directory {}: {e}", directory.display()))?;
+    Ok(entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect())
+}
+
+/// Returns true if the name matches the pattern, where * matches any sequence of characters
+/// and ? matches a single character
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Position in the pattern and name after the last *, used for backtracking
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/*******************************
+ * Batch Evaluation
+ *******************************/
+#[derive(Clone, Debug)]
+/// Summary of the evaluation of a single log
+/// # Arguments
+/// * `log` - Log folder
+/// * `runtime` - Duration of the evaluation including reading the log
+/// * `outcome` - Summary of the result or the error that occurred
+pub struct BatchEntry {
+    pub log: String,
+    pub runtime: Duration,
+    pub outcome: Result<BatchSummary, String>,
+}
+
+#[derive(Clone, Debug)]
+/// Summary of an EvaluationResult
+/// # Arguments
+/// * `robustness` - Robustness of the TBT for the whole trace
+/// * `is_approximate` - True if lazy evaluation or sub sampling was used
+/// * `branch` - Chosen child of the top-level Fallback in the best segmentation, i.e., its name if it is a leaf
+///   and otherwise the name of its first leaf, None if the TBT has no Fallback
+/// * `segments` - Leaf segments of the best segmentation
+pub struct BatchSummary {
+    pub robustness: f32,
+    pub is_approximate: bool,
+    pub branch: Option<String>,
+    pub segments: Vec<LeafSegment>,
+}
+
+#[derive(Clone, Debug)]
+/// A leaf segment using the indices and timestamps of the logfile
+pub struct LeafSegment {
+    pub name: String,
+    pub original_lower: usize,
+    pub original_upper: usize,
+    pub start_time: Option<f32>,
+    pub end_time: Option<f32>,
+}
+
+impl BatchSummary {
+    fn new(
+        robustness: f32,
+        is_approximate: bool,
+        segmentation: &[Segment],
+        mapping: &SampleMapping,
+    ) -> BatchSummary {
+        let leaf_name = |segment: &Segment| {
+            segment
+                .name
+                .clone()
+                .unwrap_or_else(|| segment.description.clone())
+        };
+        // The parents of a segment up to the root, the top-level Fallback is the one with fewest parents
+        let ancestors = |position: usize| {
+            std::iter::successors(segmentation[position].parent, |parent| {
+                segmentation[*parent].parent
+            })
+        };
+        let top_level_fallback = (0..segmentation.len())
+            .filter(|position| segmentation[*position].kind == "Fallback")
+            .min_by_key(|position| ancestors(*position).count());
+        // A segmentation lists the segments of a subtree after its root, i.e., the first leaf
+        // that descends from the chosen child names it
+        let branch = top_level_fallback.and_then(|fallback| {
+            let child = (fallback + 1..segmentation.len())
+                .find(|position| segmentation[*position].parent == Some(fallback))?;
+            let first_leaf = (child..segmentation.len()).find(|position| {
+                segmentation[*position].is_leaf()
+                    && (*position == child || ancestors(*position).any(|parent| parent == child))
+            });
+            Some(match first_leaf {
+                Some(leaf) => leaf_name(&segmentation[leaf]),
+                None => segmentation[child].description.clone(),
+            })
+        });
+        let segments = segmentation
+            .iter()
+            .filter(|segment| segment.is_leaf())
+            .map(|segment| LeafSegment {
+                name: leaf_name(segment),
+                original_lower: mapping.original_index(segment.lower),
+                original_upper: mapping.original_index(segment.upper),
+                start_time: mapping.timestamp(segment.lower),
+                end_time: mapping.timestamp(segment.upper),
+            })
+            .collect();
+        BatchSummary {
+            robustness,
+            is_approximate,
+            branch,
+            segments,
+        }
+    }
+}
+
+/// Evaluates each log using the settings of the command line, errors of a log are part of its entry.
+/// The entries are in the same order as the logs. Returns an error if the thread pool cannot be created.
+/// # Arguments
+/// * `logs` - Log folders, see find_logs()
+/// * `specification` - TBT read from a specification file, the tree of U is used if None
+/// * `arguments` - Settings of the evaluation
+/// * `jobs` - Number of logs that are evaluated in parallel, 0 uses all cores
+pub fn run_batch<U: ProvidesTraceAndTree>(
+    logs: &[String],
+    specification: Option<&Tbt>,
+    arguments: &CommandLineArguments,
+    jobs: usize,
+) -> Result<Vec<BatchEntry>, String> {
+    let evaluate_entry = |log: &String| {
+        let start = SystemTime::now();
+        let outcome = evaluate_log::<U>(log, specification, arguments);
+        BatchEntry {
+            log: log.clone(),
+            runtime: start.elapsed().unwrap_or_default(),
+            outcome,
+        }
+    };
+    if jobs == 1 {
+        return Ok(logs.iter().map(evaluate_entry).collect());
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(|e| format!("Could not create thread pool: {e}"))?;
+    Ok(pool.install(|| logs.par_iter().map(evaluate_entry).collect()))
+}
+
+/// Evaluates a single log
+fn evaluate_log<U: ProvidesTraceAndTree>(
+    log: &str,
+    specification: Option<&Tbt>,
+    arguments: &CommandLineArguments,
+) -> Result<BatchSummary, String> {
+    let trace = read_trace::<U>(log, 0, &arguments.trace_setting)?;
+    let (number_skipped_entries, delta_rho_skipped) =
+        get_best_number_skipped_entries::<U>(&trace, arguments.sub_sampling, specification);
+    let (trace, tbt) =
+        get_tbt_and_trace::<U>(trace, number_skipped_entries, specification.cloned())?;
+    let estimate = estimate_table_memory(&tbt, trace.len(), arguments.table_backend);
+    check_memory_limit(&estimate, arguments.memory_limit, arguments.table_backend)?;
+    let mapping = SampleMapping::new(&trace, number_skipped_entries);
+    // Alternative segmentations are not part of the summary
+    let result = evaluate(
+        tbt,
+        trace,
+        SystemTime::now(),
+        arguments.sub_sampling,
+        arguments.lazy_evaluation,
+        arguments.semantics,
+        arguments.table_backend,
+        arguments.threads,
+        delta_rho_skipped,
+        arguments.print_leaf_segments_only,
+        None,
+        false,
+    )?;
+    Ok(BatchSummary::new(
+        result.robustness,
+        result.is_approximate,
+        &result.segmentation,
+        &mapping,
+    ))
+}
@@ -1,22 +1,56 @@
 // SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{stl::Stl, table::Table, Trace};
-use std::{collections::HashMap, time::SystemTime};
+use crate::{
+    semantics::Semantics,
+    stl::{Bound, Stl},
+    table::Table,
+    Trace,
+};
+use rayon::prelude::*;
+use std::{cell::Cell, collections::HashMap, time::SystemTime};
 
 type SubtreeIdx = usize;
 pub type Segmentation<'a> = Vec<(&'a TbtNode, usize, usize, f32)>;
-static mut NODECOUNT: SubtreeIdx = 0;
-/// Gets formula count
-fn gnc() -> SubtreeIdx {
-    unsafe {
-        NODECOUNT += 1;
-        NODECOUNT - 1
-    }
+/*******************************
+ * TbtBuilder
+ *******************************/
+#[derive(Default)]
+/// Allocates the indices of the nodes and formulas of a single TBT.
+/// It provides the constructors of TbtNode (see below) and Stl (see stl.rs).
+/// # Arguments
+/// * `node_count` - Number of allocated node indices
+/// * `formula_count` - Number of allocated formula indices
+pub struct TbtBuilder {
+    node_count: Cell<SubtreeIdx>,
+    formula_count: Cell<usize>,
 }
-pub fn tbt_node_reset_count() {
-    unsafe { NODECOUNT = 0 }
+
+impl TbtBuilder {
+    pub fn new() -> Self {
+        TbtBuilder::default()
+    }
+
+    /// Gets a new node index
+    fn gnc(&self) -> SubtreeIdx {
+        let index = self.node_count.get();
+        self.node_count.set(index + 1);
+        index
+    }
+
+    /// Gets a new formula index
+    pub(crate) fn gfc(&self) -> usize {
+        let index = self.formula_count.get();
+        self.formula_count.set(index + 1);
+        index
+    }
+
+    /// Creates a TBT whose node and formula counts are the indices allocated so far
+    pub fn build(&self, tree: TbtNode) -> Tbt {
+        Tbt::new(tree, self.node_count.get(), self.formula_count.get())
+    }
 }
+
 /*******************************
  * Tbt
  *******************************/
@@ -26,18 +60,37 @@ pub fn tbt_node_reset_count() {
 /// # Arguments
 /// * `next_nodes` - Maps current nodes to its successor nodes
 /// * `tree` - The main tbt tree
+/// * `number_nodes` - Upper bound of all node indices in tree
+/// * `number_formulas` - Upper bound of all formula indices in tree
 pub struct Tbt {
     pub next_nodes: HashMap<usize, Vec<usize>>,
     pub tree: TbtNode,
+    number_nodes: usize,
+    number_formulas: usize,
 }
 
 #[allow(dead_code)]
 impl Tbt {
-    pub fn new(tree: TbtNode) -> Self {
+    /// Creates a TBT, usually called by TbtBuilder::build
+    /// # Arguments
+    /// * `tree` - The main tbt tree
+    /// * `number_nodes` - Number of node indices, i.e., all node indices must be smaller
+    /// * `number_formulas` - Number of formula indices, i.e., all formula indices must be smaller
+    pub fn new(tree: TbtNode, number_nodes: usize, number_formulas: usize) -> Self {
         let mut next_nodes = HashMap::new();
         let mut stack = Vec::new();
         Tbt::init_next_nodes_map(&tree, &mut stack, &mut next_nodes);
-        Tbt { next_nodes, tree }
+        Tbt {
+            next_nodes,
+            tree,
+            number_nodes,
+            number_formulas,
+        }
+    }
+
+    /// Converts all bounds in seconds into samples, see TbtNode::resolve_bounds
+    pub fn resolve_bounds(&mut self, sampling_period: Option<f32>) -> Result<(), String> {
+        self.tree.resolve_bounds(sampling_period)
     }
 
     /// Creates the initial mapping for the TBT.next_nodes ie for each node its successor node
@@ -99,8 +152,31 @@ impl Tbt {
         }
     }
 
-    pub fn get_number_nodes() -> usize {
-        unsafe { NODECOUNT }
+    /// Returns the horizon of each subformula, see Stl::set_horizons()
+    pub fn get_formula_horizons(&self) -> Vec<Option<usize>> {
+        let mut horizons = vec![None; self.number_formulas];
+        self.tree.set_formula_horizons(&mut horizons);
+        horizons
+    }
+
+    /// Returns the largest horizon of the subformulas and timeouts in samples, i.e., the number of samples after
+    /// which bounded operators no longer depend on further samples. None if the TBT has no bounded temporal
+    /// operator or timeout. Bounds in seconds must have been resolved before.
+    pub fn get_horizon(&self) -> Option<usize> {
+        self.get_formula_horizons()
+            .into_iter()
+            .flatten()
+            .chain(self.tree.get_timeouts())
+            .filter(|horizon| *horizon > 0 && *horizon < usize::MAX)
+            .max()
+    }
+
+    pub fn get_number_nodes(&self) -> usize {
+        self.number_nodes
+    }
+
+    pub fn get_number_formulas(&self) -> usize {
+        self.number_formulas
     }
 }
 /*******************************
@@ -113,14 +189,14 @@ pub enum TbtNode {
     Fallback(SubtreeIdx, Vec<TbtNode>),
     Parallel(SubtreeIdx, usize, Vec<TbtNode>),
     Sequence(SubtreeIdx, Box<TbtNode>, Box<TbtNode>),
-    Timeout(SubtreeIdx, usize, Box<TbtNode>),
+    Timeout(SubtreeIdx, Bound, Box<TbtNode>),
     Kleene(SubtreeIdx, usize, Option<Box<TbtNode>>, Box<TbtNode>),
 }
 
 #[allow(dead_code)]
 impl TbtNode {
     /// Returns the index of a TBT node
-    fn get_index(&self) -> SubtreeIdx {
+    pub fn get_index(&self) -> SubtreeIdx {
         match self {
             TbtNode::Leaf(index, _, _)
             | TbtNode::Fallback(index, _)
@@ -131,6 +207,95 @@ impl TbtNode {
         }
     }
 
+    /// Returns the name of the operator of a TBT node, e.g., Leaf
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            TbtNode::Leaf(_, _, _) => "Leaf",
+            TbtNode::Fallback(_, _) => "Fallback",
+            TbtNode::Parallel(_, _, _) => "Parallel",
+            TbtNode::Sequence(_, _, _) => "Sequence",
+            TbtNode::Timeout(_, _, _) => "Timeout",
+            TbtNode::Kleene(_, _, _, _) => "Kleene",
+        }
+    }
+
+    /// Returns the indices of the direct children, for Kleene this includes the next repetition
+    pub fn get_children_indices(&self) -> Vec<SubtreeIdx> {
+        match self {
+            TbtNode::Leaf(_, _, _) => vec![],
+            TbtNode::Fallback(_, children) | TbtNode::Parallel(_, _, children) => {
+                children.iter().map(|child| child.get_index()).collect()
+            }
+            TbtNode::Sequence(_, l_child, r_child) => {
+                vec![l_child.get_index(), r_child.get_index()]
+            }
+            TbtNode::Timeout(_, _, child) => vec![child.get_index()],
+            TbtNode::Kleene(_, _, kleene_next, child) => {
+                let mut indices = vec![child.get_index()];
+                if let Some(kleene_next) = kleene_next {
+                    indices.push(kleene_next.get_index());
+                }
+                indices
+            }
+        }
+    }
+
+    /// Returns the number of leaves, for Kleene this includes the leaves of all repetitions
+    pub fn get_number_leaves(&self) -> usize {
+        match self {
+            TbtNode::Leaf(_, _, _) => 1,
+            TbtNode::Fallback(_, children) | TbtNode::Parallel(_, _, children) => {
+                children.iter().map(|child| child.get_number_leaves()).sum()
+            }
+            TbtNode::Sequence(_, l_child, r_child) => {
+                l_child.get_number_leaves() + r_child.get_number_leaves()
+            }
+            TbtNode::Timeout(_, _, child) => child.get_number_leaves(),
+            TbtNode::Kleene(_, _, kleene_next, child) => {
+                child.get_number_leaves()
+                    + kleene_next
+                        .as_ref()
+                        .map_or(0, |kleene_next| kleene_next.get_number_leaves())
+            }
+        }
+    }
+
+    /// Returns the bounds of all timeouts in samples, bounds in seconds must have been resolved before
+    pub fn get_timeouts(&self) -> Vec<usize> {
+        match self {
+            TbtNode::Leaf(_, _, _) => vec![],
+            TbtNode::Fallback(_, children) | TbtNode::Parallel(_, _, children) => children
+                .iter()
+                .flat_map(|child| child.get_timeouts())
+                .collect(),
+            TbtNode::Sequence(_, l_child, r_child) => {
+                let mut timeouts = l_child.get_timeouts();
+                timeouts.extend(r_child.get_timeouts());
+                timeouts
+            }
+            TbtNode::Timeout(_, bound, child) => {
+                let mut timeouts = child.get_timeouts();
+                timeouts.push(bound.samples());
+                timeouts
+            }
+            TbtNode::Kleene(_, _, kleene_next, child) => {
+                let mut timeouts = child.get_timeouts();
+                if let Some(kleene_next) = kleene_next {
+                    timeouts.extend(kleene_next.get_timeouts());
+                }
+                timeouts
+            }
+        }
+    }
+
+    /// Returns the name of a leaf node and None for all other nodes
+    pub fn get_name(&self) -> Option<&str> {
+        match self {
+            TbtNode::Leaf(_, _, name) => Some(name),
+            _ => None,
+        }
+    }
+
     /// Recursive call until first leaf node it found
     fn get_leaf(&self, leaf_index: usize) -> Option<&TbtNode> {
         match self {
@@ -166,45 +331,51 @@ impl TbtNode {
             }
         }
     }
+}
 
-    /****************
-     * Constructors
-     ****************/
+/*******************************
+ * TbtNode Constructors
+ *******************************/
+#[allow(dead_code)]
+impl TbtBuilder {
+    pub fn leaf(&self, formula: Stl, name: String) -> TbtNode {
+        TbtNode::Leaf(self.gnc(), formula, name)
+    }
 
-    pub fn leaf(formula: Stl, name: String) -> Self {
-        TbtNode::Leaf(gnc(), formula, name)
+    pub fn fallback(&self, formulas: Vec<TbtNode>) -> TbtNode {
+        TbtNode::Fallback(self.gnc(), formulas)
     }
 
-    pub fn fallback(formulas: Vec<TbtNode>) -> Self {
-        TbtNode::Fallback(gnc(), formulas)
+    pub fn parallel(&self, m: usize, formulas: Vec<TbtNode>) -> TbtNode {
+        TbtNode::Parallel(self.gnc(), m, formulas)
     }
 
-    pub fn parallel(m: usize, formulas: Vec<TbtNode>) -> Self {
-        TbtNode::Parallel(gnc(), m, formulas)
+    pub fn sequence(&self, left_child: TbtNode, right_child: TbtNode) -> TbtNode {
+        TbtNode::Sequence(self.gnc(), Box::new(left_child), Box::new(right_child))
     }
 
-    pub fn sequence(left_child: TbtNode, right_child: TbtNode) -> Self {
-        TbtNode::Sequence(gnc(), Box::new(left_child), Box::new(right_child))
+    pub fn timeout(&self, t: usize, child: TbtNode) -> TbtNode {
+        self.timeout_bound(Bound::Samples(t), child)
     }
 
-    pub fn timeout(t: usize, child: TbtNode) -> Self {
-        TbtNode::Timeout(gnc(), t, Box::new(child))
+    pub fn timeout_bound(&self, t: Bound, child: TbtNode) -> TbtNode {
+        TbtNode::Timeout(self.gnc(), t, Box::new(child))
     }
 
-    pub fn kleene(n: usize, child: TbtNode) -> Self {
-        let index = gnc();
+    pub fn kleene(&self, n: usize, child: TbtNode) -> TbtNode {
+        let index = self.gnc();
         let kleene_next = if n > 0 {
-            Some(Box::new(TbtNode::kleene(n - 1, child.clone())))
+            Some(Box::new(self.kleene(n - 1, child.clone())))
         } else {
             None
         };
         TbtNode::Kleene(index, n, kleene_next, Box::new(child))
     }
 
-    pub fn kleene_inf(child: TbtNode, trace_length: usize) -> Self {
+    pub fn kleene_inf(&self, child: TbtNode, trace_length: usize) -> TbtNode {
         let n = usize::max(1, trace_length);
         let mut formulas = vec![];
-        let mut kleene = TbtNode::kleene(n, child);
+        let mut kleene = self.kleene(n, child);
         formulas.push(kleene.clone());
         while let TbtNode::Kleene(_, n, Some(kleene_minus_one), _) = kleene {
             if n == 1 {
@@ -214,7 +385,7 @@ impl TbtNode {
             formulas.push(kleene.clone());
         }
         assert_eq!(n, formulas.len());
-        TbtNode::parallel(1, formulas)
+        self.parallel(1, formulas)
     }
 }
 
@@ -348,6 +519,91 @@ impl TbtNode {
         }
     }
 
+    /// Sets the robustness of atomic propositions for unknown samples in all leaves, see Stl::set_unknown_robustness()
+    /// # Arguments
+    /// * `signal` - Signal that is not 0 for unknown samples
+    /// * `robustness` - Robustness of an atomic proposition for an unknown sample
+    pub fn set_unknown_robustness(&mut self, signal: &str, robustness: f32) {
+        match self {
+            TbtNode::Leaf(_, formula, _) => formula.set_unknown_robustness(signal, robustness),
+            TbtNode::Fallback(_, children) | TbtNode::Parallel(_, _, children) => {
+                for child in children {
+                    child.set_unknown_robustness(signal, robustness);
+                }
+            }
+            TbtNode::Sequence(_, l_child, r_child) => {
+                l_child.set_unknown_robustness(signal, robustness);
+                r_child.set_unknown_robustness(signal, robustness);
+            }
+            TbtNode::Timeout(_, _, child) => child.set_unknown_robustness(signal, robustness),
+            TbtNode::Kleene(_, _, kleene_next, child) => {
+                if let Some(kleene_next) = kleene_next {
+                    kleene_next.set_unknown_robustness(signal, robustness);
+                }
+                child.set_unknown_robustness(signal, robustness);
+            }
+        }
+    }
+
+    /// Sets the horizons of the formulas of all leaves, see Stl::set_horizons()
+    /// # Arguments
+    /// * `horizons` - Horizons indexed by subformula
+    pub fn set_formula_horizons(&self, horizons: &mut [Option<usize>]) {
+        match self {
+            TbtNode::Leaf(_, formula, _) => {
+                formula.set_horizons(horizons);
+            }
+            TbtNode::Fallback(_, children) | TbtNode::Parallel(_, _, children) => {
+                for child in children {
+                    child.set_formula_horizons(horizons);
+                }
+            }
+            TbtNode::Sequence(_, l_child, r_child) => {
+                l_child.set_formula_horizons(horizons);
+                r_child.set_formula_horizons(horizons);
+            }
+            TbtNode::Timeout(_, _, child) => child.set_formula_horizons(horizons),
+            TbtNode::Kleene(_, _, kleene_next, child) => {
+                if let Some(kleene_next) = kleene_next {
+                    kleene_next.set_formula_horizons(horizons);
+                }
+                child.set_formula_horizons(horizons);
+            }
+        }
+    }
+
+    /// Converts all bounds in seconds of timeouts and STL formulas into samples
+    /// # Arguments
+    /// * `sampling_period` - Time between two samples in seconds, None if the trace has no time axis
+    pub fn resolve_bounds(&mut self, sampling_period: Option<f32>) -> Result<(), String> {
+        match self {
+            TbtNode::Leaf(_, formula, _) => formula.resolve_bounds(sampling_period),
+            TbtNode::Fallback(_, children) | TbtNode::Parallel(_, _, children) => {
+                for child in children {
+                    child.resolve_bounds(sampling_period)?;
+                }
+                Ok(())
+            }
+            TbtNode::Sequence(_, l_child, r_child) => {
+                l_child.resolve_bounds(sampling_period)?;
+                r_child.resolve_bounds(sampling_period)
+            }
+            TbtNode::Timeout(index, t, child) => {
+                *t = t.resolve(sampling_period)?;
+                if t.samples() == 0 {
+                    return Err(format!("Timeout({index}) requires t > 0"));
+                }
+                child.resolve_bounds(sampling_period)
+            }
+            TbtNode::Kleene(_, _, kleene_next, child) => {
+                if let Some(kleene_next) = kleene_next {
+                    kleene_next.resolve_bounds(sampling_period)?;
+                }
+                child.resolve_bounds(sampling_period)
+            }
+        }
+    }
+
     /***********************
      * Standard Evaluation
      ***********************/
@@ -362,18 +618,22 @@ impl TbtNode {
     /// * `system_time` - Used for profiling
     /// * `debug` - Enables debugging messages  
     /// * `lazy_eval` - Enables / disables lazy evaluation
+    /// * `semantics` - Defines how robustness values are combined
+    /// * `parallel` - Evaluates subtrees and splits in parallel using the current thread pool
     #[allow(clippy::too_many_arguments)]
     pub fn evaluate(
         &self,
         depth_manager_tree: &mut HashMap<usize, (usize, usize, f32)>,
-        tree_table: &mut Table,
-        formula_table: &mut Table,
+        tree_table: &Table,
+        formula_table: &Table,
         trace: &Trace,
         lower: usize,
         upper: usize,
         system_time: &SystemTime,
         debug: bool,
         lazy_eval: bool,
+        semantics: Semantics,
+        parallel: bool,
     ) -> f32 {
         // Display progress
         if debug {
@@ -398,17 +658,14 @@ impl TbtNode {
         } else {
             let (v, index) = match self {
                 TbtNode::Leaf(index, formula, _) => {
-                    let v = formula.evaluate(formula_table, trace, lower, upper, lazy_eval);
+                    let v =
+                        formula.evaluate(formula_table, trace, lower, upper, lazy_eval, semantics);
                     (v, *index)
                 }
-                TbtNode::Fallback(index, subtrees) => {
-                    let (l, u, mut v) = if lazy_eval {
-                        match depth_manager_tree.get(index) {
-                            Some((last_l, last_u, last_v)) => (*last_l, *last_u, *last_v),
-                            None => (lower, upper, f32::NEG_INFINITY),
-                        }
-                    } else {
-                        (lower, upper, f32::NEG_INFINITY)
+                TbtNode::Fallback(index, subtrees) if lazy_eval => {
+                    let (l, u, mut v) = match depth_manager_tree.get(index) {
+                        Some((last_l, last_u, last_v)) => (*last_l, *last_u, *last_v),
+                        None => (lower, upper, f32::NEG_INFINITY),
                     };
                     for i in l..(u + 1) {
                         for subtree in subtrees {
@@ -422,9 +679,11 @@ impl TbtNode {
                                 system_time,
                                 debug,
                                 lazy_eval,
+                                semantics,
+                                parallel,
                             );
-                            v = f32::max(s_v, v);
-                            if lazy_eval && v > 0.0 {
+                            v = semantics.max(v, s_v);
+                            if v > 0.0 {
                                 depth_manager_tree.insert(*index, (i + 1, u, v));
                                 break;
                             }
@@ -432,33 +691,100 @@ impl TbtNode {
                     }
                     (v, *index)
                 }
-                TbtNode::Parallel(index, m, subtrees) => {
-                    let mut v_vec = vec![];
-                    for subtree in subtrees {
-                        v_vec.push(subtree.evaluate(
-                            depth_manager_tree,
+                TbtNode::Fallback(index, subtrees) => {
+                    // Each subtree is evaluated for each segment start independently
+                    let count = (upper + 1).saturating_sub(lower) * subtrees.len();
+                    let v = evaluate_disjunction(count, semantics, parallel, |k| {
+                        subtrees[k % subtrees.len()].evaluate(
+                            &mut HashMap::new(),
                             tree_table,
                             formula_table,
                             trace,
-                            lower,
+                            lower + k / subtrees.len(),
                             upper,
                             system_time,
                             debug,
                             lazy_eval,
-                        ));
-                    }
-                    v_vec.sort_by(|a, b| b.partial_cmp(a).unwrap());
-                    let mth_v_value = v_vec[m - 1];
-                    (mth_v_value, *index)
+                            semantics,
+                            parallel,
+                        )
+                    });
+                    (v, *index)
                 }
-                TbtNode::Sequence(index, left_child, right_child) => {
-                    let (l, u, mut v) = if lazy_eval {
-                        match depth_manager_tree.get(index) {
-                            Some((last_l, last_u, last_v)) => (*last_l, *last_u, *last_v),
-                            None => (lower, upper, f32::NEG_INFINITY),
+                TbtNode::Parallel(index, m, subtrees) => {
+                    let mut v_vec = if lazy_eval {
+                        let mut v_vec = vec![];
+                        for subtree in subtrees {
+                            v_vec.push(subtree.evaluate(
+                                depth_manager_tree,
+                                tree_table,
+                                formula_table,
+                                trace,
+                                lower,
+                                upper,
+                                system_time,
+                                debug,
+                                lazy_eval,
+                                semantics,
+                                parallel,
+                            ));
                         }
+                        v_vec
                     } else {
-                        (lower, upper, f32::NEG_INFINITY)
+                        evaluate_all(subtrees.len(), parallel, |k| {
+                            subtrees[k].evaluate(
+                                &mut HashMap::new(),
+                                tree_table,
+                                formula_table,
+                                trace,
+                                lower,
+                                upper,
+                                system_time,
+                                debug,
+                                lazy_eval,
+                                semantics,
+                                parallel,
+                            )
+                        })
+                    };
+                    // Conjunction of the m best subtrees, i.e., the m-th best value for classic semantics
+                    v_vec.sort_by(|a, b| b.partial_cmp(a).unwrap());
+                    let mut mth_v_value = semantics.conjunction();
+                    for v in v_vec.iter().take(*m) {
+                        mth_v_value.add(*v);
+                    }
+                    (mth_v_value.value(), *index)
+                }
+                TbtNode::Sequence(index, left_child, right_child) if !lazy_eval => {
+                    let v = evaluate_splits(
+                        left_child,
+                        right_child,
+                        lower,
+                        upper,
+                        semantics,
+                        parallel,
+                        |child, lower, upper| {
+                            child.evaluate(
+                                &mut HashMap::new(),
+                                tree_table,
+                                formula_table,
+                                trace,
+                                lower,
+                                upper,
+                                system_time,
+                                debug,
+                                lazy_eval,
+                                semantics,
+                                parallel,
+                            )
+                        },
+                    );
+                    (v, *index)
+                }
+                TbtNode::Sequence(index, left_child, right_child) => {
+                    let (l, u, mut v) = match depth_manager_tree.get(index) {
+                        Some((last_l, last_u, last_v)) => (*last_l, *last_u, *last_v),
+                        None => (lower, upper, f32::NEG_INFINITY),
                     };
                     for i in l..(u + 1) {
                         let t1_v = left_child.evaluate(
@@ -471,6 +797,8 @@ impl TbtNode {
                             system_time,
                             debug,
                             lazy_eval,
+                            semantics,
+                            parallel,
                         );
                         let t2_v = right_child.evaluate(
                             depth_manager_tree,
@@ -482,9 +810,11 @@ impl TbtNode {
                             system_time,
                             debug,
                             lazy_eval,
+                            semantics,
+                            parallel,
                         );
-                        v = f32::max(v, f32::min(t1_v, t2_v));
-                        if lazy_eval && v > 0.0 {
+                        v = semantics.max(v, semantics.min(t1_v, t2_v));
+                        if v > 0.0 {
                             depth_manager_tree.insert(*index, (i + 1, u, v));
                             break;
                         }
@@ -492,6 +822,7 @@ impl TbtNode {
                     (v, *index)
                 }
                 TbtNode::Timeout(index, t, subtree) => {
+                    let t = t.samples();
                     let v = subtree.evaluate(
                         depth_manager_tree,
                         tree_table,
@@ -502,6 +833,8 @@ impl TbtNode {
                         system_time,
                         debug,
                         lazy_eval,
+                        semantics,
+                        parallel,
                     );
                     (v, *index)
                 }
@@ -514,7 +847,33 @@ impl TbtNode {
                     } else {
                         (lower, upper, f32::NEG_INFINITY)
                     };
-                    if l <= u && *n > 0 {
+                    if l <= u && *n > 0 && !lazy_eval {
+                        let kleene_n_minus_1 = kleene_next.as_ref().unwrap();
+                        let v = evaluate_splits(
+                            subtree,
+                            kleene_n_minus_1,
+                            lower,
+                            upper,
+                            semantics,
+                            parallel,
+                            |child, lower, upper| {
+                                child.evaluate(
+                                    &mut HashMap::new(),
+                                    tree_table,
+                                    formula_table,
+                                    trace,
+                                    lower,
+                                    upper,
+                                    system_time,
+                                    debug,
+                                    lazy_eval,
+                                    semantics,
+                                    parallel,
+                                )
+                            },
+                        );
+                        (v, *index)
+                    } else if l <= u && *n > 0 {
                         let kleene_n_minus_1 = kleene_next.as_ref().unwrap();
                         // Copy of Sequence start!
                         for i in l..(u + 1) {
@@ -528,6 +887,8 @@ impl TbtNode {
                                 system_time,
                                 debug,
                                 lazy_eval,
+                                semantics,
+                                parallel,
                             );
                             let t2_v = kleene_n_minus_1.evaluate(
                                 depth_manager_tree,
@@ -539,8 +900,10 @@ impl TbtNode {
                                 system_time,
                                 debug,
                                 lazy_eval,
+                                semantics,
+                                parallel,
                             );
-                            v = f32::max(v, f32::min(t1_v, t2_v));
+                            v = semantics.max(v, semantics.min(t1_v, t2_v));
                             if lazy_eval && v > 0.0 {
                                 depth_manager_tree.insert(*index, (i + 1, u, v));
                                 break;
@@ -559,6 +922,8 @@ impl TbtNode {
                             system_time,
                             debug,
                             lazy_eval,
+                            semantics,
+                            parallel,
                         );
                         (v, *index)
                     } else {
@@ -585,23 +950,28 @@ impl TbtNode {
     /// * `lower` - Segment start
     /// * `upper` - Segment end
     /// * `is_lazy` - Enables / disables lazy evaluation
+    /// * `semantics` - Defines how robustness values are combined
+    #[allow(clippy::too_many_arguments)]
     pub fn get_segmentation(
         &self,
-        tree_table: &mut Table,
-        formula_table: &mut Table,
+        tree_table: &Table,
+        formula_table: &Table,
         trace: &Trace,
         lower: usize,
         upper: usize,
         is_lazy: bool,
+        semantics: Semantics,
     ) -> Segmentation {
         match self {
             TbtNode::Leaf(index, formula, _) => {
                 let v = if lower > upper {
-                    formula.evaluate(formula_table, trace, lower, upper, is_lazy)
+                    formula.evaluate(formula_table, trace, lower, upper, is_lazy, semantics)
                 } else {
                     match tree_table.lookup(*index, lower, upper) {
                         Some(v) => v,
-                        None => formula.evaluate(formula_table, trace, lower, upper, is_lazy),
+                        None => {
+                            formula.evaluate(formula_table, trace, lower, upper, is_lazy, semantics)
+                        }
                     }
                 };
                 vec![(self, lower, upper, v)]
@@ -629,13 +999,15 @@ impl TbtNode {
                     }
                 }
                 let mut self_segmentation = vec![(self, lower, upper, v)];
-                let mut child_segmentation = st.unwrap().get_segmentation(
+                // The first subtree is used if no subtree is satisfiable or the segment is empty, e.g., for short prefixes
+                let mut child_segmentation = st.unwrap_or(&subtrees[0]).get_segmentation(
                     tree_table,
                     formula_table,
                     trace,
                     begin,
                     end,
                     is_lazy,
+                    semantics,
                 );
                 self_segmentation.append(&mut child_segmentation);
                 self_segmentation
@@ -656,8 +1028,11 @@ impl TbtNode {
                     v_vec.push((s_v, subtree));
                 }
                 v_vec.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap());
-                let (mth_v_value, _) = v_vec[m - 1];
-                let mut self_segmentation = vec![(self, lower, upper, mth_v_value)];
+                let mut mth_v_value = semantics.conjunction();
+                for (v, _) in v_vec.iter().take(*m) {
+                    mth_v_value.add(*v);
+                }
+                let mut self_segmentation = vec![(self, lower, upper, mth_v_value.value())];
                 for (_, st) in v_vec.iter().take(*m) {
                     let mut child_segmentation = st.get_segmentation(
                         tree_table,
@@ -666,6 +1041,7 @@ impl TbtNode {
                         lower,
                         upper,
                         is_lazy,
+                        semantics,
                     );
                     self_segmentation.append(&mut child_segmentation);
                 }
@@ -698,14 +1074,9 @@ impl TbtNode {
                             }
                         }
                     };
-                    if t1_v < t2_v {
-                        if t1_v > v {
-                            v = t1_v;
-                            begin = lower;
-                            change = u;
-                        }
-                    } else if t2_v > v {
-                        v = t2_v;
+                    let min_v = semantics.min(t1_v, t2_v);
+                    if min_v > v {
+                        v = min_v;
                         begin = lower;
                         change = u;
                     }
@@ -718,6 +1089,7 @@ impl TbtNode {
                     begin,
                     change,
                     is_lazy,
+                    semantics,
                 );
                 self_segmentation.append(&mut child_segmentation);
                 let mut child_segmentation = right_child.get_segmentation(
@@ -727,11 +1099,13 @@ impl TbtNode {
                     change + 1,
                     upper,
                     is_lazy,
+                    semantics,
                 );
                 self_segmentation.append(&mut child_segmentation);
                 self_segmentation
             }
             TbtNode::Timeout(_, t, child) => {
+                let t = t.samples();
                 let v = if lower > usize::min(upper, lower + t - 1) {
                     f32::NEG_INFINITY
                 } else {
@@ -747,6 +1121,7 @@ impl TbtNode {
                     lower,
                     usize::min(upper, lower + t - 1),
                     is_lazy,
+                    semantics,
                 );
                 self_segmentation.append(&mut child_segmentation);
                 self_segmentation
@@ -783,14 +1158,9 @@ impl TbtNode {
                                 }
                             }
                         };
-                        if t1_v < t2_v {
-                            if t1_v > v {
-                                v = t1_v;
-                                begin = lower;
-                                change = u;
-                            }
-                        } else if t2_v > v {
-                            v = t2_v;
+                        let min_v = semantics.min(t1_v, t2_v);
+                        if min_v > v {
+                            v = min_v;
                             begin = lower;
                             change = u;
                         }
@@ -803,6 +1173,7 @@ impl TbtNode {
                         begin,
                         change,
                         is_lazy,
+                        semantics,
                     );
                     self_segmentation.append(&mut child_segmentation);
                     let mut child_segmentation = right_child.get_segmentation(
@@ -812,6 +1183,7 @@ impl TbtNode {
                         change + 1,
                         upper,
                         is_lazy,
+                        semantics,
                     );
                     self_segmentation.append(&mut child_segmentation);
                     self_segmentation
@@ -828,6 +1200,7 @@ impl TbtNode {
                         lower,
                         upper,
                         is_lazy,
+                        semantics,
                     ));
                     self_segmentation
                 } else {
@@ -896,25 +1269,29 @@ impl TbtNode {
     /// * `tau_dif` - Used to filter out alternatives based on where segments start and end
     /// * `rho_dif` - Used to filter out alternatives based on their difference in robustness
     /// * `segmentations` - Previously identified alternative segmentations
+    /// * `semantics` - Defines how robustness values are combined
     fn get_segmentation_under_restriction(
         &self,
-        tree_table: &mut Table,
-        formula_table: &mut Table,
+        tree_table: &Table,
+        formula_table: &Table,
         trace: &Trace,
         lower: usize,
         upper: usize,
         tau_dif: usize,
         rho_dif: f32,
         segmentations: &Vec<Segmentation>,
+        semantics: Semantics,
     ) -> (usize, Segmentation) {
         match self {
             TbtNode::Leaf(index, formula, _) => {
                 let v = if lower > upper {
-                    formula.evaluate(formula_table, trace, lower, upper, false)
+                    formula.evaluate(formula_table, trace, lower, upper, false, semantics)
                 } else {
                     match tree_table.lookup(*index, lower, upper) {
                         Some(v) => v,
-                        None => formula.evaluate(formula_table, trace, lower, upper, false),
+                        None => {
+                            formula.evaluate(formula_table, trace, lower, upper, false, semantics)
+                        }
                     }
                 };
                 match self.get_tau_dif(lower, upper, segmentations) {
@@ -948,6 +1325,7 @@ impl TbtNode {
                         tau_dif,
                         rho_dif,
                         segmentations,
+                        semantics,
                     );
                     if candidate.0 > tau_dif {
                         let mut self_segmentation = vec![(self, lower, upper, v)];
@@ -974,8 +1352,11 @@ impl TbtNode {
                 }
                 candidates.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap());
                 candidates.retain(|&(rho, _)| rho > rho_dif);
-                let (mth_v_value, _) = candidates[m - 1];
-                let mut self_segmentation = vec![(self, lower, upper, mth_v_value)];
+                let mut mth_v_value = semantics.conjunction();
+                for (v, _) in candidates.iter().take(*m) {
+                    mth_v_value.add(*v);
+                }
+                let mut self_segmentation = vec![(self, lower, upper, mth_v_value.value())];
                 let mut sum_tau_dif = 0;
                 for (_, st) in candidates.iter().take(*m) {
                     let mut child_segmentation = st.get_segmentation_under_restriction(
@@ -987,6 +1368,7 @@ impl TbtNode {
                         tau_dif,
                         rho_dif,
                         segmentations,
+                        semantics,
                     );
                     sum_tau_dif += child_segmentation.0;
                     self_segmentation.append(&mut child_segmentation.1);
@@ -1009,7 +1391,7 @@ impl TbtNode {
                         tree_table.lookup_segmentation_tree(right_child, u + 1, upper)
                     };
                     if let Some(t2_v) = t2_v {
-                        let min_v = f32::min(t1_v, t2_v);
+                        let min_v = semantics.min(t1_v, t2_v);
                         candidates.push((
                             min_v,
                             (t1_v, lower, u, left_child),
@@ -1037,6 +1419,7 @@ impl TbtNode {
                             tau_dif,
                             rho_dif,
                             segmentations,
+                            semantics,
                         );
                     let mut right_child_segmentation =
                         right_child.3.get_segmentation_under_restriction(
@@ -1048,6 +1431,7 @@ impl TbtNode {
                             tau_dif,
                             rho_dif,
                             segmentations,
+                            semantics,
                         );
                     let sum_tau_dif = match left_child_segmentation
                         .0
@@ -1076,6 +1460,7 @@ impl TbtNode {
                 (best_segmentation.0, self_segmentation)
             }
             TbtNode::Timeout(_, t, child) => {
+                let t = t.samples();
                 let v = tree_table
                     .lookup_segmentation_tree(child, lower, upper)
                     .unwrap();
@@ -1089,6 +1474,7 @@ impl TbtNode {
                     tau_dif,
                     rho_dif,
                     segmentations,
+                    semantics,
                 );
                 self_segmentation.append(&mut child_segmentation.1);
                 (child_segmentation.0, self_segmentation)
@@ -1114,7 +1500,7 @@ impl TbtNode {
                             tree_table.lookup_segmentation_tree(right_child, u + 1, upper)
                         };
                         if let Some(t2_v) = t2_v {
-                            let min_v = f32::min(t1_v, t2_v);
+                            let min_v = semantics.min(t1_v, t2_v);
                             candidates.push((
                                 min_v,
                                 (t1_v, lower, u, left_child),
@@ -1142,6 +1528,7 @@ impl TbtNode {
                                 tau_dif,
                                 rho_dif,
                                 segmentations,
+                                semantics,
                             );
                         let mut right_child_segmentation =
                             right_child.3.get_segmentation_under_restriction(
@@ -1153,6 +1540,7 @@ impl TbtNode {
                                 tau_dif,
                                 rho_dif,
                                 segmentations,
+                                semantics,
                             );
                         let sum_tau_dif = left_child_segmentation.0 + right_child_segmentation.0;
                         if sum_tau_dif > tau_dif {
@@ -1187,6 +1575,7 @@ impl TbtNode {
                         tau_dif,
                         rho_dif,
                         segmentations,
+                        semantics,
                     );
                     let mut self_segmentation = vec![(self, lower, upper, v)];
                     self_segmentation.append(&mut child_segmentation.1);
@@ -1210,11 +1599,11 @@ impl TbtNode {
     /// * `tau_dif` - Used to filter out alternatives based on where segments start and end
     /// * `rho_dif` - Used to filter out alternatives based on their difference in robustness
     /// * `number` - Number of alternatives that shall be computed
-    /// * `print_leaf_segments_only` - Defines whether only leaves or also other nodes are printed
+    /// * `semantics` - Defines how robustness values are combined
     pub fn get_alternative_segmentation(
         &self,
-        tree_table: &mut Table,
-        formula_table: &mut Table,
+        tree_table: &Table,
+        formula_table: &Table,
         trace: &Trace,
         lower: usize,
         upper: usize,
@@ -1222,13 +1611,11 @@ impl TbtNode {
         tau_dif: usize,
         rho_dif: f32,
         number: usize,
-        print_leaf_segments_only: bool,
+        semantics: Semantics,
     ) -> Vec<(usize, Segmentation)> {
-        println!("\n\nAlternatives:");
         let mut res_segmentation = Vec::new();
         let mut segmentations = vec![best_segmentation.to_vec()];
-        for i in 0..number {
-            println!("Got {}/{} alternative segmentations.", i, number);
+        for _ in 0..number {
             let segmentation = self.get_segmentation_under_restriction(
                 tree_table,
                 formula_table,
@@ -1238,10 +1625,8 @@ impl TbtNode {
                 tau_dif,
                 rho_dif,
                 &segmentations,
+                semantics,
             );
-            let (robustness_value, segmentation_str) =
-                print_segmentation(&segmentation.1, print_leaf_segments_only, false);
-            println!("Segmentation with remaining tau difference of {} and robustness of {robustness_value} is:\n{segmentation_str}", segmentation.0);
             segmentations.push(segmentation.1.to_vec());
             res_segmentation.push(segmentation);
         }
@@ -1249,11 +1634,79 @@ impl TbtNode {
     }
 }
 
+/*******************************
+ * Parallel evaluation
+ *******************************/
+/// Evaluates f for 0..count and returns the values in order such that they are combined as in a
+/// sequential evaluation. If parallel, the values are computed by the current thread pool, see evaluate().
+fn evaluate_all<F>(count: usize, parallel: bool, f: F) -> Vec<f32>
+where
+    F: Fn(usize) -> f32 + Send + Sync,
+{
+    if parallel {
+        (0..count).into_par_iter().map(f).collect()
+    } else {
+        (0..count).map(f).collect()
+    }
+}
+
+/// Returns the disjunction of f(0), ..., f(count - 1), see evaluate_all()
+fn evaluate_disjunction<F>(count: usize, semantics: Semantics, parallel: bool, f: F) -> f32
+where
+    F: Fn(usize) -> f32 + Send + Sync,
+{
+    let disjunction = |v, s_v| semantics.max(v, s_v);
+    if parallel {
+        evaluate_all(count, parallel, f)
+            .into_iter()
+            .fold(f32::NEG_INFINITY, disjunction)
+    } else {
+        // Avoids allocating the values for each evaluated segment
+        (0..count).map(f).fold(f32::NEG_INFINITY, disjunction)
+    }
+}
+
+/// Returns the disjunction over all splits of the segment of the conjunction of the left child
+/// up to the split and the right child after it, i.e., the robustness of a Sequence
+/// # Arguments
+/// * `left_child` - Child that is evaluated from lower to the split
+/// * `right_child` - Child that is evaluated after the split to upper
+/// * `lower` - Segment start
+/// * `upper` - Segment end
+/// * `semantics` - Defines how robustness values are combined
+/// * `parallel` - Evaluates the splits in parallel, see evaluate_all()
+/// * `evaluate` - Evaluates a child for a segment
+fn evaluate_splits<F>(
+    left_child: &TbtNode,
+    right_child: &TbtNode,
+    lower: usize,
+    upper: usize,
+    semantics: Semantics,
+    parallel: bool,
+    evaluate: F,
+) -> f32
+where
+    F: Fn(&TbtNode, usize, usize) -> f32 + Send + Sync,
+{
+    evaluate_disjunction(
+        (upper + 1).saturating_sub(lower),
+        semantics,
+        parallel,
+        |k| {
+            let i = lower + k;
+            semantics.min(
+                evaluate(left_child, lower, i),
+                evaluate(right_child, i + 1, upper),
+            )
+        },
+    )
+}
+
 /*******************************
  * Progress and print functions
  *******************************/
 /// Debugging progress bar
-fn progress(tree_table: &mut Table, formula_table: &mut Table, system_time: &SystemTime) {
+fn progress(tree_table: &Table, formula_table: &Table, system_time: &SystemTime) {
     let (tree_set_calls, tree_total) = tree_table.progress();
     let (formula_set_calls, formula_total) = formula_table.progress();
     if tree_set_calls % 10000 == 0 || formula_set_calls % 10000 == 0 {
@@ -1272,35 +1725,32 @@ fn progress(tree_table: &mut Table, formula_table: &mut Table, system_time: &Sys
     }
 }
 
-/// Given a Segmentation is prints the result
-pub fn print_segmentation(
+/// Given a Segmentation it returns its robustness, i.e., the conjunction (the minimum for classic semantics) of all segments
+/// # Arguments
+/// * `segmentation` - Segmentation that is analyzed
+/// * `only_leaves` - If true, only segments of leaves are considered
+/// * `is_lazy` - If true, negative robustness values are approximated by -inf
+/// * `semantics` - Defines how the values of the segments are combined
+pub fn get_segmentation_robustness(
     segmentation: &Segmentation,
     only_leaves: bool,
     is_lazy: bool,
-) -> (f32, String) {
-    let mut robustness = f32::INFINITY;
-    let mut seg_string = String::new();
-    for (tbt_node, lower, upper, value) in segmentation {
+    semantics: Semantics,
+) -> f32 {
+    let mut robustness = semantics.conjunction();
+    for (tbt_node, _, _, value) in segmentation {
         if only_leaves {
             match tbt_node {
                 TbtNode::Leaf(_, _, _) => (),
                 _ => continue,
             }
         }
-        seg_string += &format!(
-            "lower: {:10}   upper: {:10}   value: {:15}  segment: {}\n",
-            lower,
-            upper,
-            value,
-            tbt_node.pretty_print(false, 0),
-        );
-        if value < &robustness {
-            robustness = *value;
-        }
+        robustness.add(*value);
     }
+    let robustness = robustness.value();
     if is_lazy && robustness < 0.0 {
-        (f32::NEG_INFINITY, seg_string)
+        f32::NEG_INFINITY
     } else {
-        (robustness, seg_string)
+        robustness
     }
 }
@@ -1,13 +1,38 @@
 // SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::{
+    batch::BatchSetting,
+    reporter::OutputFormat,
+    semantics::Semantics,
+    table::TableBackend,
+    trace::{read_mapping, TraceSetting},
+};
+
+/// Exit code if the robustness is below the threshold of the check command
+pub const EXIT_FAIL: i32 = 1;
+
+/// Exit code of errors, e.g., invalid arguments or a logfile that cannot be read, which differs from EXIT_FAIL
+pub const EXIT_ERROR: i32 = 2;
+
 pub struct CommandLineArguments {
     pub logfile: String,
+    pub specification: Option<String>,
     pub lazy_evaluation: bool,
+    pub semantics: Semantics,
+    pub table_backend: TableBackend,
+    pub memory_limit: Option<usize>,
+    pub threads: usize,
     pub sub_sampling: bool,
     pub debug_console: bool,
     pub print_leaf_segments_only: bool,
     pub segmentation_setting: Option<SegmentationSetting>,
+    pub output_format: OutputFormat,
+    pub output: Option<String>,
+    pub trace_setting: TraceSetting,
+    pub batch: Option<BatchSetting>,
+    pub check: Option<CheckSetting>,
+    pub monitor: Option<MonitorSetting>,
 }
 
 pub struct SegmentationSetting {
@@ -16,26 +41,78 @@ pub struct SegmentationSetting {
     pub amount: usize,
 }
 
+/// Defines the verdict of the check command
+/// # Arguments
+/// * `threshold` - The check fails if the robustness is below this value
+/// * `verdict_only` - If true, only PASS or FAIL is printed
+/// * `junit` - Location of a JUnit XML report where each leaf segment is a test case
+pub struct CheckSetting {
+    pub threshold: f32,
+    pub verdict_only: bool,
+    pub junit: Option<String>,
+}
+
+/// Defines the samples of the monitor command
+/// # Arguments
+/// * `source` - csv-file (or - for stdin) with one column per signal, the logfile is replayed if None
+/// * `lookahead` - Maximal number of unknown samples for the worst and best case, None uses the horizon of the TBT
+pub struct MonitorSetting {
+    pub source: Option<String>,
+    pub lookahead: Option<usize>,
+}
+
 impl CommandLineArguments {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         logfile: String,
+        specification: Option<String>,
         lazy_evaluation: bool,
+        semantics: Semantics,
+        table_backend: TableBackend,
+        memory_limit: Option<usize>,
+        threads: usize,
         sub_sampling: bool,
         debug_console: bool,
         print_leaf_segments_only: bool,
         segmentation_setting: Option<SegmentationSetting>,
+        output_format: OutputFormat,
+        output: Option<String>,
+        trace_setting: TraceSetting,
+        batch: Option<BatchSetting>,
+        check: Option<CheckSetting>,
+        monitor: Option<MonitorSetting>,
     ) -> CommandLineArguments {
         CommandLineArguments {
             logfile,
+            specification,
             lazy_evaluation,
+            semantics,
+            table_backend,
+            memory_limit,
+            threads,
             sub_sampling,
             debug_console,
             print_leaf_segments_only,
             segmentation_setting,
+            output_format,
+            output,
+            trace_setting,
+            batch,
+            check,
+            monitor,
         }
     }
 }
 
+/// Prints the error and exits with EXIT_ERROR, the help and version are printed and exit successfully
+fn exit_with_error(error: clap::Error) -> ! {
+    if error.use_stderr() {
+        eprintln!("{}", error.message);
+        std::process::exit(EXIT_ERROR);
+    }
+    error.exit()
+}
+
 pub fn parse_command_line() -> CommandLineArguments {
     // Basic app information
     let app = clap::App::new("Temporal Behavior Trees")
@@ -43,21 +120,76 @@ pub fn parse_command_line() -> CommandLineArguments {
         .about("Computes robustness and segmentation.")
         .author(clap::crate_authors!("\n"));
     // Define the name command line option
+    // Required unless the batch command is used, see below
     let logfile = clap::Arg::with_name("logfile")
-        .required(true)
+        .required(false)
+        .global(true)
         .short("f")
         .long("logfile")
         .takes_value(true)
         .value_name("FILE")
-        .help("Get logfile location");
+        .help("Get logfile location, - reads a csv-file or JSON Lines from stdin");
+    let specification = clap::Arg::with_name("specification")
+        .required(false)
+        .global(true)
+        .short("p")
+        .long("spec")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Reads the TBT from a specification file instead of using the built-in tree");
     let lazy_evaluation = clap::Arg::with_name("lazy_evaluation")
         .required(false)
+        .global(true)
         .short("l")
         .long("lazy")
         .takes_value(false)
         .help("Activates lazy evaluation");
+    let semantics = clap::Arg::with_name("semantics")
+        .required(false)
+        .global(true)
+        .long("semantics")
+        .takes_value(true)
+        .default_value("classic")
+        .value_name("SEMANTICS")
+        .validator(|s| s.parse::<Semantics>().map(|_| ()))
+        .help("Specifies the robustness semantics: classic, boolean, averaged, smooth, or smooth:TEMPERATURE");
+    let table_backend = clap::Arg::with_name("table_backend")
+        .required(false)
+        .global(true)
+        .long("table")
+        .takes_value(true)
+        .possible_values(&["dense", "lazy", "sparse"])
+        .default_value("dense")
+        .value_name("BACKEND")
+        .help("Specifies how memoized robustness values are stored, lazy and sparse tables need less memory");
+    let memory_limit = clap::Arg::with_name("memory_limit")
+        .required(false)
+        .global(true)
+        .long("memory-limit")
+        .takes_value(true)
+        .value_name("MB")
+        .validator(|s| match s.parse::<f64>() {
+            Ok(limit) if limit > 0.0 => Ok(()),
+            _ => Err(format!(
+                "memory limit must be a positive number but was '{s}'"
+            )),
+        })
+        .help("Aborts if the tables initially need more memory than the given megabytes");
+    let threads = clap::Arg::with_name("threads")
+        .required(false)
+        .global(true)
+        .short("j")
+        .long("threads")
+        .takes_value(true)
+        .default_value("1")
+        .value_name("UINT")
+        .validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+        .help(
+            "Specifies the number of threads that evaluate subtrees in parallel, 0 uses all cores",
+        );
     let sub_sampling = clap::Arg::with_name("sub_sampling")
         .required(false)
+        .global(true)
         .short("s")
         .long("sampling")
         .takes_value(false)
@@ -94,27 +226,261 @@ pub fn parse_command_line() -> CommandLineArguments {
         .help("Specifies number of alternative segmentations");
     let children = clap::Arg::with_name("children")
         .required(false)
+        .global(true)
         .short("c")
         .long("children")
         .help("Specifies whether only leaf nodes of a segmentation shall be printed");
+    let output_format = clap::Arg::with_name("output_format")
+        .required(false)
+        .global(true)
+        .long("output-format")
+        .takes_value(true)
+        .possible_values(&["text", "json", "csv"])
+        .default_value("text")
+        .value_name("FORMAT")
+        .help("Specifies the format of the segmentations");
+    let output = clap::Arg::with_name("output")
+        .required(false)
+        .global(true)
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Writes the segmentations to a file instead of stdout");
+    let bad_values = clap::Arg::with_name("bad_values")
+        .required(false)
+        .global(true)
+        .long("bad-values")
+        .takes_value(true)
+        .possible_values(&["fail", "skip", "fill", "nan"])
+        .default_value("fail")
+        .value_name("POLICY")
+        .help("Specifies how values of the logfile that are not numbers are handled");
+    let interpolation = clap::Arg::with_name("interpolation")
+        .required(false)
+        .global(true)
+        .long("interpolation")
+        .takes_value(true)
+        .possible_values(&["zoh", "linear"])
+        .default_value("zoh")
+        .value_name("METHOD")
+        .help("Specifies how logfiles with different rates are aligned");
+    let rate = clap::Arg::with_name("rate")
+        .required(false)
+        .global(true)
+        .long("rate")
+        .takes_value(true)
+        .value_name("HZ")
+        .help("Specifies the rate of the common time base, by default the rate of the fastest logfile is used");
+    let columns = clap::Arg::with_name("columns")
+        .required(false)
+        .global(true)
+        .long("columns")
+        .takes_value(true)
+        .use_delimiter(true)
+        .value_name("NAMES")
+        .help("Specifies the columns of a Parquet or Arrow logfile or the fields of a ULog file (e.g., vehicle_local_position.x) or a MAVLink telemetry log (e.g., ATTITUDE.roll) that are read, SIGNAL=NAME renames them, by default all numeric columns are read");
+    let time_column = clap::Arg::with_name("time_column")
+        .required(false)
+        .global(true)
+        .long("time-column")
+        .takes_value(true)
+        .value_name("NAME")
+        .help("Specifies the column of a Parquet or Arrow logfile that is used as time axis, by default a numeric column time");
+    let mapping = clap::Arg::with_name("mapping")
+        .required(false)
+        .global(true)
+        .long("mapping")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Specifies a file with one SIGNAL=NAME per line that is added to the columns, e.g., x=LOCAL_POSITION_NED.x for a MAVLink telemetry log");
+    // Evaluates several logs, the options above marked as global also apply to each log
+    let batch = clap::SubCommand::with_name("batch")
+        .about("Evaluates each log folder and prints a summary with one row per log.")
+        .arg(
+            clap::Arg::with_name("logs")
+                .required(true)
+                .multiple(true)
+                .value_name("DIR")
+                .help("Directories of log folders or glob patterns of log folders, e.g., 'res/logs_*'"),
+        )
+        .arg(
+            clap::Arg::with_name("jobs")
+                .required(false)
+                .long("jobs")
+                .takes_value(true)
+                .default_value("1")
+                .value_name("UINT")
+                .validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Specifies the number of logs that are evaluated in parallel, 0 uses all cores"),
+        );
+    // Evaluates a log and reports the verdict by the exit code
+    let check = clap::SubCommand::with_name("check")
+        .about("Evaluates the log and exits with 1 if the robustness is below the threshold.")
+        .arg(
+            clap::Arg::with_name("threshold")
+                .required(false)
+                .long("threshold")
+                .takes_value(true)
+                .default_value("0.0")
+                .value_name("RHO")
+                .allow_hyphen_values(true)
+                .validator(|s| s.parse::<f32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Specifies the minimal robustness that passes the check"),
+        )
+        .arg(
+            clap::Arg::with_name("verdict_only")
+                .required(false)
+                .short("q")
+                .long("verdict-only")
+                .takes_value(false)
+                .help("Prints only the verdict, i.e., PASS or FAIL"),
+        )
+        .arg(
+            clap::Arg::with_name("junit")
+                .required(false)
+                .long("junit")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Writes a JUnit XML report where each leaf segment is a test case"),
+        );
+    // Evaluates the samples one at a time
+    let monitor = clap::SubCommand::with_name("monitor")
+        .about("Monitors the TBT while samples arrive and prints the robustness interval and active leaf per sample.")
+        .arg(
+            clap::Arg::with_name("source")
+                .required(false)
+                .value_name("CSV")
+                .help("Reads the samples from a csv-file with one column per signal or from stdin if -, replays the logfile by default"),
+        )
+        .arg(
+            clap::Arg::with_name("lookahead")
+                .required(false)
+                .long("lookahead")
+                .takes_value(true)
+                .value_name("UINT")
+                .validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Specifies the number of unknown samples for the worst and best case, by default the largest bound of the temporal operators and timeouts"),
+        );
     // Add arguments to the app to be parsed
     let app = app
+        .subcommand(batch)
+        .subcommand(check)
+        .subcommand(monitor)
         .arg(logfile)
+        .arg(specification)
         .arg(lazy_evaluation)
+        .arg(semantics)
+        .arg(table_backend)
+        .arg(memory_limit)
+        .arg(threads)
         .arg(sub_sampling)
         .arg(debugging)
         .arg(tau_dif)
         .arg(rho_dif)
         .arg(children)
-        .arg(amount);
+        .arg(amount)
+        .arg(output_format)
+        .arg(output)
+        .arg(bad_values)
+        .arg(interpolation)
+        .arg(rate)
+        .arg(columns)
+        .arg(time_column)
+        .arg(mapping);
     // Extract the matches
-    let matches = app.get_matches();
+    let matches = app
+        .get_matches_safe()
+        .unwrap_or_else(|e| exit_with_error(e));
     // Extract data
-    let logfile = matches
-        .value_of("logfile")
-        .expect("This can't be None, since it is required")
-        .to_string();
+    // The batch and monitor commands may read the logs or samples from their own argument
+    let has_source = matches
+        .subcommand_matches("monitor")
+        .is_some_and(|monitor| monitor.is_present("source"));
+    if !matches.is_present("logfile")
+        && matches.subcommand_matches("batch").is_none()
+        && !has_source
+    {
+        exit_with_error(clap::Error::with_description(
+            "The following required arguments were not provided:\n    --logfile <FILE>",
+            clap::ErrorKind::MissingRequiredArgument,
+        ));
+    }
+    let logfile = matches.value_of("logfile").unwrap_or_default().to_string();
+    let batch = matches
+        .subcommand_matches("batch")
+        .map(|batch| BatchSetting {
+            patterns: batch
+                .values_of("logs")
+                .expect("This can't be None, since it is required")
+                .map(|log| log.to_string())
+                .collect(),
+            jobs: batch
+                .value_of("jobs")
+                .expect("This can't be None, since it is present")
+                .parse()
+                .unwrap(),
+        });
+    let check = matches
+        .subcommand_matches("check")
+        .map(|check| CheckSetting {
+            threshold: check
+                .value_of("threshold")
+                .expect("This can't be None, since it is present")
+                .parse()
+                .unwrap(),
+            verdict_only: check.is_present("verdict_only"),
+            junit: check.value_of("junit").map(|s| s.to_string()),
+        });
+    let monitor = matches
+        .subcommand_matches("monitor")
+        .map(|monitor| MonitorSetting {
+            source: monitor.value_of("source").map(|s| s.to_string()),
+            lookahead: monitor
+                .value_of("lookahead")
+                .map(|lookahead| lookahead.parse().unwrap()),
+        });
+    // The monitor evaluates each sample at the full rate, sequentially, and without lazy evaluation
+    if monitor.is_some() {
+        for (name, option) in [
+            ("lazy_evaluation", "-l"),
+            ("sub_sampling", "-s"),
+            ("threads", "-j"),
+        ] {
+            if matches.occurrences_of(name) > 0 {
+                exit_with_error(clap::Error::with_description(
+                    &format!("{option} is not supported by the monitor command"),
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+        }
+    }
+    let specification = matches.value_of("specification").map(|s| s.to_string());
     let lazy_evaluation = matches.is_present("lazy_evaluation");
+    let semantics: Semantics = matches
+        .value_of("semantics")
+        .expect("This can't be None, since it is present")
+        .parse()
+        .unwrap();
+    if lazy_evaluation && !semantics.supports_lazy_evaluation() {
+        exit_with_error(clap::Error::with_description(
+            &format!("lazy evaluation is not supported by the {semantics} semantics"),
+            clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+    let table_backend = matches
+        .value_of("table_backend")
+        .expect("This can't be None, since it is present")
+        .parse()
+        .unwrap();
+    let memory_limit = matches
+        .value_of("memory_limit")
+        .map(|limit| (limit.parse::<f64>().unwrap() * 1e6) as usize);
+    let threads = matches
+        .value_of("threads")
+        .expect("This can't be None, since it is present")
+        .parse()
+        .unwrap();
     let sub_sampling = matches.is_present("sub_sampling");
     let debug_console = matches.is_present("debugging");
     let tau_dif = matches
@@ -133,6 +499,36 @@ pub fn parse_command_line() -> CommandLineArguments {
         .parse()
         .unwrap();
     let print_leaf_segments_only = matches.is_present("children");
+    let output_format = matches
+        .value_of("output_format")
+        .expect("This can't be None, since it is present")
+        .parse()
+        .unwrap();
+    let output = matches.value_of("output").map(|s| s.to_string());
+    let mut columns: Vec<String> = matches
+        .values_of("columns")
+        .map_or(vec![], |columns| columns.map(|c| c.to_string()).collect());
+    if let Some(mapping) = matches.value_of("mapping") {
+        match read_mapping(mapping) {
+            Ok(mapping) => columns.extend(mapping),
+            Err(e) => exit_with_error(clap::Error::with_description(&e, clap::ErrorKind::Io)),
+        }
+    }
+    let trace_setting = TraceSetting {
+        bad_value_policy: matches
+            .value_of("bad_values")
+            .expect("This can't be None, since it is present")
+            .parse()
+            .unwrap(),
+        interpolation: matches
+            .value_of("interpolation")
+            .expect("This can't be None, since it is present")
+            .parse()
+            .unwrap(),
+        rate: matches.value_of("rate").map(|rate| rate.parse().unwrap()),
+        columns,
+        time_column: matches.value_of("time_column").map(|s| s.to_string()),
+    };
     let segmentation_setting = if !lazy_evaluation {
         Some(SegmentationSetting {
             tau_dif,
@@ -145,10 +541,21 @@ pub fn parse_command_line() -> CommandLineArguments {
 
     CommandLineArguments::new(
         logfile,
+        specification,
         lazy_evaluation,
+        semantics,
+        table_backend,
+        memory_limit,
+        threads,
         sub_sampling,
         debug_console,
         print_leaf_segments_only,
         segmentation_setting,
+        output_format,
+        output,
+        trace_setting,
+        batch,
+        check,
+        monitor,
     )
 }
@@ -3,59 +3,443 @@
 
 use crate::{behaviortree::TbtNode, stl::Stl, Trace};
 use csv::ReaderBuilder;
-use std::fs::File;
+use std::{collections::HashMap, fmt, fs::File, io::Read, str::FromStr};
 
+/*******************************
+ * Errors and Policies
+ *******************************/
+#[derive(Clone, Debug, PartialEq)]
+/// Errors that occur when reading a csv-file
+pub enum CsvError {
+    /// The file cannot be opened or read
+    Io { file: String, message: String },
+    /// The header of the file does not contain the column
+    MissingColumn { file: String, column: String },
+    /// The value in row (line number in the file) cannot be parsed as number
+    Parse {
+        file: String,
+        column: String,
+        row: u64,
+        value: String,
+    },
+    /// The file is not a valid csv-file, e.g., a record has too few fields
+    Format { file: String, message: String },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io { file, message } => write!(f, "Could not read {file}: {message}"),
+            CsvError::MissingColumn { file, column } => {
+                write!(f, "Column '{column}' not found in header of {file}")
+            }
+            CsvError::Parse {
+                file,
+                column,
+                row,
+                value,
+            } => write!(
+                f,
+                "Could not parse value '{value}' of column '{column}' in {file} at row {row}"
+            ),
+            CsvError::Format { file, message } => write!(f, "Malformed csv-file {file}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Defines how values that cannot be parsed as number are handled
+pub enum BadValuePolicy {
+    /// Reading fails with CsvError::Parse
+    Fail,
+    /// The row is skipped if any of its read fields is not a number, i.e., the row is skipped for all read columns
+    SkipRow,
+    /// The last valid value of the column is used, reading fails if the first value is bad
+    ForwardFill,
+    /// The value is replaced by NaN
+    Nan,
+}
+
+impl FromStr for BadValuePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(BadValuePolicy::Fail),
+            "skip" => Ok(BadValuePolicy::SkipRow),
+            "fill" => Ok(BadValuePolicy::ForwardFill),
+            "nan" => Ok(BadValuePolicy::Nan),
+            _ => Err(format!(
+                "unknown policy '{s}', expected fail, skip, fill, or nan"
+            )),
+        }
+    }
+}
+
+/*******************************
+ * Reader
+ *******************************/
+/// Number of rows that are read to decide whether a column is numeric, i.e., a column is a signal if
+/// any of these rows contains a number
+pub const NUMERIC_DETECTION_ROWS: usize = 100;
+
+/// Reads a single column of a csv-file
+/// # Arguments
+/// * `file_name` - Location of the csv-file
+/// * `column_name` - Name of the column in the header
+/// * `number_skipped_entries` - used for subsampling ie only every n-th row is read
+/// * `policy` - Defines how values that cannot be parsed are handled, SkipRow only skips rows whose value of this
+///   column is bad, see read_csv_trace() to skip rows for several columns
 pub fn read_csv_file(
     file_name: &str,
     column_name: &str,
     number_skipped_entries: usize,
-) -> Vec<f32> {
-    let delimiter = b',';
-    // Open the CSV file
-    let file = File::open(file_name).unwrap();
-
-    let mut reader = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(delimiter)
-        .from_reader(file);
-
+    policy: BadValuePolicy,
+) -> Result<Vec<f32>, CsvError> {
+    let mut reader = open_csv_file(file_name)?;
+    let format_error = |e| format_error(file_name, e);
     let column_index = reader
         .headers()
-        .unwrap()
+        .map_err(format_error)?
         .iter()
         .position(|header| header == column_name)
-        .ok_or(format!("Column '{}' not found in CSV header.", column_name))
-        .unwrap();
+        .ok_or(CsvError::MissingColumn {
+            file: file_name.to_string(),
+            column: column_name.to_string(),
+        })?;
 
-    // Iterate through the CSV records and print the selected column
+    // Iterate through the CSV records and collect the selected column
     let mut trace_given_name = Vec::<f32>::new();
     let mut take_only_each_tenth_item = if number_skipped_entries == 0 {
         number_skipped_entries
     } else {
         number_skipped_entries - 1
     };
+    let mut last_value = None;
     for result in reader.records() {
-        let record = result.unwrap();
-        if let Some(str_number) = record.get(column_index) {
-            // Attempt to parse the string into a f32
-            if number_skipped_entries == 0
-                || take_only_each_tenth_item == number_skipped_entries - 1
-            {
-                take_only_each_tenth_item = 0;
-                match str_number.parse::<f32>() {
-                    Ok(f) => {
-                        trace_given_name.push(f);
-                    }
-                    Err(e) => {
-                        println!("Failed to parse: {}", e);
-                    }
+        let record = result.map_err(format_error)?;
+        let row = record.position().map_or(0, |position| position.line());
+        let str_number = record.get(column_index).ok_or(CsvError::Format {
+            file: file_name.to_string(),
+            message: format!("row {row} has no column '{column_name}'"),
+        })?;
+        let value = match str_number.trim().parse::<f32>() {
+            Ok(f) => f,
+            Err(_) => match (policy, last_value) {
+                (BadValuePolicy::SkipRow, _) => continue,
+                (BadValuePolicy::ForwardFill, Some(last_value)) => last_value,
+                (BadValuePolicy::Nan, _) => f32::NAN,
+                (BadValuePolicy::Fail, _) | (BadValuePolicy::ForwardFill, None) => {
+                    return Err(CsvError::Parse {
+                        file: file_name.to_string(),
+                        column: column_name.to_string(),
+                        row,
+                        value: str_number.to_string(),
+                    })
                 }
-            } else {
-                take_only_each_tenth_item += 1;
+            },
+        };
+        last_value = Some(value);
+        if number_skipped_entries == 0 || take_only_each_tenth_item == number_skipped_entries - 1 {
+            take_only_each_tenth_item = 0;
+            trace_given_name.push(value);
+        } else {
+            take_only_each_tenth_item += 1;
+        }
+    }
+    Ok(trace_given_name)
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Columns of a csv-file that are read into a trace
+pub enum CsvColumns<'a> {
+    /// Pairs of column name in the header and signal name in the trace
+    Selected(&'a [(&'a str, &'a str)]),
+    /// All columns with a number in any of the first NUMERIC_DETECTION_ROWS rows, the signal name is the column name
+    AllNumeric,
+}
+
+/// Reads several columns of a csv-file in a single pass
+/// # Arguments
+/// * `file_name` - Location of the csv-file
+/// * `columns` - Columns that are read and their signal names
+/// * `prefix` - Prefix of all signal names, e.g., ``uas_``
+/// * `number_skipped_entries` - used for subsampling ie only every n-th row is read
+/// * `policy` - Defines how values that cannot be parsed are handled, SkipRow skips a row if any read column is bad
+pub fn read_csv_trace(
+    file_name: &str,
+    columns: CsvColumns,
+    prefix: &str,
+    number_skipped_entries: usize,
+    policy: BadValuePolicy,
+) -> Result<Trace, CsvError> {
+    let file = File::open(file_name).map_err(|e| CsvError::Io {
+        file: file_name.to_string(),
+        message: e.to_string(),
+    })?;
+    read_csv_trace_from(
+        file,
+        file_name,
+        columns,
+        prefix,
+        number_skipped_entries,
+        policy,
+    )
+}
+
+/// Reads several columns of a csv-file from a reader, e.g., stdin, see read_csv_trace()
+/// # Arguments
+/// * `reader` - Source of the csv-file
+/// * `file_name` - Name of the source used in errors
+/// * `columns` - Columns that are read and their signal names
+/// * `prefix` - Prefix of all signal names, e.g., ``uas_``
+/// * `number_skipped_entries` - used for subsampling ie only every n-th row is read
+/// * `policy` - Defines how values that cannot be parsed are handled, SkipRow skips a row if any read column is bad
+pub fn read_csv_trace_from<R: Read>(
+    reader: R,
+    file_name: &str,
+    columns: CsvColumns,
+    prefix: &str,
+    number_skipped_entries: usize,
+    policy: BadValuePolicy,
+) -> Result<Trace, CsvError> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .delimiter(b',')
+        .from_reader(reader);
+    let format_error = |e| format_error(file_name, e);
+    let headers = reader.headers().map_err(format_error)?.clone();
+    let mut records = reader.records();
+    // Rows that are read to decide which columns are numeric, they are evaluated afterwards
+    let mut first_records = vec![];
+    // (index in record, column name, signal name)
+    let selected: Vec<(usize, String, String)> = match columns {
+        CsvColumns::Selected(columns) => columns
+            .iter()
+            .map(|(column, signal)| {
+                headers
+                    .iter()
+                    .position(|header| header == *column)
+                    .map(|index| (index, column.to_string(), format!("{prefix}{signal}")))
+                    .ok_or(CsvError::MissingColumn {
+                        file: file_name.to_string(),
+                        column: column.to_string(),
+                    })
+            })
+            .collect::<Result<_, _>>()?,
+        CsvColumns::AllNumeric => {
+            for result in records.by_ref().take(NUMERIC_DETECTION_ROWS) {
+                first_records.push(result.map_err(format_error)?);
+            }
+            // A bad or empty value of a numeric column is handled by the policy
+            headers
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| {
+                    first_records.iter().any(|record| {
+                        record
+                            .get(*index)
+                            .is_some_and(|field| field.trim().parse::<f32>().is_ok())
+                    })
+                })
+                .map(|(index, column)| (index, column.to_string(), format!("{prefix}{column}")))
+                .collect()
+        }
+    };
+
+    // Iterate through the CSV records and collect the selected columns
+    let mut values = vec![Vec::<f32>::new(); selected.len()];
+    let mut last_values = vec![None; selected.len()];
+    let mut row_values = vec![0.0; selected.len()];
+    let mut take_only_each_tenth_item = if number_skipped_entries == 0 {
+        number_skipped_entries
+    } else {
+        number_skipped_entries - 1
+    };
+    'records: for result in first_records.into_iter().map(Ok).chain(records) {
+        let record = result.map_err(format_error)?;
+        let row = record.position().map_or(0, |position| position.line());
+        for (i, (index, column, _)) in selected.iter().enumerate() {
+            let str_number = record.get(*index).ok_or(CsvError::Format {
+                file: file_name.to_string(),
+                message: format!("row {row} has no column '{column}'"),
+            })?;
+            row_values[i] = match (str_number.trim().parse::<f32>(), policy, last_values[i]) {
+                (Ok(f), _, _) => f,
+                // Skipped rows must be skipped for all columns
+                (Err(_), BadValuePolicy::SkipRow, _) => continue 'records,
+                (Err(_), BadValuePolicy::ForwardFill, Some(last_value)) => last_value,
+                (Err(_), BadValuePolicy::Nan, _) => f32::NAN,
+                (Err(_), BadValuePolicy::Fail, _) | (Err(_), BadValuePolicy::ForwardFill, None) => {
+                    return Err(CsvError::Parse {
+                        file: file_name.to_string(),
+                        column: column.to_string(),
+                        row,
+                        value: str_number.to_string(),
+                    })
+                }
+            };
+        }
+        for (last_value, value) in last_values.iter_mut().zip(&row_values) {
+            *last_value = Some(*value);
+        }
+        if number_skipped_entries == 0 || take_only_each_tenth_item == number_skipped_entries - 1 {
+            take_only_each_tenth_item = 0;
+            for (column_values, value) in values.iter_mut().zip(&row_values) {
+                column_values.push(*value);
+            }
+        } else {
+            take_only_each_tenth_item += 1;
+        }
+    }
+    let signals = selected
+        .into_iter()
+        .map(|(_, _, signal)| signal)
+        .zip(values)
+        .collect();
+    Ok(Trace::new(signals).expect("All columns have the same number of values"))
+}
+
+/// Reads the rows of a csv-file one at a time, e.g., while they are written to stdin.
+/// Each row is returned as sample that maps the column names to their values.
+pub struct CsvSamples<R: Read> {
+    file_name: String,
+    headers: csv::StringRecord,
+    records: csv::StringRecordsIntoIter<R>,
+    selected: Vec<(usize, String)>,
+    last_values: Vec<Option<f32>>,
+    policy: BadValuePolicy,
+}
+
+impl<R: Read> CsvSamples<R> {
+    /// Reads the header, each selected column must be part of it
+    /// # Arguments
+    /// * `reader` - Source of the csv-file, e.g., stdin
+    /// * `file_name` - Name of the source used in errors
+    /// * `columns` - Names of the columns that are read
+    /// * `policy` - Defines how values that cannot be parsed are handled
+    pub fn new(
+        reader: R,
+        file_name: &str,
+        columns: &[String],
+        policy: BadValuePolicy,
+    ) -> Result<CsvSamples<R>, CsvError> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .delimiter(b',')
+            .from_reader(reader);
+        let headers = reader
+            .headers()
+            .map_err(|e| format_error(file_name, e))?
+            .clone();
+        let selected = columns
+            .iter()
+            .map(|column| {
+                headers
+                    .iter()
+                    .position(|header| header == column)
+                    .map(|index| (index, column.clone()))
+                    .ok_or(CsvError::MissingColumn {
+                        file: file_name.to_string(),
+                        column: column.clone(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CsvSamples {
+            file_name: file_name.to_string(),
+            headers,
+            records: reader.into_records(),
+            last_values: vec![None; selected.len()],
+            selected,
+            policy,
+        })
+    }
+
+    /// Reads a column in addition to the selected columns if it is part of the header, e.g., the time axis
+    /// # Arguments
+    /// * `column` - Name of the column
+    pub fn with_optional_column(mut self, column: &str) -> CsvSamples<R> {
+        let index = self.headers.iter().position(|header| header == column);
+        if let Some(index) = index {
+            if self.selected.iter().all(|(_, selected)| selected != column) {
+                self.selected.push((index, column.to_string()));
+                self.last_values.push(None);
+            }
+        }
+        self
+    }
+}
+
+impl<R: Read> Iterator for CsvSamples<R> {
+    type Item = Result<HashMap<String, f32>, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        'records: loop {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(format_error(&self.file_name, e))),
+            };
+            let row = record.position().map_or(0, |position| position.line());
+            let mut row_values = Vec::with_capacity(self.selected.len());
+            for (i, (index, column)) in self.selected.iter().enumerate() {
+                let Some(str_number) = record.get(*index) else {
+                    return Some(Err(CsvError::Format {
+                        file: self.file_name.clone(),
+                        message: format!("row {row} has no column '{column}'"),
+                    }));
+                };
+                let value = match (
+                    str_number.trim().parse::<f32>(),
+                    self.policy,
+                    self.last_values[i],
+                ) {
+                    (Ok(f), _, _) => f,
+                    (Err(_), BadValuePolicy::SkipRow, _) => continue 'records,
+                    (Err(_), BadValuePolicy::ForwardFill, Some(last_value)) => last_value,
+                    (Err(_), BadValuePolicy::Nan, _) => f32::NAN,
+                    (Err(_), BadValuePolicy::Fail, _)
+                    | (Err(_), BadValuePolicy::ForwardFill, None) => {
+                        return Some(Err(CsvError::Parse {
+                            file: self.file_name.clone(),
+                            column: column.clone(),
+                            row,
+                            value: str_number.to_string(),
+                        }))
+                    }
+                };
+                row_values.push(value);
+            }
+            for (last_value, value) in self.last_values.iter_mut().zip(&row_values) {
+                *last_value = Some(*value);
             }
+            return Some(Ok(self
+                .selected
+                .iter()
+                .map(|(_, column)| column.clone())
+                .zip(row_values)
+                .collect()));
         }
     }
-    trace_given_name
+}
+
+fn open_csv_file(file_name: &str) -> Result<csv::Reader<File>, CsvError> {
+    let file = File::open(file_name).map_err(|e| CsvError::Io {
+        file: file_name.to_string(),
+        message: e.to_string(),
+    })?;
+    Ok(ReaderBuilder::new()
+        .has_headers(true)
+        .delimiter(b',')
+        .from_reader(file))
+}
+
+fn format_error(file_name: &str, e: csv::Error) -> CsvError {
+    CsvError::Format {
+        file: file_name.to_string(),
+        message: e.to_string(),
+    }
 }
 
 pub fn get_best_number_skipped(trace: Trace, tree: TbtNode) -> (usize, (f32, f32), (f32, f32)) {
@@ -74,8 +458,8 @@ pub fn get_best_number_skipped(trace: Trace, tree: TbtNode) -> (usize, (f32, f32
         let mut neg_number_consecutive_false_dif = (f32::MAX, f32::MIN);
         let mut neg_interval_values = (f32::MAX, f32::MIN);
         let mut neg_count = 0_usize;
-        for i in 0..trace.0 {
-            if let Stl::Atomic(_, names, function) = ap {
+        for i in 0..trace.len() {
+            if let Stl::Atomic(_, names, function, _) = ap {
                 let v = ap.evaluate_fnc(names, &trace, i, function);
                 // Positive values
                 if v >= 0.0 {
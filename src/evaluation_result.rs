@@ -0,0 +1,127 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::behaviortree::Segmentation;
+use std::time::Duration;
+
+/*******************************
+ * Evaluation Result
+ *******************************/
+#[derive(Clone, Debug)]
+/// Result of evaluate() that can be post-processed or printed using the reporter
+/// # Arguments
+/// * `robustness` - Robustness of the TBT for the whole trace
+/// * `segmentation_robustness` - Robustness of the best segmentation, i.e., the minimal value of its segments
+/// * `is_approximate` - True if lazy evaluation or sub sampling was used
+/// * `delta_rho_skipped` - Robustness difference induced by sub sampling
+/// * `segmentation` - Best segmentation
+/// * `alternatives` - Alternative segmentations that are computed if a segmentation setting is provided
+/// * `statistics` - Sizes and lookups of the tables used for dynamic programming
+/// * `timings` - Durations of the different evaluation steps
+pub struct EvaluationResult {
+    pub robustness: f32,
+    pub segmentation_robustness: f32,
+    pub is_approximate: bool,
+    pub delta_rho_skipped: f32,
+    pub segmentation: Vec<Segment>,
+    pub alternatives: Vec<AlternativeSegmentation>,
+    pub statistics: TableStatistics,
+    pub timings: Timings,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A segment assigns a TBT node to the trace positions lower to upper (both inclusive)
+/// # Arguments
+/// * `node_id` - Index of the TBT node
+/// * `kind` - Operator of the TBT node, e.g., Leaf or Sequence
+/// * `name` - Name of the TBT node if it is a leaf
+/// * `description` - Printed TBT node without children, e.g., Leaf(3 descend)
+/// * `lower` - First position of the segment
+/// * `upper` - Last position of the segment
+/// * `value` - Robustness of the TBT node for this segment
+/// * `parent` - Position of the segment of the parent node in the segmentation, None for the root
+pub struct Segment {
+    pub node_id: usize,
+    pub kind: &'static str,
+    pub name: Option<String>,
+    pub description: String,
+    pub lower: usize,
+    pub upper: usize,
+    pub value: f32,
+    pub parent: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+/// An alternative segmentation that differs from the best segmentation
+/// # Arguments
+/// * `remaining_tau_dif` - Remaining difference of segment boundaries to the previous segmentations
+/// * `robustness` - Robustness of this segmentation
+/// * `segmentation` - The alternative segmentation
+pub struct AlternativeSegmentation {
+    pub remaining_tau_dif: usize,
+    pub robustness: f32,
+    pub segmentation: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Statistics of the tables used for dynamic programming
+pub struct TableStatistics {
+    pub tree_entries: usize,
+    pub formula_entries: usize,
+    pub tree_lookups: usize,
+    pub formula_lookups: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Durations of the evaluation steps
+/// # Arguments
+/// * `evaluation` - Time from start (including reading the trace) until the robustness is computed
+/// * `segmentation` - Time to compute the best segmentation
+/// * `alternatives` - Time to compute the alternative segmentations
+pub struct Timings {
+    pub evaluation: Duration,
+    pub segmentation: Duration,
+    pub alternatives: Duration,
+}
+
+impl EvaluationResult {
+    /// Returns true if the robustness is at least the threshold, e.g., 0.0 to check satisfaction
+    pub fn passes(&self, threshold: f32) -> bool {
+        self.robustness >= threshold
+    }
+}
+
+impl Segment {
+    /// Converts a segmentation, which references the TBT, into owned segments.
+    /// A segmentation lists each node before the segments of its children, hence,
+    /// the parent of a segment is the closest previous segment that has its node as child.
+    pub fn from_segmentation(segmentation: &Segmentation) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut stack: Vec<(usize, Vec<usize>)> = Vec::new();
+        for (position, (tbt_node, lower, upper, value)) in segmentation.iter().enumerate() {
+            let node_id = tbt_node.get_index();
+            while let Some((_, children)) = stack.last() {
+                if children.contains(&node_id) {
+                    break;
+                }
+                stack.pop();
+            }
+            segments.push(Segment {
+                node_id,
+                kind: tbt_node.get_kind(),
+                name: tbt_node.get_name().map(|name| name.to_string()),
+                description: tbt_node.pretty_print(false, 0),
+                lower: *lower,
+                upper: *upper,
+                value: *value,
+                parent: stack.last().map(|(parent, _)| *parent),
+            });
+            stack.push((position, tbt_node.get_children_indices()));
+        }
+        segments
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.kind == "Leaf"
+    }
+}
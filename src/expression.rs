@@ -0,0 +1,295 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::ApF;
+use std::{f32::consts::PI, fmt, sync::Arc};
+
+/*******************************
+ * Expression Syntax
+ *******************************/
+#[derive(Clone, Debug, PartialEq)]
+/// Arithmetic expression over signals of a trace that is used as atomic proposition
+pub enum Expression {
+    Signal(String),
+    Constant(f32),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOperator {
+    Neg,
+    Abs,
+    Sqrt,
+    Sin,
+    Cos,
+    /// Wraps an angle (in radian) into the interval [-pi, pi)
+    WrapAngle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Min,
+    Max,
+    Atan2,
+}
+
+impl UnaryOperator {
+    /// Name used for function calls, e.g., abs(x)
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnaryOperator::Neg => "-",
+            UnaryOperator::Abs => "abs",
+            UnaryOperator::Sqrt => "sqrt",
+            UnaryOperator::Sin => "sin",
+            UnaryOperator::Cos => "cos",
+            UnaryOperator::WrapAngle => "wrap",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "abs" => Some(UnaryOperator::Abs),
+            "sqrt" => Some(UnaryOperator::Sqrt),
+            "sin" => Some(UnaryOperator::Sin),
+            "cos" => Some(UnaryOperator::Cos),
+            "wrap" => Some(UnaryOperator::WrapAngle),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, v: f32) -> f32 {
+        match self {
+            UnaryOperator::Neg => -v,
+            UnaryOperator::Abs => f32::abs(v),
+            UnaryOperator::Sqrt => f32::sqrt(v),
+            UnaryOperator::Sin => f32::sin(v),
+            UnaryOperator::Cos => f32::cos(v),
+            UnaryOperator::WrapAngle => (v + PI).rem_euclid(2.0 * PI) - PI,
+        }
+    }
+}
+
+impl BinaryOperator {
+    /// Name used for infix operators and function calls, e.g., a + b or min(a, b)
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Min => "min",
+            BinaryOperator::Max => "max",
+            BinaryOperator::Atan2 => "atan2",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "min" => Some(BinaryOperator::Min),
+            "max" => Some(BinaryOperator::Max),
+            "atan2" => Some(BinaryOperator::Atan2),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, l: f32, r: f32) -> f32 {
+        match self {
+            BinaryOperator::Add => l + r,
+            BinaryOperator::Sub => l - r,
+            BinaryOperator::Mul => l * r,
+            BinaryOperator::Div => l / r,
+            BinaryOperator::Min => f32::min(l, r),
+            BinaryOperator::Max => f32::max(l, r),
+            BinaryOperator::Atan2 => f32::atan2(l, r),
+        }
+    }
+
+    /// Binding strength of infix operators, function calls bind strongest
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Add | BinaryOperator::Sub => 1,
+            BinaryOperator::Mul | BinaryOperator::Div => 2,
+            BinaryOperator::Min | BinaryOperator::Max | BinaryOperator::Atan2 => 4,
+        }
+    }
+
+    fn is_infix(&self) -> bool {
+        self.precedence() < 3
+    }
+}
+
+/*******************************
+ * Constructors
+ *******************************/
+#[allow(dead_code)]
+impl Expression {
+    pub fn signal(name: &str) -> Self {
+        Expression::Signal(name.to_string())
+    }
+    pub fn constant(value: f32) -> Self {
+        Expression::Constant(value)
+    }
+    pub fn unary(operator: UnaryOperator, child: Expression) -> Self {
+        Expression::Unary(operator, Box::new(child))
+    }
+    pub fn binary(
+        operator: BinaryOperator,
+        left_child: Expression,
+        right_child: Expression,
+    ) -> Self {
+        Expression::Binary(operator, Box::new(left_child), Box::new(right_child))
+    }
+}
+
+/*******************************
+ * Functions
+ *******************************/
+impl Expression {
+    /// Returns the names of all signals that are read, each name is returned once
+    pub fn signals(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_signals(&mut names);
+        names
+    }
+
+    fn collect_signals(&self, names: &mut Vec<String>) {
+        match self {
+            Expression::Signal(name) => {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            Expression::Constant(_) => (),
+            Expression::Unary(_, child) => child.collect_signals(names),
+            Expression::Binary(_, l_child, r_child) => {
+                l_child.collect_signals(names);
+                r_child.collect_signals(names);
+            }
+        }
+    }
+
+    /// Compiles the expression into a function of an atomic proposition.
+    /// The function expects the values of the signals in the order returned by signals().
+    pub fn compile(&self) -> (Vec<String>, ApF) {
+        let names = self.signals();
+        let compiled = Compiled::new(self, &names);
+        (
+            names,
+            Arc::new(move |values: &[f32]| compiled.evaluate(values)),
+        )
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Signal(_) => 4,
+            Expression::Constant(c) => {
+                if *c < 0.0 {
+                    3
+                } else {
+                    4
+                }
+            }
+            Expression::Unary(UnaryOperator::Neg, _) => 3,
+            Expression::Unary(_, _) => 4,
+            Expression::Binary(operator, _, _) => operator.precedence(),
+        }
+    }
+}
+
+/// Returns true if the name is read as signal without quotes, i.e., it starts with a letter or _,
+/// consists of alphanumerics and _, and is not the constant pi
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && name != "pi"
+}
+
+/// Prints the expression using as few parentheses as possible
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // Names that the parser does not read as identifier are quoted, e.g., vehicle_local_position.x
+            Expression::Signal(name) if is_identifier(name) => write!(f, "{name}"),
+            Expression::Signal(name) => write!(f, "\"{name}\""),
+            Expression::Constant(c) => write!(f, "{c}"),
+            Expression::Unary(UnaryOperator::Neg, child) => {
+                if child.precedence() < 3 {
+                    write!(f, "-({child})")
+                } else {
+                    write!(f, "-{child}")
+                }
+            }
+            Expression::Unary(operator, child) => write!(f, "{}({child})", operator.name()),
+            Expression::Binary(operator, l_child, r_child) => {
+                if operator.is_infix() {
+                    let precedence = operator.precedence();
+                    if l_child.precedence() < precedence {
+                        write!(f, "({l_child})")?;
+                    } else {
+                        write!(f, "{l_child}")?;
+                    }
+                    write!(f, " {} ", operator.name())?;
+                    // Subtraction and division are not associative
+                    let non_associative =
+                        matches!(operator, BinaryOperator::Sub | BinaryOperator::Div);
+                    if r_child.precedence() < precedence
+                        || (non_associative && r_child.precedence() == precedence)
+                    {
+                        write!(f, "({r_child})")
+                    } else {
+                        write!(f, "{r_child}")
+                    }
+                } else {
+                    write!(f, "{}({l_child}, {r_child})", operator.name())
+                }
+            }
+        }
+    }
+}
+
+/*******************************
+ * Compiled Expression
+ *******************************/
+/// Expression where signal names are replaced by their position in the value slice
+enum Compiled {
+    Value(usize),
+    Constant(f32),
+    Unary(UnaryOperator, Box<Compiled>),
+    Binary(BinaryOperator, Box<Compiled>, Box<Compiled>),
+}
+
+impl Compiled {
+    fn new(expression: &Expression, names: &[String]) -> Self {
+        match expression {
+            Expression::Signal(name) => {
+                Compiled::Value(names.iter().position(|n| n == name).unwrap())
+            }
+            Expression::Constant(c) => Compiled::Constant(*c),
+            Expression::Unary(operator, child) => {
+                Compiled::Unary(*operator, Box::new(Compiled::new(child, names)))
+            }
+            Expression::Binary(operator, l_child, r_child) => Compiled::Binary(
+                *operator,
+                Box::new(Compiled::new(l_child, names)),
+                Box::new(Compiled::new(r_child, names)),
+            ),
+        }
+    }
+
+    fn evaluate(&self, values: &[f32]) -> f32 {
+        match self {
+            Compiled::Value(index) => values[*index],
+            Compiled::Constant(c) => *c,
+            Compiled::Unary(operator, child) => operator.apply(child.evaluate(values)),
+            Compiled::Binary(operator, l_child, r_child) => {
+                operator.apply(l_child.evaluate(values), r_child.evaluate(values))
+            }
+        }
+    }
+}
@@ -21,43 +21,81 @@ mod tree {
     }
 }
 
+pub mod arrow_reader;
+pub mod batch;
 pub mod behaviortree;
 mod command_line_parser;
-mod csv_reader;
-mod stl;
+pub mod csv_reader;
+pub mod evaluation_result;
+pub mod expression;
+pub mod monitor;
+pub mod parser;
+pub mod reporter;
+pub mod semantics;
+pub mod serialization;
+mod sliding_window;
+pub mod stl;
+pub mod stream_reader;
 mod table;
 #[cfg(test)]
 mod tests;
-use behaviortree::print_segmentation;
+pub mod tlog_reader;
+pub mod trace;
+pub mod ulog_reader;
+use behaviortree::get_segmentation_robustness;
 use behaviortree::Segmentation;
 use behaviortree::Tbt;
 use command_line_parser::CommandLineArguments;
-use command_line_parser::SegmentationSetting;
-use csv_reader::get_best_number_skipped;
-use num_format::{Locale, ToFormattedString};
+pub use command_line_parser::{SegmentationSetting, EXIT_ERROR, EXIT_FAIL};
+use csv_reader::{get_best_number_skipped, CsvColumns};
+use evaluation_result::{
+    AlternativeSegmentation, EvaluationResult, Segment, TableStatistics, Timings,
+};
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::time::SystemTime;
 use stl::Stl;
 use table::Table;
 
-/*
- * This trait must be implemented by the user.
- * It is needed to provide a trace and a tree.
- */
-struct UserProvidedFunction;
+pub use table::{MemoryEstimate, TableBackend};
 
-trait ProvidesTraceAndTree {
-    fn get_trace(logfile: &str, number_skipped_entries: usize) -> Trace;
+pub use semantics::Semantics;
+use trace::TIME_SIGNAL;
+pub use trace::{Trace, TraceSetting};
+pub use tree::shipdeck_landing::get_trace_and_tree::ShipdeckLanding;
+
+/// A use case that provides traces and a tree, e.g., ShipdeckLanding.
+/// External crates implement it for their own domain and call ``read_trace::<UseCase>(...)``,
+/// ``get_best_number_skipped_entries::<UseCase>(...)``, and ``get_tbt_and_trace::<UseCase>(...)``.
+pub trait ProvidesTraceAndTree {
+    /// Reads the trace from a logfile
+    /// # Arguments
+    /// * `logfile` - Location of logfile
+    /// * `number_skipped_entries` - used for subsampling ie number of entries in the logfile that are skipped
+    /// * `setting` - Defines how values that cannot be parsed are handled and how logfiles are aligned
+    fn get_trace(
+        logfile: &str,
+        number_skipped_entries: usize,
+        setting: &TraceSetting,
+    ) -> Result<Trace, String>;
+
+    /// Builds the tree, e.g., using a TbtBuilder
+    /// # Arguments
+    /// * `number_skipped_entries` - used for subsampling, not needed if bounds are given in seconds
     fn get_tree(number_skipped_entries: usize) -> Tbt;
+
+    /// Atomic propositions that can be referenced by name in specification files
+    fn get_atomics() -> AtomicRegistry {
+        AtomicRegistry::new()
+    }
 }
 
-/// A trace consists of the length of the trace (usize) and a hashmap
-/// that maps a variable name (String) to its stream of values (Vec<f32>)
-pub type Trace = (usize, HashMap<String, Vec<f32>>);
+/// A function that represents an atomic proposition, it can be shared by threads that evaluate subtrees in parallel
+pub type ApF = Arc<dyn Fn(&[f32]) -> f32 + Send + Sync>;
 
-/// A function that represents an atomic proposition
-pub type ApF = Rc<dyn Fn(&[f32]) -> f32>;
+/// Maps the name of an atomic proposition to the variables it reads and its function.
+/// It is used to resolve ``Atomic(name)`` in textual specifications.
+pub type AtomicRegistry = HashMap<String, (Vec<String>, ApF)>;
 
 /************************
  * Command Line Arguments
@@ -67,59 +105,215 @@ pub fn parse_command_line() -> CommandLineArguments {
 }
 
 /**********************************
- * Returns TBT and Trace
+ * Reads a textual TBT specification
+ **********************************/
+/// Parses a specification file using the atomic propositions provided by the use case U.
+/// Files ending with .json, .yaml or .yml are deserialized, all other files are parsed as textual specification.
+/// # Arguments
+/// * `specification_file` - Location of the specification file
+pub fn read_specification<U: ProvidesTraceAndTree>(
+    specification_file: &str,
+) -> Result<Tbt, String> {
+    let input = std::fs::read_to_string(specification_file)
+        .map_err(|e| format!("Could not read specification {specification_file}: {e}"))?;
+    let atomics = U::get_atomics();
+    let extension = std::path::Path::new(specification_file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    match extension {
+        "json" => serialization::tbt_from_json(&input, &atomics),
+        "yaml" | "yml" => serialization::tbt_from_yaml(&input, &atomics),
+        _ => parser::parse_tbt(&input, &atomics).map_err(|e| e.to_string()),
+    }
+    .map_err(|e| format!("{specification_file}:{e}"))
+}
+
+/**********************************
+ * Reads the Trace
  **********************************/
+/// Reads the trace of the use case U from a logfile. PX4 ULog, MAVLink telemetry logs, Parquet, and Arrow IPC files are
+/// read using the columns of the setting, see ulog_reader, tlog_reader, and arrow_reader. If the logfile is - or another file such as a named pipe instead of a log folder, the
+/// trace is read in csv or JSON Lines format, see stream_reader. A trace without samples is an error.
 /// # Arguments
-/// * `logfile` - Location of logfile
-/// * `number_skipped_entries` - used for subsampling ie number of entries in the logfile that can be skipped
-/// * `lazy_evaluation` - enables/disables lazy evaluation
-/// * `sub_sampling` - enables/disables sub sampling
-pub fn get_tbt_and_trace(
+/// * `logfile` - Location of logfile, - for stdin
+/// * `number_skipped_entries` - used for subsampling ie number of entries in the logfile that are skipped
+/// * `setting` - Defines how values that cannot be parsed are handled and how logfiles are aligned
+pub fn read_trace<U: ProvidesTraceAndTree>(
     logfile: &str,
     number_skipped_entries: usize,
-    lazy_evaluation: bool,
-    sub_sampling: bool,
-) -> (Trace, Tbt) {
-    let trace = UserProvidedFunction::get_trace(logfile, number_skipped_entries);
-    let tbt = UserProvidedFunction::get_tree(number_skipped_entries);
-    println!(
-        "SETTING:\n\tLogfile: {logfile}\n\tApproximations: lazy evaluation={lazy_evaluation}, subsampling={sub_sampling}(delta: {number_skipped_entries})\n\tTrace length: {}\n\nTemporal behavior tree:\n{}\n",
-        trace.0,
-        tbt.tree.pretty_print(true, 2),
-    );
-    (trace, tbt)
+    setting: &TraceSetting,
+) -> Result<Trace, String> {
+    // A column is renamed by signal=column
+    let columns: Vec<(&str, &str)> = setting
+        .columns
+        .iter()
+        .map(|column| match column.split_once('=') {
+            Some((signal, column)) => (column, signal),
+            None => (column.as_str(), column.as_str()),
+        })
+        .collect();
+    let columns = if columns.is_empty() {
+        CsvColumns::AllNumeric
+    } else {
+        CsvColumns::Selected(&columns)
+    };
+    let trace = if ulog_reader::is_ulog(logfile) {
+        ulog_reader::read_ulog_trace(
+            logfile,
+            columns,
+            number_skipped_entries,
+            setting.interpolation,
+            setting.rate,
+        )
+    } else if tlog_reader::is_tlog(logfile) {
+        tlog_reader::read_tlog_trace(
+            logfile,
+            columns,
+            number_skipped_entries,
+            setting.interpolation,
+            setting.rate,
+        )
+    } else if arrow_reader::ArrowFormat::from_path(logfile).is_some() {
+        let trace = arrow_reader::read_arrow_trace(
+            logfile,
+            columns,
+            "",
+            setting.time_column.as_deref(),
+            number_skipped_entries,
+            setting.bad_value_policy,
+        )?;
+        match (&setting.time_column, trace.get(TIME_SIGNAL)) {
+            (None, Some(_)) => trace.with_time_signal(TIME_SIGNAL),
+            _ => Ok(trace),
+        }
+    } else if stream_reader::is_stream(logfile) {
+        stream_reader::read_stream_trace(logfile, number_skipped_entries, setting.bad_value_policy)
+    } else {
+        U::get_trace(logfile, number_skipped_entries, setting)
+    }?;
+    // The tables are sized by the length of the trace, e.g., an empty stdin has no samples
+    if trace.is_empty() {
+        return Err("trace has no samples".to_string());
+    }
+    Ok(trace)
+}
+
+/**********************************
+ * Returns TBT and Trace
+ **********************************/
+/// Subsamples the trace and returns it with the TBT of the use case U.
+/// Bounds in seconds are resolved against the sampling period of the trace and each signal of the TBT must be part of it.
+/// # Arguments
+/// * `trace` - Trace read by read_trace() without skipped entries, it is read only once since streams cannot be read again
+/// * `number_skipped_entries` - used for subsampling ie only every n-th sample of the trace is kept
+/// * `specification` - TBT read from a specification file, the tree of U is used if None
+pub fn get_tbt_and_trace<U: ProvidesTraceAndTree>(
+    trace: Trace,
+    number_skipped_entries: usize,
+    specification: Option<Tbt>,
+) -> Result<(Trace, Tbt), String> {
+    let trace = trace.subsample(number_skipped_entries);
+    let mut tbt = match specification {
+        Some(tbt) => tbt,
+        None => U::get_tree(number_skipped_entries),
+    };
+    // Signal names of atomic propositions may not match the columns of an arbitrary logfile
+    for atomic in tbt.tree.get_atomics() {
+        if let Stl::Atomic(_, names, _, _) = atomic {
+            if let Some(name) = names.iter().find(|name| trace.get(name).is_none()) {
+                let mut signals: Vec<&str> = trace.signals().keys().map(|s| s.as_str()).collect();
+                signals.sort();
+                return Err(format!(
+                    "signal '{name}' of the specification is not part of the trace, which has the signals {}",
+                    signals.join(", ")
+                ));
+            }
+        }
+    }
+    tbt.resolve_bounds(trace.sampling_period())?;
+    Ok((trace, tbt))
 }
 
 /*******************************************************
  * Get best number skipped entries by analyzing logfile
  *******************************************************/
-pub fn get_best_number_skipped_entries(logfile: &str, sub_sampling: bool) -> (usize, f32) {
-    let trace = UserProvidedFunction::get_trace(logfile, 0);
-    let tree = UserProvidedFunction::get_tree(0).tree;
-    let (number_skipped_entries, delta_rho_skipped) = if sub_sampling {
-        let (number_skipped_entries, (interval_min, interval_max), (_, _)) =
-            get_best_number_skipped(trace, tree);
-        let delta_rho = interval_max - interval_min;
-        (number_skipped_entries, delta_rho)
-    } else {
-        (0, 0.0)
+/// Returns the number of skipped entries and the induced robustness error for the use case U
+/// # Arguments
+/// * `trace` - Trace read by read_trace() without skipped entries
+/// * `sub_sampling` - enables/disables sub sampling, if disabled (0, 0.0) is returned
+/// * `specification` - TBT read from a specification file, the tree of U is used if None
+pub fn get_best_number_skipped_entries<U: ProvidesTraceAndTree>(
+    trace: &Trace,
+    sub_sampling: bool,
+    specification: Option<&Tbt>,
+) -> (usize, f32) {
+    if !sub_sampling {
+        return (0, 0.0);
+    }
+    let tree = match specification {
+        Some(tbt) => tbt.tree.clone(),
+        None => U::get_tree(0).tree,
     };
-    (number_skipped_entries, delta_rho_skipped)
+    let (number_skipped_entries, (interval_min, interval_max), (_, _)) =
+        get_best_number_skipped(trace.clone(), tree);
+    (number_skipped_entries, interval_max - interval_min)
+}
+
+/**********************************
+ * Estimates the memory of the tables
+ **********************************/
+/// Estimates the memory of the tables that are allocated by evaluate() without allocating them
+/// # Arguments
+/// * `tbt` - TBT specification that is evaluated
+/// * `trace_length` - Length of the trace that is evaluated
+/// * `table_backend` - Defines how the entries of the tables are stored
+pub fn estimate_table_memory(
+    tbt: &Tbt,
+    trace_length: usize,
+    table_backend: TableBackend,
+) -> MemoryEstimate {
+    table_backend.estimate_memory(&vec![None; tbt.get_number_nodes()], trace_length)
+        + table_backend.estimate_memory(&tbt.get_formula_horizons(), trace_length)
+}
+
+/// Returns an error if the tables initially need more memory than the limit
+/// # Arguments
+/// * `estimate` - Memory estimate of the tables, see estimate_table_memory()
+/// * `memory_limit` - Limit in bytes, None means unlimited
+/// * `table_backend` - Used for the error message
+pub fn check_memory_limit(
+    estimate: &MemoryEstimate,
+    memory_limit: Option<usize>,
+    table_backend: TableBackend,
+) -> Result<(), String> {
+    match memory_limit {
+        Some(memory_limit) if estimate.initial > memory_limit => Err(format!(
+            "the {table_backend} tables need {} bytes, which exceeds the memory limit of {memory_limit} bytes. Consider the lazy or sparse table backend or subsampling.",
+            estimate.initial
+        )),
+        _ => Ok(()),
+    }
 }
 
 /***************
  *  Evaluation
  ***************/
 #[allow(clippy::too_many_arguments)]
-/// Core function that evaluates a logfile given a TBT specification
+/// Core function that evaluates a logfile given a TBT specification.
+/// Returns an error if a bound in seconds cannot be resolved, e.g., since the trace has no time axis, or if
+/// lazy evaluation is not supported by the semantics.
 /// # Arguments
-/// * `tbt` - TBT specification
+/// * `tbt` - TBT specification, bounds in seconds are resolved against the sampling period of the trace
 /// * `trace` - Provided trace that is analyzed
 /// * `start` - Used for profiling
 /// * `sub_sampling` - Enables/disables sub sampling
-/// * `lazy_evaluation` - Enables/disables lazy evaluation
+/// * `lazy_evaluation` - Enables/disables lazy evaluation, which is not supported by the averaged semantics
+/// * `semantics` - Defines how robustness values are combined by STL operators and TBT nodes
+/// * `table_backend` - Defines how the entries of the tables are stored, see estimate_table_memory()
+/// * `threads` - Number of threads that evaluate subtrees in parallel (0 uses all cores), lazy evaluation is sequential
 /// * `delta_rho_skipped` - Used for subsampling
-/// * `print_leaf_segments_only` - Used for debugging: if true only leaves are printed
+/// * `leaf_segments_only` - If true, the robustness of a segmentation only considers leaves
 /// * `segmentation_setting` - Represents the command line arguments to compute the alternative segmentations
 /// * `debug` - Used for progress bar
 pub fn evaluate(
@@ -128,121 +322,118 @@ pub fn evaluate(
     start: SystemTime,
     sub_sampling: bool,
     lazy_evaluation: bool,
+    semantics: Semantics,
+    table_backend: TableBackend,
+    threads: usize,
     delta_rho_skipped: f32,
-    print_leaf_segments_only: bool,
+    leaf_segments_only: bool,
     segmentation_setting: Option<SegmentationSetting>,
     debug: bool,
-) -> f32 {
+) -> Result<EvaluationResult, String> {
+    let mut tbt = tbt;
+    tbt.resolve_bounds(trace.sampling_period())?;
+    if lazy_evaluation && !semantics.supports_lazy_evaluation() {
+        return Err(format!(
+            "lazy evaluation is not supported by the {semantics} semantics"
+        ));
+    }
     // MEMORY ALLOCATIONS
-    let mut tree_table = Table::new(Tbt::get_number_nodes(), trace.0);
-    println!(
-        "Created tree table with {} entries.",
-        tree_table.total_entries.to_formatted_string(&Locale::en)
-    );
-    let mut formula_table = Table::new(Stl::get_number_formulas(), trace.0);
-    println!(
-        "Created formula table with {} entries.\n",
-        formula_table.total_entries.to_formatted_string(&Locale::en)
-    );
+    let tree_table = Table::new(tbt.get_number_nodes(), trace.len(), table_backend);
+    // Subformulas with a horizon only need horizon + 1 entries per lower index
+    let formula_table =
+        Table::with_horizons(tbt.get_formula_horizons(), trace.len(), table_backend);
 
     let mut depth_manager_tree = HashMap::new();
     // EVALUATION
-    let robustness_res = tbt.tree.evaluate(
-        &mut depth_manager_tree,
-        &mut tree_table,
-        &mut formula_table,
-        &trace,
-        0,
-        trace.0 - 1,
-        &start,
-        debug,
-        lazy_evaluation,
-    );
+    // Subtrees are only evaluated in parallel inside the thread pool of this evaluation, e.g., not by the
+    // thread pool of the batch command
+    let parallel = threads != 1 && !lazy_evaluation;
+    let mut evaluate_tree = || {
+        tbt.tree.evaluate(
+            &mut depth_manager_tree,
+            &tree_table,
+            &formula_table,
+            &trace,
+            0,
+            trace.len() - 1,
+            &start,
+            debug,
+            lazy_evaluation,
+            semantics,
+            parallel,
+        )
+    };
+    let robustness_res = if parallel {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| format!("Could not create thread pool: {e}"))?
+            .install(evaluate_tree)
+    } else {
+        evaluate_tree()
+    };
     let robustness_res = if lazy_evaluation && robustness_res < 0.0 {
         f32::NEG_INFINITY
     } else {
         robustness_res
     };
+    let mut timings = Timings {
+        evaluation: start.elapsed().unwrap_or_default(),
+        ..Default::default()
+    };
+    let statistics = TableStatistics {
+        tree_entries: tree_table.total_entries,
+        formula_entries: formula_table.total_entries,
+        tree_lookups: tree_table.total_lookups(),
+        formula_lookups: formula_table.total_lookups(),
+    };
     // SEGMENTATION
-    let (segmentation, robustness_value) = get_segmentation(
-        robustness_res,
-        &mut tree_table,
-        &mut formula_table,
-        start,
-        &tbt,
+    let segmentation_start = SystemTime::now();
+    let segmentation = tbt.tree.get_segmentation(
+        &tree_table,
+        &formula_table,
         &trace,
+        0,
+        trace.len() - 1,
         lazy_evaluation,
-        sub_sampling,
-        delta_rho_skipped,
-        print_leaf_segments_only,
+        semantics,
     );
-
-    if !lazy_evaluation {
-        if let Some(segmentation_setting) = segmentation_setting {
-            get_alternative_segmentation(
-                &tbt,
-                &mut tree_table,
-                &mut formula_table,
-                &trace,
-                &segmentation,
-                robustness_value,
-                print_leaf_segments_only,
-                segmentation_setting,
-            );
-        }
-    }
-    robustness_res
-}
-
-/***********************
- * SEGMENTATION
- ***********************/
-#[allow(clippy::too_many_arguments)]
-/// Used to produce the segmentation
-/// # Arguments
-/// * `robustness_res` - Robustness result found using evaluate() that is used to find the segmentation here
-/// * `tree_table` - TBT table used for dynamic programming
-/// * `formula_table` - STL table used for dynamic programming
-/// * `start` - Used for profiling
-/// * `tbt` - TBT specification
-/// * `trace` - Trace that is used
-/// * `lazy_evaluation` - Enables/disables lazy evaluation
-/// * `sub_sampling` - Enables/disables sub sampling
-/// * `delta_rho_skipped` - Delta used for the sub sampling
-/// * `print_children_only` - Enables/disables debugging prints
-fn get_segmentation<'a>(
-    robustness_res: f32,
-    tree_table: &mut Table,
-    formula_table: &mut Table,
-    start: SystemTime,
-    tbt: &'a Tbt,
-    trace: &Trace,
-    lazy_evaluation: bool,
-    sub_sampling: bool,
-    delta_rho_skipped: f32,
-    print_children_only: bool,
-) -> (Segmentation<'a>, f32) {
-    println!(
-        "\nStatistics: Robustness value is {} with {} total tree lookups and {} formula lookups\nGet segmentation after {} seconds.",
-        robustness_res, tree_table.total_lookups.to_formatted_string(&Locale::en), formula_table.total_lookups.to_formatted_string(&Locale::en),start.elapsed().unwrap().as_secs()
-    );
-
-    let segmentation = tbt.tree.get_segmentation(
-        tree_table,
-        formula_table,
-        trace,
-        0,
-        trace.0 - 1,
+    let segmentation_robustness = get_segmentation_robustness(
+        &segmentation,
+        leaf_segments_only,
         lazy_evaluation,
+        semantics,
     );
-    /*******************
-     * PRINTING RESULTS
-     *******************/
-    let (robustness_value, segmentation_str) =
-        print_segmentation(&segmentation, print_children_only, lazy_evaluation);
-    println!(
-     "{} segmentation with robustness {robustness_value} and subsampling delta of {delta_rho_skipped} is:\n{segmentation_str}", if lazy_evaluation || sub_sampling {"Approximate"} else {"Best"});
-    (segmentation, robustness_value)
+    timings.segmentation = segmentation_start.elapsed().unwrap_or_default();
+
+    // ALTERNATIVE SEGMENTATION
+    let alternatives_start = SystemTime::now();
+    let alternatives = match segmentation_setting {
+        Some(segmentation_setting) if !lazy_evaluation => get_alternative_segmentation(
+            &tbt,
+            &tree_table,
+            &formula_table,
+            &trace,
+            &segmentation,
+            segmentation_robustness,
+            leaf_segments_only,
+            segmentation_setting,
+            semantics,
+        ),
+        _ => vec![],
+    };
+    timings.alternatives = alternatives_start.elapsed().unwrap_or_default();
+
+    Ok(EvaluationResult {
+        robustness: robustness_res,
+        segmentation_robustness,
+        is_approximate: lazy_evaluation || sub_sampling,
+        delta_rho_skipped,
+        segmentation: Segment::from_segmentation(&segmentation),
+        alternatives,
+        statistics,
+        timings,
+    })
 }
 
 /***************************
@@ -257,28 +448,45 @@ fn get_segmentation<'a>(
 /// * `trace` - Trace that is used
 /// * `segmentation` - Optimal segmenation returned by get_segmentation()
 /// * `robustness_value` - Robustness values produced by evaluate()
-/// * `print_leaf_segments_only` - Enables/disables to print only leaf nodes
+/// * `leaf_segments_only` - If true, the robustness of a segmentation only considers leaves
 /// * `segmentation_setting` - Read command line arguments such as tau and rho
-pub fn get_alternative_segmentation(
+/// * `semantics` - Defines how robustness values are combined
+fn get_alternative_segmentation(
     tbt: &Tbt,
-    tree_table: &mut Table,
-    formula_table: &mut Table,
+    tree_table: &Table,
+    formula_table: &Table,
     trace: &Trace,
     segmentation: &Segmentation,
     robustness_value: f32,
-    print_leaf_segments_only: bool,
+    leaf_segments_only: bool,
     segmentation_setting: SegmentationSetting,
-) {
-    let _other_segmentations = tbt.tree.get_alternative_segmentation(
+    semantics: Semantics,
+) -> Vec<AlternativeSegmentation> {
+    let other_segmentations = tbt.tree.get_alternative_segmentation(
         tree_table,
         formula_table,
         trace,
         0,
-        trace.0 - 1,
+        trace.len() - 1,
         segmentation,
         segmentation_setting.tau_dif,
         robustness_value - segmentation_setting.rho_dif,
         segmentation_setting.amount,
-        print_leaf_segments_only,
+        semantics,
     );
+    other_segmentations
+        .iter()
+        .map(
+            |(remaining_tau_dif, segmentation)| AlternativeSegmentation {
+                remaining_tau_dif: *remaining_tau_dif,
+                robustness: get_segmentation_robustness(
+                    segmentation,
+                    leaf_segments_only,
+                    false,
+                    semantics,
+                ),
+                segmentation: Segment::from_segmentation(segmentation),
+            },
+        )
+        .collect()
 }
@@ -3,7 +3,13 @@
 
 use std::time::SystemTime;
 use tbt_segmentation::{
-    evaluate, get_best_number_skipped_entries, get_tbt_and_trace, parse_command_line,
+    batch::{find_logs, run_batch},
+    check_memory_limit, estimate_table_memory, evaluate, get_best_number_skipped_entries,
+    get_tbt_and_trace,
+    monitor::run_monitor,
+    parse_command_line, read_specification, read_trace,
+    reporter::{self, OutputFormat, SampleMapping},
+    ShipdeckLanding, EXIT_ERROR, EXIT_FAIL,
 };
 
 fn main() {
@@ -12,43 +18,219 @@ fn main() {
      * PARAMETERS
      *************/
     let arguments = parse_command_line();
+    let specification = match &arguments.specification {
+        Some(specification_file) => match read_specification::<ShipdeckLanding>(specification_file)
+        {
+            Ok(tbt) => Some(tbt),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(EXIT_ERROR);
+            }
+        },
+        None => None,
+    };
+
+    /*************
+     * BATCH MODE
+     *************/
+    if let Some(batch) = &arguments.batch {
+        let logs = match find_logs(&batch.patterns) {
+            Ok(logs) => logs,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(EXIT_ERROR);
+            }
+        };
+        let entries = match run_batch::<ShipdeckLanding>(
+            &logs,
+            specification.as_ref(),
+            &arguments,
+            batch.jobs,
+        ) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(EXIT_ERROR);
+            }
+        };
+        if let Err(e) = reporter::write_batch(
+            &entries,
+            arguments.output_format,
+            arguments.output.as_deref(),
+        ) {
+            eprintln!("Error: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+        // Errors of a log do not abort the batch but are reported by the exit code
+        if entries.iter().any(|entry| entry.outcome.is_err()) {
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
+
+    /***************
+     * MONITOR MODE
+     ***************/
+    if let Some(monitor) = &arguments.monitor {
+        let mut output: Box<dyn std::io::Write> = match &arguments.output {
+            Some(output_file) => match std::fs::File::create(output_file) {
+                Ok(file) => Box::new(std::io::LineWriter::new(file)),
+                Err(e) => {
+                    eprintln!("Error: Could not write output {output_file}: {e}");
+                    std::process::exit(EXIT_ERROR);
+                }
+            },
+            None => Box::new(std::io::stdout()),
+        };
+        if let Err(e) =
+            run_monitor::<ShipdeckLanding>(specification, &arguments, monitor, &mut output)
+        {
+            eprintln!("Error: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+        return;
+    }
 
     /**********************************
      * Get best number skipped entries
      **********************************/
+    // The trace is read once since stdin and named pipes cannot be read again
+    let trace = match read_trace::<ShipdeckLanding>(&arguments.logfile, 0, &arguments.trace_setting)
+    {
+        Ok(trace) => trace,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    };
     let (number_skipped_entries, delta_rho_skipped) =
-        get_best_number_skipped_entries(&arguments.logfile, arguments.sub_sampling);
+        get_best_number_skipped_entries::<ShipdeckLanding>(
+            &trace,
+            arguments.sub_sampling,
+            specification.as_ref(),
+        );
 
     /*******************
      * STARTUP ROUTINES
      *******************/
-    let (trace, tbt) = get_tbt_and_trace(
-        &arguments.logfile,
-        number_skipped_entries,
-        arguments.lazy_evaluation,
-        arguments.sub_sampling,
-    );
+    let (trace, tbt) =
+        match get_tbt_and_trace::<ShipdeckLanding>(trace, number_skipped_entries, specification) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(EXIT_ERROR);
+            }
+        };
+    // JSON and CSV written to stdout must not be mixed with text
+    let verdict_only = arguments
+        .check
+        .as_ref()
+        .is_some_and(|check| check.verdict_only);
+    let print_text = !verdict_only
+        && (arguments.output_format == OutputFormat::Text || arguments.output.is_some());
+    if print_text {
+        reporter::print_setting(
+            &arguments.logfile,
+            number_skipped_entries,
+            arguments.lazy_evaluation,
+            arguments.sub_sampling,
+            &trace,
+            &tbt,
+        );
+    }
+    let mapping = SampleMapping::new(&trace, number_skipped_entries);
+
+    /*********************
+     * Memory Estimate
+     *********************/
+    let estimate = estimate_table_memory(&tbt, trace.len(), arguments.table_backend);
+    if print_text {
+        reporter::print_memory_estimate(&estimate, arguments.table_backend);
+    }
+    if let Err(e) = check_memory_limit(&estimate, arguments.memory_limit, arguments.table_backend) {
+        eprintln!("Error: {e}");
+        std::process::exit(EXIT_ERROR);
+    }
 
     /*********************
      * Evaluation
      *********************/
-    evaluate(
+    let result = match evaluate(
         tbt,
         trace,
         start,
         arguments.sub_sampling,
         arguments.lazy_evaluation,
+        arguments.semantics,
+        arguments.table_backend,
+        arguments.threads,
         delta_rho_skipped,
         arguments.print_leaf_segments_only,
-        arguments.segmentation_setting,
+        // Alternative segmentations are not printed with the verdict only
+        arguments.segmentation_setting.filter(|_| !verdict_only),
         arguments.debug_console,
-    );
+    ) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    if arguments.output.is_some() && !verdict_only {
+        reporter::print_result(&result, arguments.print_leaf_segments_only);
+    }
+    if !verdict_only {
+        if let Err(e) = reporter::write_result(
+            &result,
+            &mapping,
+            arguments.output_format,
+            arguments.output.as_deref(),
+            arguments.print_leaf_segments_only,
+        ) {
+            eprintln!("Error: {e}");
+            std::process::exit(EXIT_ERROR);
+        }
+    }
+
+    /*********************
+     * Verdict
+     *********************/
+    let mut passes = true;
+    if let Some(check) = &arguments.check {
+        if let Some(junit) = &check.junit {
+            if let Err(e) = reporter::write_junit(
+                &result,
+                &mapping,
+                &arguments.logfile,
+                check.threshold,
+                junit,
+            ) {
+                eprintln!("Error: {e}");
+                std::process::exit(EXIT_ERROR);
+            }
+        }
+        passes = result.passes(check.threshold);
+        let verdict = if passes { "PASS" } else { "FAIL" };
+        if verdict_only {
+            println!("{verdict}");
+        } else if print_text {
+            println!(
+                "Verdict: {verdict} (robustness {} and threshold {})",
+                result.robustness, check.threshold
+            );
+        }
+    }
 
     /*********************
      * Finish Execution
      *********************/
-    println!(
-        "Finished after {} seconds.",
-        start.elapsed().unwrap().as_secs()
-    );
+    if print_text {
+        println!(
+            "Finished after {} seconds.",
+            start.elapsed().unwrap().as_secs()
+        );
+    }
+    if !passes {
+        std::process::exit(EXIT_FAIL);
+    }
 }
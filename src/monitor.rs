@@ -0,0 +1,393 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    behaviortree::Tbt,
+    check_memory_limit,
+    command_line_parser::{CommandLineArguments, MonitorSetting},
+    csv_reader::CsvSamples,
+    estimate_table_memory,
+    evaluation_result::Segment,
+    read_trace,
+    reporter::{monitor_header, verdict_to_line},
+    semantics::Semantics,
+    stl::Stl,
+    table::{Table, TableBackend},
+    trace::TIME_SIGNAL,
+    ProvidesTraceAndTree, Trace,
+};
+use std::{collections::HashMap, io::Write, time::SystemTime};
+
+/// Signal that is 1 for samples that are not known yet and 0 otherwise
+const UNKNOWN_SIGNAL: &str = "#unknown";
+
+/// Number of samples the tables are created for, it is doubled whenever the trace gets longer
+const INITIAL_CAPACITY: usize = 256;
+
+/*******************************
+ * Monitor Verdict
+ *******************************/
+#[derive(Clone, Debug)]
+/// Verdict of the online monitor after a sample has been added
+/// # Arguments
+/// * `sample` - Index of the sample
+/// * `robustness` - Robustness of the TBT if the trace ended with this sample
+/// * `worst_case` - Lowest robustness if the trace ends now or continues with up to lookahead unknown samples
+/// * `best_case` - Highest robustness if the trace ends now or continues with up to lookahead unknown samples
+/// * `active_leaf` - Segment of the best segmentation that contains the sample, i.e., the currently active leaf
+pub struct MonitorVerdict {
+    pub sample: usize,
+    pub robustness: f32,
+    pub worst_case: f32,
+    pub best_case: f32,
+    pub active_leaf: Option<Segment>,
+}
+
+/*******************************
+ * Online Monitor
+ *******************************/
+/// Evaluates a TBT incrementally while samples arrive. The robustness of a node or subformula for a segment
+/// only depends on the samples of the segment, hence, the entries of the tables remain valid when a sample is
+/// added and only the segments that end with the new sample are evaluated. Lazy evaluation is not supported.
+///
+/// The unknown future is represented by up to lookahead samples whose atomic propositions are evaluated
+/// optimistically (best case) or pessimistically (worst case), see Stl::set_unknown_robustness(). The entries
+/// of segments that end in these samples are removed before the next sample is added.
+pub struct OnlineMonitor {
+    tbt: Tbt,
+    optimistic: Tbt,
+    pessimistic: Tbt,
+    trace: Trace,
+    tree_table: Table,
+    formula_table: Table,
+    capacity: usize,
+    lookahead: usize,
+    semantics: Semantics,
+    table_backend: TableBackend,
+    memory_limit: Option<usize>,
+}
+
+impl OnlineMonitor {
+    /// Creates a monitor without samples
+    /// # Arguments
+    /// * `tbt` - TBT specification, bounds in seconds are resolved against the sampling period
+    /// * `sampling_period` - Time between two samples in seconds, required if the TBT has bounds in seconds
+    /// * `semantics` - Defines how robustness values are combined by STL operators and TBT nodes
+    /// * `table_backend` - Defines how the entries of the tables are stored
+    /// * `lookahead` - Maximal number of unknown samples for the worst and best case, None uses the horizon of the TBT,
+    ///   see Tbt::get_horizon()
+    /// * `memory_limit` - Limit of the tables in bytes that is checked whenever they grow, None means unlimited
+    pub fn new(
+        tbt: Tbt,
+        sampling_period: Option<f32>,
+        semantics: Semantics,
+        table_backend: TableBackend,
+        lookahead: Option<usize>,
+        memory_limit: Option<usize>,
+    ) -> Result<OnlineMonitor, String> {
+        let mut tbt = tbt;
+        tbt.resolve_bounds(sampling_period)?;
+        let mut optimistic = tbt.clone();
+        optimistic
+            .tree
+            .set_unknown_robustness(UNKNOWN_SIGNAL, f32::INFINITY);
+        let mut pessimistic = tbt.clone();
+        pessimistic
+            .tree
+            .set_unknown_robustness(UNKNOWN_SIGNAL, f32::NEG_INFINITY);
+        let mut signals: HashMap<String, Vec<f32>> = specification_signals(&tbt)
+            .into_iter()
+            .map(|name| (name, vec![]))
+            .collect();
+        signals.insert(UNKNOWN_SIGNAL.to_string(), vec![]);
+        // Unknown samples beyond the horizon do not change bounded operators
+        let lookahead = lookahead.or_else(|| tbt.get_horizon()).ok_or(
+            "the TBT has no bounded temporal operator or timeout, hence, --lookahead must be given",
+        )?;
+        let capacity = INITIAL_CAPACITY.max(lookahead + 1);
+        let (tree_table, formula_table) =
+            create_tables(&tbt, capacity, table_backend, memory_limit)?;
+        Ok(OnlineMonitor {
+            tbt,
+            optimistic,
+            pessimistic,
+            trace: Trace::new(signals)?,
+            tree_table,
+            formula_table,
+            capacity,
+            lookahead,
+            semantics,
+            table_backend,
+            memory_limit,
+        })
+    }
+
+    /// Returns the sorted names of the signals that each sample must contain
+    pub fn signals(&self) -> Vec<String> {
+        let mut signals: Vec<String> = self
+            .trace
+            .signals()
+            .keys()
+            .filter(|name| *name != UNKNOWN_SIGNAL)
+            .cloned()
+            .collect();
+        signals.sort();
+        signals
+    }
+
+    /// Number of samples that have been added
+    pub fn len(&self) -> usize {
+        self.trace.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trace.is_empty()
+    }
+
+    /// Adds a sample and returns the verdict for the trace that ends with it
+    /// # Arguments
+    /// * `sample` - Maps each signal to its value, other values are ignored
+    pub fn push(&mut self, sample: &HashMap<String, f32>) -> Result<MonitorVerdict, String> {
+        let mut known = sample.clone();
+        known.insert(UNKNOWN_SIGNAL.to_string(), 0.0);
+        self.trace.push(&known)?;
+        let length = self.trace.len();
+        if length + self.lookahead > self.capacity {
+            // The entries are computed again when they are needed
+            while length + self.lookahead > self.capacity {
+                self.capacity *= 2;
+            }
+            (self.tree_table, self.formula_table) = create_tables(
+                &self.tbt,
+                self.capacity,
+                self.table_backend,
+                self.memory_limit,
+            )?;
+        }
+        let upper = length - 1;
+        let robustness = self.evaluate(&self.tbt, upper);
+        let segmentation = self.tbt.tree.get_segmentation(
+            &self.tree_table,
+            &self.formula_table,
+            &self.trace,
+            0,
+            upper,
+            false,
+            self.semantics,
+        );
+        let active_leaf = Segment::from_segmentation(&segmentation)
+            .into_iter()
+            .rev()
+            .find(|segment| segment.is_leaf() && segment.lower <= upper && upper <= segment.upper);
+
+        // Worst and best case if the trace continues with unknown samples
+        let unknown: HashMap<String, f32> = self
+            .trace
+            .signals()
+            .keys()
+            .map(|name| {
+                let value = if name == UNKNOWN_SIGNAL {
+                    1.0
+                } else {
+                    f32::NAN
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        for _ in 0..self.lookahead {
+            self.trace.push(&unknown)?;
+        }
+        let mut best_case = robustness;
+        for number_unknown in 1..=self.lookahead {
+            best_case = best_case.max(self.evaluate(&self.optimistic, upper + number_unknown));
+        }
+        self.tree_table
+            .remove_uppers(length..length + self.lookahead);
+        self.formula_table
+            .remove_uppers(length..length + self.lookahead);
+        let mut worst_case = robustness;
+        for number_unknown in 1..=self.lookahead {
+            worst_case = worst_case.min(self.evaluate(&self.pessimistic, upper + number_unknown));
+        }
+        self.tree_table
+            .remove_uppers(length..length + self.lookahead);
+        self.formula_table
+            .remove_uppers(length..length + self.lookahead);
+        self.trace.truncate(length);
+
+        Ok(MonitorVerdict {
+            sample: upper,
+            robustness,
+            worst_case,
+            best_case,
+            active_leaf,
+        })
+    }
+
+    /// Evaluates the TBT for the segment from the first sample to upper
+    fn evaluate(&self, tbt: &Tbt, upper: usize) -> f32 {
+        tbt.tree.evaluate(
+            &mut HashMap::new(),
+            &self.tree_table,
+            &self.formula_table,
+            &self.trace,
+            0,
+            upper,
+            &SystemTime::now(),
+            false,
+            false,
+            self.semantics,
+            false,
+        )
+    }
+}
+
+/// Returns the sorted names of the signals that the atomic propositions of the TBT depend on
+fn specification_signals(tbt: &Tbt) -> Vec<String> {
+    let mut signals: Vec<String> = tbt
+        .tree
+        .get_atomics()
+        .into_iter()
+        .flat_map(|atomic| match atomic {
+            Stl::Atomic(_, names, _, _) => names.clone(),
+            _ => vec![],
+        })
+        .collect();
+    signals.sort();
+    signals.dedup();
+    signals
+}
+
+/// Creates the tree and formula table for the given number of samples if they do not exceed the memory limit
+fn create_tables(
+    tbt: &Tbt,
+    capacity: usize,
+    table_backend: TableBackend,
+    memory_limit: Option<usize>,
+) -> Result<(Table, Table), String> {
+    let estimate = estimate_table_memory(tbt, capacity, table_backend);
+    check_memory_limit(&estimate, memory_limit, table_backend)?;
+    Ok((
+        Table::new(tbt.get_number_nodes(), capacity, table_backend),
+        Table::with_horizons(tbt.get_formula_horizons(), capacity, table_backend),
+    ))
+}
+
+/*******************************
+ * Monitoring a Source
+ *******************************/
+/// Evaluates the samples of a csv-file, stdin, or the replayed logfile one at a time and writes the verdict
+/// of each sample as soon as it is known
+/// # Arguments
+/// * `specification` - TBT read from a specification file, the tree of U is used if None
+/// * `arguments` - Settings of the evaluation and output
+/// * `setting` - Source of the samples and lookahead
+/// * `output` - Destination of the verdicts, e.g., stdout
+pub fn run_monitor<U: ProvidesTraceAndTree>(
+    specification: Option<Tbt>,
+    arguments: &CommandLineArguments,
+    setting: &MonitorSetting,
+    output: &mut dyn Write,
+) -> Result<(), String> {
+    let tbt = specification.unwrap_or_else(|| U::get_tree(0));
+    // Replaying the logfile reads the whole trace, other sources are read while they are written
+    let replayed = match setting.source {
+        Some(_) => None,
+        None => Some(read_trace::<U>(
+            &arguments.logfile,
+            0,
+            &arguments.trace_setting,
+        )?),
+    };
+    let signals = specification_signals(&tbt);
+    let mut samples: Box<dyn Iterator<Item = Result<HashMap<String, f32>, String>>> =
+        match (&replayed, setting.source.as_deref()) {
+            (Some(trace), _) => Box::new((0..trace.len()).map(|index| {
+                Ok(trace
+                    .signals()
+                    .iter()
+                    .map(|(name, values)| (name.clone(), values[index]))
+                    .collect())
+            })),
+            (None, Some("-")) => Box::new(
+                CsvSamples::new(
+                    std::io::stdin().lock(),
+                    "stdin",
+                    &signals,
+                    arguments.trace_setting.bad_value_policy,
+                )
+                .map_err(|e| e.to_string())?
+                .with_optional_column(TIME_SIGNAL)
+                .map(|sample| sample.map_err(|e| e.to_string())),
+            ),
+            (None, source) => {
+                let source = source.expect("The logfile is replayed if there is no source");
+                let file = std::fs::File::open(source)
+                    .map_err(|e| format!("Could not read {source}: {e}"))?;
+                Box::new(
+                    CsvSamples::new(
+                        file,
+                        source,
+                        &signals,
+                        arguments.trace_setting.bad_value_policy,
+                    )
+                    .map_err(|e| e.to_string())?
+                    .with_optional_column(TIME_SIGNAL)
+                    .map(|sample| sample.map_err(|e| e.to_string())),
+                )
+            }
+        };
+    // Samples of a source are not resampled, hence, the sampling period is the difference of the first two
+    // timestamps of its time column and --rate is only used if the source has no time column
+    let mut first_samples = vec![];
+    let sampling_period = match (&replayed, setting.source.as_deref()) {
+        (Some(trace), _) => trace.sampling_period(),
+        (None, source) => {
+            if let Some(sample) = samples.next() {
+                first_samples.push(sample?);
+            }
+            let time = |sample: &HashMap<String, f32>| sample.get(TIME_SIGNAL).copied();
+            let sampling_period = match first_samples.first().and_then(time) {
+                Some(first) => {
+                    if let Some(sample) = samples.next() {
+                        first_samples.push(sample?);
+                    }
+                    first_samples
+                        .get(1)
+                        .and_then(time)
+                        .map(|second| second - first)
+                }
+                None => arguments.trace_setting.rate.map(|rate| 1.0 / rate),
+            };
+            if sampling_period.is_none() {
+                if let Err(e) = tbt.clone().resolve_bounds(None) {
+                    return Err(format!(
+                        "{e}, but the samples of {} have no column '{TIME_SIGNAL}' and --rate is not given",
+                        source.unwrap_or_default()
+                    ));
+                }
+            }
+            sampling_period
+        }
+    };
+    let mut monitor = OnlineMonitor::new(
+        tbt,
+        sampling_period,
+        arguments.semantics,
+        arguments.table_backend,
+        setting.lookahead,
+        arguments.memory_limit,
+    )?;
+    let samples = first_samples.into_iter().map(Ok).chain(samples);
+    let write_error = |e: std::io::Error| format!("Could not write verdict: {e}");
+    if let Some(header) = monitor_header(arguments.output_format) {
+        output.write_all(header.as_bytes()).map_err(write_error)?;
+    }
+    for sample in samples {
+        let verdict = monitor.push(&sample?)?;
+        let line = verdict_to_line(&verdict, arguments.output_format)?;
+        output.write_all(line.as_bytes()).map_err(write_error)?;
+        output.flush().map_err(write_error)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,619 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    behaviortree::{Tbt, TbtBuilder, TbtNode},
+    expression::{BinaryOperator, Expression, UnaryOperator},
+    stl::{Bound, Stl},
+    AtomicRegistry,
+};
+use std::fmt;
+
+/*
+ * Grammar of the specification language. Operator names follow the README.
+ *
+ *  T := Leaf(name, S)
+ *     | Fallback([T, ..., T])
+ *     | Parallel(m, [T, ..., T])
+ *     | Sequence([T, ..., T])          (more than two children are nested to the right)
+ *     | Timeout(t, T)
+ *     | Kleene(n, T)
+ *
+ *  S := Atomic(name) | Atomic(E)
+ *     | Conjunction(S, S) | Disjunction(S, S) | Implies(S, S) | Iff(S, S) | Neg(S) | Next(S)
+ *     | Eventually(S) | Globally(S) | Until(S, S) | Release(S, S)
+ *     | EventuallyInterval(l, u, S) | GloballyInterval(l, u, S) | UntilInterval(l, u, S, S)
+ *     | ReleaseInterval(l, u, S, S)
+ *     | Previous(S) | Once(S) | Historically(S) | Since(S, S)
+ *     | OnceInterval(l, u, S) | HistoricallyInterval(l, u, S) | SinceInterval(l, u, S, S)
+ *
+ *  E := E + E | E - E | E * E | E / E | -E | (E) | number | pi | signal
+ *     | abs(E) | sqrt(E) | sin(E) | cos(E) | wrap(E) | min(E, E) | max(E, E) | atan2(E, E)
+ *
+ * Atomic(name) refers to a registered atomic proposition, any other identifier in an
+ * expression refers to a signal of the trace. Signal names that are no identifiers can be
+ * written as double quoted strings.
+ * Bounds of intervals and timeouts are numbers of samples, e.g., 1000, or durations in seconds,
+ * e.g., 5s or 2.5s, that are resolved against the sampling period of the trace.
+ * Names are identifiers or double quoted strings, the upper bound of an interval may be `inf`,
+ * lists may end with a trailing comma and everything after `#` or `//` until the end of the line
+ * is a comment.
+ */
+
+/// Error produced while parsing a specification including its position
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/*******************************
+ * Lexer
+ *******************************/
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(String),
+    Text(String),
+    LeftParenthesis,
+    RightParenthesis,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Identifier(s) => write!(f, "'{s}'"),
+            Token::Number(s) => write!(f, "number '{s}'"),
+            Token::Text(s) => write!(f, "string \"{s}\""),
+            Token::LeftParenthesis => write!(f, "'('"),
+            Token::RightParenthesis => write!(f, "')'"),
+            Token::LeftBracket => write!(f, "'['"),
+            Token::RightBracket => write!(f, "']'"),
+            Token::Comma => write!(f, "','"),
+            Token::Plus => write!(f, "'+'"),
+            Token::Minus => write!(f, "'-'"),
+            Token::Star => write!(f, "'*'"),
+            Token::Slash => write!(f, "'/'"),
+            Token::End => write!(f, "end of input"),
+        }
+    }
+}
+
+/// A token together with its line and column (both starting at 1)
+type Located = (Token, usize, usize);
+
+fn tokenize(input: &str) -> Result<Vec<Located>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut i, mut line, mut line_start) = (0, 1, 0);
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i - line_start + 1;
+        if c == '\n' {
+            i += 1;
+            line += 1;
+            line_start = i;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        // Comments
+        if c == '#' || (c == '/' && chars.get(i + 1) == Some(&'/')) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        let token = match c {
+            '(' => Token::LeftParenthesis,
+            ')' => Token::RightParenthesis,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            ',' => Token::Comma,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '"' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\n' {
+                        break;
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() || chars[i] != '"' {
+                    return Err(ParseError {
+                        line,
+                        column,
+                        message: String::from("unterminated string"),
+                    });
+                }
+                tokens.push((Token::Text(chars[start..i].iter().collect()), line, column));
+                i += 1;
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                    // Exponent with sign, e.g., 1e-3
+                    if (chars[i] == 'e' || chars[i] == 'E')
+                        && matches!(chars.get(i + 1), Some('+') | Some('-'))
+                        && chars.get(i + 2).is_some_and(|c| c.is_ascii_digit())
+                    {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                tokens.push((
+                    Token::Number(chars[start..i].iter().collect()),
+                    line,
+                    column,
+                ));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push((
+                    Token::Identifier(chars[start..i].iter().collect()),
+                    line,
+                    column,
+                ));
+                continue;
+            }
+            c => {
+                return Err(ParseError {
+                    line,
+                    column,
+                    message: format!("unexpected character '{c}'"),
+                })
+            }
+        };
+        i += 1;
+        tokens.push((token, line, column));
+    }
+    tokens.push((Token::End, line, chars.len() - line_start + 1));
+    Ok(tokens)
+}
+
+/*******************************
+ * Parser
+ *******************************/
+struct Parser<'a> {
+    tokens: Vec<Located>,
+    position: usize,
+    atomics: &'a AtomicRegistry,
+    builder: &'a TbtBuilder,
+}
+
+impl<'a> Parser<'a> {
+    fn new(
+        input: &str,
+        atomics: &'a AtomicRegistry,
+        builder: &'a TbtBuilder,
+    ) -> Result<Self, ParseError> {
+        Ok(Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            atomics,
+            builder,
+        })
+    }
+
+    fn peek(&self) -> &Located {
+        &self.tokens[self.position]
+    }
+
+    fn next(&mut self) -> Located {
+        let token = self.tokens[self.position].clone();
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, (_, line, column): &Located, message: String) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: *line,
+            column: *column,
+            message,
+        })
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let token = self.next();
+        if token.0 == expected {
+            Ok(())
+        } else {
+            self.error(&token, format!("expected {expected} but found {}", token.0))
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<(), ParseError> {
+        let token = self.next();
+        if token.0 == Token::End {
+            Ok(())
+        } else {
+            self.error(
+                &token,
+                format!("expected end of input but found {}", token.0),
+            )
+        }
+    }
+
+    /// Parses a name which is either an identifier or a quoted string
+    fn name(&mut self) -> Result<String, ParseError> {
+        let token = self.next();
+        match &token.0 {
+            Token::Identifier(name) | Token::Text(name) => Ok(name.clone()),
+            other => self.error(&token, format!("expected a name but found {other}")),
+        }
+    }
+
+    /// Parses a non-negative integer, `inf` is accepted if `allow_infinity` is set
+    fn integer(
+        &mut self,
+        what: &str,
+        allow_infinity: bool,
+    ) -> Result<(usize, Located), ParseError> {
+        let token = self.next();
+        match &token.0 {
+            Token::Number(number) => match number.parse::<usize>() {
+                Ok(value) => Ok((value, token)),
+                Err(_) => self.error(
+                    &token,
+                    format!("expected a non-negative integer as {what} but found '{number}'"),
+                ),
+            },
+            Token::Identifier(identifier) if allow_infinity && identifier == "inf" => {
+                Ok((usize::MAX, token))
+            }
+            other => self.error(
+                &token,
+                format!("expected a non-negative integer as {what} but found {other}"),
+            ),
+        }
+    }
+
+    /// Parses a bound, i.e., a non-negative integer (samples) or a duration with suffix s (seconds)
+    fn bound(&mut self, what: &str, allow_infinity: bool) -> Result<(Bound, Located), ParseError> {
+        if let Token::Number(number) = self.peek().0.clone() {
+            if let Some(seconds) = number.strip_suffix('s') {
+                let token = self.next();
+                return match seconds.parse::<f32>() {
+                    Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => {
+                        Ok((Bound::Seconds(seconds), token))
+                    }
+                    _ => self.error(
+                        &token,
+                        format!("expected a non-negative duration as {what} but found '{number}'"),
+                    ),
+                };
+            }
+        }
+        let (samples, token) = self.integer(what, allow_infinity)?;
+        Ok((Bound::Samples(samples), token))
+    }
+
+    /// Parses the bounds `l, u` of an interval operator
+    fn interval(&mut self) -> Result<(Bound, Bound), ParseError> {
+        let (lower, _) = self.bound("lower bound", false)?;
+        self.expect(Token::Comma)?;
+        let (upper, upper_token) = self.bound("upper bound", true)?;
+        // Bounds of different units are checked when they are resolved against the sampling period
+        let is_malformed = match (lower, upper) {
+            (Bound::Samples(l), Bound::Samples(u)) => l > u,
+            (Bound::Seconds(l), Bound::Seconds(u)) => l > u,
+            _ => false,
+        };
+        if is_malformed {
+            return self.error(
+                &upper_token,
+                format!("malformed interval [{lower},{upper}]: lower bound exceeds upper bound"),
+            );
+        }
+        Ok((lower, upper))
+    }
+
+    /// Parses `[T, ..., T]`
+    fn tbt_list(&mut self) -> Result<Vec<TbtNode>, ParseError> {
+        self.expect(Token::LeftBracket)?;
+        let mut children = vec![self.tbt()?];
+        while self.peek().0 == Token::Comma {
+            self.next();
+            // Allows a trailing comma
+            if self.peek().0 == Token::RightBracket {
+                break;
+            }
+            children.push(self.tbt()?);
+        }
+        self.expect(Token::RightBracket)?;
+        Ok(children)
+    }
+
+    fn tbt(&mut self) -> Result<TbtNode, ParseError> {
+        let token = self.next();
+        let operator = match &token.0 {
+            Token::Identifier(operator) => operator.clone(),
+            other => {
+                return self.error(&token, format!("expected a TBT operator but found {other}"))
+            }
+        };
+        self.expect(Token::LeftParenthesis)?;
+        let node = match operator.as_str() {
+            "Leaf" => {
+                let name = self.name()?;
+                self.expect(Token::Comma)?;
+                self.builder.leaf(self.stl()?, name)
+            }
+            "Fallback" => self.builder.fallback(self.tbt_list()?),
+            "Parallel" => {
+                let (m, m_token) = self.integer("m", false)?;
+                self.expect(Token::Comma)?;
+                let children = self.tbt_list()?;
+                if m == 0 || m > children.len() {
+                    return self.error(
+                        &m_token,
+                        format!(
+                            "Parallel requires 1 <= m <= {} but m is {m}",
+                            children.len()
+                        ),
+                    );
+                }
+                self.builder.parallel(m, children)
+            }
+            "Sequence" => {
+                let list_token = self.peek().clone();
+                let mut children = self.tbt_list()?;
+                if children.len() < 2 {
+                    return self.error(
+                        &list_token,
+                        String::from("Sequence requires at least two children"),
+                    );
+                }
+                let mut right_child = children.pop().unwrap();
+                while let Some(left_child) = children.pop() {
+                    right_child = self.builder.sequence(left_child, right_child);
+                }
+                right_child
+            }
+            "Timeout" => {
+                let (t, t_token) = self.bound("t", false)?;
+                if t == Bound::Samples(0) || t == Bound::Seconds(0.0) {
+                    return self.error(&t_token, String::from("Timeout requires t > 0"));
+                }
+                self.expect(Token::Comma)?;
+                self.builder.timeout_bound(t, self.tbt()?)
+            }
+            "Kleene" => {
+                let (n, _) = self.integer("n", false)?;
+                self.expect(Token::Comma)?;
+                self.builder.kleene(n, self.tbt()?)
+            }
+            _ => return self.error(&token, format!("unknown TBT operator '{operator}'")),
+        };
+        self.expect(Token::RightParenthesis)?;
+        Ok(node)
+    }
+
+    /// Parses `term (('+' | '-') term)*`
+    fn expression(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.term()?;
+        loop {
+            let operator = match self.peek().0 {
+                Token::Plus => BinaryOperator::Add,
+                Token::Minus => BinaryOperator::Sub,
+                _ => return Ok(expression),
+            };
+            self.next();
+            expression = Expression::binary(operator, expression, self.term()?);
+        }
+    }
+
+    /// Parses `factor (('*' | '/') factor)*`
+    fn term(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.factor()?;
+        loop {
+            let operator = match self.peek().0 {
+                Token::Star => BinaryOperator::Mul,
+                Token::Slash => BinaryOperator::Div,
+                _ => return Ok(expression),
+            };
+            self.next();
+            expression = Expression::binary(operator, expression, self.factor()?);
+        }
+    }
+
+    /// Parses negations, constants, signals, function calls, and parenthesized expressions
+    fn factor(&mut self) -> Result<Expression, ParseError> {
+        let token = self.next();
+        match &token.0 {
+            Token::Minus => match self.factor()? {
+                Expression::Constant(c) => Ok(Expression::constant(-c)),
+                child => Ok(Expression::unary(UnaryOperator::Neg, child)),
+            },
+            Token::Number(number) => match number.parse::<f32>() {
+                Ok(value) => Ok(Expression::constant(value)),
+                Err(_) => self.error(&token, format!("malformed number '{number}'")),
+            },
+            Token::LeftParenthesis => {
+                let expression = self.expression()?;
+                self.expect(Token::RightParenthesis)?;
+                Ok(expression)
+            }
+            Token::Text(name) => Ok(Expression::signal(name)),
+            Token::Identifier(name) => {
+                if self.peek().0 != Token::LeftParenthesis {
+                    return Ok(match name.as_str() {
+                        "pi" => Expression::constant(std::f32::consts::PI),
+                        _ => Expression::signal(name),
+                    });
+                }
+                self.next();
+                let expression = if let Some(operator) = UnaryOperator::from_name(name) {
+                    Expression::unary(operator, self.expression()?)
+                } else if let Some(operator) = BinaryOperator::from_name(name) {
+                    let l_child = self.expression()?;
+                    self.expect(Token::Comma)?;
+                    Expression::binary(operator, l_child, self.expression()?)
+                } else {
+                    return self.error(&token, format!("unknown function '{name}'"));
+                };
+                self.expect(Token::RightParenthesis)?;
+                Ok(expression)
+            }
+            other => self.error(&token, format!("expected an expression but found {other}")),
+        }
+    }
+
+    fn stl(&mut self) -> Result<Stl, ParseError> {
+        let token = self.next();
+        let operator = match &token.0 {
+            Token::Identifier(operator) => operator.clone(),
+            other => {
+                return self.error(
+                    &token,
+                    format!("expected an STL operator but found {other}"),
+                )
+            }
+        };
+        self.expect(Token::LeftParenthesis)?;
+        let formula = match operator.as_str() {
+            "Atomic" => {
+                let expression = self.expression()?;
+                match &expression {
+                    Expression::Signal(name) if self.atomics.contains_key(name) => {
+                        let (names, function) = &self.atomics[name];
+                        self.builder
+                            .atomic_named(name, names.clone(), function.clone())
+                    }
+                    _ => self.builder.atomic_expression(expression),
+                }
+            }
+            "Conjunction" | "Disjunction" | "Implies" | "Iff" | "Until" | "Release" | "Since" => {
+                let left_child = self.stl()?;
+                self.expect(Token::Comma)?;
+                let right_child = self.stl()?;
+                match operator.as_str() {
+                    "Conjunction" => self.builder.conjunction(left_child, right_child),
+                    "Disjunction" => self.builder.disjunction(left_child, right_child),
+                    "Implies" => self.builder.implies(left_child, right_child),
+                    "Iff" => self.builder.iff(left_child, right_child),
+                    "Until" => self.builder.until(left_child, right_child),
+                    "Release" => self.builder.release(left_child, right_child),
+                    _ => self.builder.since(left_child, right_child),
+                }
+            }
+            "Neg" => self.builder.neg(self.stl()?),
+            "Next" => self.builder.next(self.stl()?),
+            "Eventually" => self.builder.eventually(self.stl()?),
+            "Globally" => self.builder.globally(self.stl()?),
+            "Previous" => self.builder.previous(self.stl()?),
+            "Once" => self.builder.once(self.stl()?),
+            "Historically" => self.builder.historically(self.stl()?),
+            "EventuallyInterval" | "GloballyInterval" | "OnceInterval" | "HistoricallyInterval" => {
+                let (lower, upper) = self.interval()?;
+                self.expect(Token::Comma)?;
+                let child = self.stl()?;
+                match operator.as_str() {
+                    "EventuallyInterval" => {
+                        self.builder.eventually_interval_bounds(lower, upper, child)
+                    }
+                    "GloballyInterval" => {
+                        self.builder.globally_interval_bounds(lower, upper, child)
+                    }
+                    "OnceInterval" => self.builder.once_interval_bounds(lower, upper, child),
+                    _ => self
+                        .builder
+                        .historically_interval_bounds(lower, upper, child),
+                }
+            }
+            "UntilInterval" | "ReleaseInterval" | "SinceInterval" => {
+                let (lower, upper) = self.interval()?;
+                self.expect(Token::Comma)?;
+                let left_child = self.stl()?;
+                self.expect(Token::Comma)?;
+                let right_child = self.stl()?;
+                match operator.as_str() {
+                    "UntilInterval" => {
+                        self.builder
+                            .until_interval_bounds(lower, upper, left_child, right_child)
+                    }
+                    "ReleaseInterval" => {
+                        self.builder
+                            .release_interval_bounds(lower, upper, left_child, right_child)
+                    }
+                    _ => self
+                        .builder
+                        .since_interval_bounds(lower, upper, left_child, right_child),
+                }
+            }
+            _ => return self.error(&token, format!("unknown STL operator '{operator}'")),
+        };
+        self.expect(Token::RightParenthesis)?;
+        Ok(formula)
+    }
+}
+
+/// Parses a TBT specification
+/// # Arguments
+/// * `input` - Specification text
+/// * `atomics` - Atomic propositions that can be referenced by name using `Atomic(name)`
+pub fn parse_tbt(input: &str, atomics: &AtomicRegistry) -> Result<Tbt, ParseError> {
+    let builder = TbtBuilder::new();
+    let mut parser = Parser::new(input, atomics, &builder)?;
+    let tree = parser.tbt()?;
+    parser.expect_end()?;
+    Ok(builder.build(tree))
+}
+
+/// Parses a single STL formula
+/// # Arguments
+/// * `input` - Formula text
+/// * `atomics` - Atomic propositions that can be referenced by name using `Atomic(name)`
+/// * `builder` - Allocates the formula indices, e.g., the builder of the TBT that uses the formula
+pub fn parse_stl(
+    input: &str,
+    atomics: &AtomicRegistry,
+    builder: &TbtBuilder,
+) -> Result<Stl, ParseError> {
+    let mut parser = Parser::new(input, atomics, builder)?;
+    let formula = parser.stl()?;
+    parser.expect_end()?;
+    Ok(formula)
+}
+
+/// Parses an arithmetic expression, e.g., `2.5 - sqrt((uas_x - ship_x) * (uas_x - ship_x))`
+/// # Arguments
+/// * `input` - Expression text
+pub fn parse_expression(input: &str) -> Result<Expression, ParseError> {
+    let atomics = AtomicRegistry::new();
+    let builder = TbtBuilder::new();
+    let mut parser = Parser::new(input, &atomics, &builder)?;
+    let expression = parser.expression()?;
+    parser.expect_end()?;
+    Ok(expression)
+}
@@ -0,0 +1,755 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    batch::BatchEntry,
+    behaviortree::Tbt,
+    evaluation_result::{EvaluationResult, Segment},
+    monitor::MonitorVerdict,
+    MemoryEstimate, TableBackend, Trace,
+};
+use num_format::{Locale, ToFormattedString};
+use serde::Serialize;
+use std::str::FromStr;
+
+/*******************************
+ * Output Settings
+ *******************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Format used to write an EvaluationResult
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "unknown output format '{s}', expected json, csv, or text"
+            )),
+        }
+    }
+}
+
+/// Maps positions of the (subsampled) trace to the logfile
+/// # Arguments
+/// * `number_skipped_entries` - used for subsampling ie only every n-th entry of the logfile is in the trace
+/// * `time` - Timestamps of the trace, i.e., its time axis if known
+pub struct SampleMapping {
+    pub number_skipped_entries: usize,
+    pub time: Option<Vec<f32>>,
+}
+
+impl SampleMapping {
+    pub fn new(trace: &Trace, number_skipped_entries: usize) -> Self {
+        SampleMapping {
+            number_skipped_entries,
+            time: trace.time().map(|time| time.to_vec()),
+        }
+    }
+
+    /// Returns the index in the logfile given a position of the trace
+    pub fn original_index(&self, index: usize) -> usize {
+        index * usize::max(1, self.number_skipped_entries)
+    }
+
+    /// Returns the timestamp given a position of the trace
+    pub fn timestamp(&self, index: usize) -> Option<f32> {
+        self.time.as_ref().and_then(|time| time.get(index).copied())
+    }
+}
+
+/*******************************
+ * Text Reporter
+ *******************************/
+/// Prints the setting of an evaluation
+/// # Arguments
+/// * `logfile` - Location of logfile
+/// * `number_skipped_entries` - used for subsampling ie number of entries in the logfile that are skipped
+/// * `lazy_evaluation` - enables/disables lazy evaluation
+/// * `sub_sampling` - enables/disables sub sampling
+/// * `trace` - Trace that is evaluated
+/// * `tbt` - TBT that is evaluated
+pub fn print_setting(
+    logfile: &str,
+    number_skipped_entries: usize,
+    lazy_evaluation: bool,
+    sub_sampling: bool,
+    trace: &Trace,
+    tbt: &Tbt,
+) {
+    println!(
+        "SETTING:\n\tLogfile: {logfile}\n\tApproximations: lazy evaluation={lazy_evaluation}, subsampling={sub_sampling}(delta: {number_skipped_entries})\n\tTrace length: {}\n\nTemporal behavior tree:\n{}\n",
+        trace.len(),
+        tbt.tree.pretty_print(true, 2),
+    );
+}
+
+/// Prints the estimated memory of the tables before they are allocated
+/// # Arguments
+/// * `estimate` - Estimate returned by estimate_table_memory()
+/// * `table_backend` - Backend of the tables
+pub fn print_memory_estimate(estimate: &MemoryEstimate, table_backend: TableBackend) {
+    let megabytes = |bytes: usize| bytes as f64 / 1e6;
+    if estimate.initial == estimate.maximum {
+        println!(
+            "MEMORY:\n\t{table_backend} tables: {:.1} MB\n",
+            megabytes(estimate.initial)
+        );
+    } else {
+        println!(
+            "MEMORY:\n\t{table_backend} tables: {:.1} MB initially, at most {:.1} MB\n",
+            megabytes(estimate.initial),
+            megabytes(estimate.maximum)
+        );
+    }
+}
+
+/// Prints the statistics, the best segmentation, and the alternative segmentations
+/// # Arguments
+/// * `result` - Result returned by evaluate()
+/// * `print_leaf_segments_only` - If true, only segments of leaves are printed
+pub fn print_result(result: &EvaluationResult, print_leaf_segments_only: bool) {
+    print!("{}", result_to_string(result, print_leaf_segments_only));
+}
+
+/// Returns the statistics, the best segmentation, and the alternative segmentations as text
+/// # Arguments
+/// * `result` - Result returned by evaluate()
+/// * `only_leaves` - If true, only segments of leaves are printed
+pub fn result_to_string(result: &EvaluationResult, only_leaves: bool) -> String {
+    let statistics = &result.statistics;
+    let mut text = format!(
+        "Created tree table with {} entries.\nCreated formula table with {} entries.\n\n",
+        statistics.tree_entries.to_formatted_string(&Locale::en),
+        statistics.formula_entries.to_formatted_string(&Locale::en)
+    );
+    text += &format!(
+        "\nStatistics: Robustness value is {} with {} total tree lookups and {} formula lookups\nGet segmentation after {} seconds.\n",
+        result.robustness,
+        statistics.tree_lookups.to_formatted_string(&Locale::en),
+        statistics.formula_lookups.to_formatted_string(&Locale::en),
+        result.timings.evaluation.as_secs()
+    );
+    text += &format!(
+        "{} segmentation with robustness {} and subsampling delta of {} is:\n{}\n",
+        if result.is_approximate {
+            "Approximate"
+        } else {
+            "Best"
+        },
+        result.segmentation_robustness,
+        result.delta_rho_skipped,
+        segmentation_to_string(&result.segmentation, only_leaves)
+    );
+    if !result.alternatives.is_empty() {
+        text += "\n\nAlternatives:\n";
+        for alternative in &result.alternatives {
+            text += &format!(
+                "Segmentation with remaining tau difference of {} and robustness of {} is:\n{}\n",
+                alternative.remaining_tau_dif,
+                alternative.robustness,
+                segmentation_to_string(&alternative.segmentation, only_leaves)
+            );
+        }
+    }
+    text
+}
+
+/// Returns one line per segment, i.e., lower, upper, value, and the TBT node
+/// # Arguments
+/// * `segmentation` - Segments that are printed
+/// * `only_leaves` - If true, only segments of leaves are printed
+pub fn segmentation_to_string(segmentation: &[Segment], only_leaves: bool) -> String {
+    let mut seg_string = String::new();
+    for segment in segmentation {
+        if only_leaves && !segment.is_leaf() {
+            continue;
+        }
+        seg_string += &format!(
+            "lower: {:10}   upper: {:10}   value: {:15}  segment: {}\n",
+            segment.lower, segment.upper, segment.value, segment.description,
+        );
+    }
+    seg_string
+}
+
+/*******************************
+ * JSON and CSV Export
+ *******************************/
+#[derive(Serialize)]
+struct ResultOutput {
+    robustness: f32,
+    segmentation_robustness: f32,
+    is_approximate: bool,
+    delta_rho_skipped: f32,
+    number_skipped_entries: usize,
+    statistics: StatisticsOutput,
+    timings: TimingsOutput,
+    segmentation: Option<SegmentOutput>,
+    alternatives: Vec<AlternativeOutput>,
+}
+
+#[derive(Serialize)]
+struct StatisticsOutput {
+    tree_entries: usize,
+    formula_entries: usize,
+    tree_lookups: usize,
+    formula_lookups: usize,
+}
+
+/// Timings in seconds
+#[derive(Serialize)]
+struct TimingsOutput {
+    evaluation: f64,
+    segmentation: f64,
+    alternatives: f64,
+}
+
+#[derive(Serialize)]
+struct AlternativeOutput {
+    remaining_tau_dif: usize,
+    robustness: f32,
+    segmentation: Option<SegmentOutput>,
+}
+
+/// A segment including the segments of its children
+#[derive(Serialize)]
+struct SegmentOutput {
+    node_id: usize,
+    kind: &'static str,
+    name: Option<String>,
+    lower: usize,
+    upper: usize,
+    original_lower: usize,
+    original_upper: usize,
+    start_time: Option<f32>,
+    end_time: Option<f32>,
+    robustness: f32,
+    children: Vec<SegmentOutput>,
+}
+
+impl SegmentOutput {
+    /// Builds the nested segment of the segment at the given position
+    fn new(segmentation: &[Segment], position: usize, mapping: &SampleMapping) -> Self {
+        let segment = &segmentation[position];
+        let children = segmentation
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| child.parent == Some(position))
+            .map(|(child_position, _)| SegmentOutput::new(segmentation, child_position, mapping))
+            .collect();
+        SegmentOutput {
+            node_id: segment.node_id,
+            kind: segment.kind,
+            name: segment.name.clone(),
+            lower: segment.lower,
+            upper: segment.upper,
+            original_lower: mapping.original_index(segment.lower),
+            original_upper: mapping.original_index(segment.upper),
+            start_time: mapping.timestamp(segment.lower),
+            end_time: mapping.timestamp(segment.upper),
+            robustness: segment.value,
+            children,
+        }
+    }
+
+    /// Builds the nested segmentation starting at the root, i.e., the first segment
+    fn from_segmentation(segmentation: &[Segment], mapping: &SampleMapping) -> Option<Self> {
+        if segmentation.is_empty() {
+            None
+        } else {
+            Some(SegmentOutput::new(segmentation, 0, mapping))
+        }
+    }
+}
+
+/// Returns the result as JSON where each segment contains the segments of its children
+/// # Arguments
+/// * `result` - Result returned by evaluate()
+/// * `mapping` - Used to compute the original indices and timestamps of segments
+pub fn result_to_json(
+    result: &EvaluationResult,
+    mapping: &SampleMapping,
+) -> Result<String, String> {
+    let statistics = &result.statistics;
+    let output = ResultOutput {
+        robustness: result.robustness,
+        segmentation_robustness: result.segmentation_robustness,
+        is_approximate: result.is_approximate,
+        delta_rho_skipped: result.delta_rho_skipped,
+        number_skipped_entries: mapping.number_skipped_entries,
+        statistics: StatisticsOutput {
+            tree_entries: statistics.tree_entries,
+            formula_entries: statistics.formula_entries,
+            tree_lookups: statistics.tree_lookups,
+            formula_lookups: statistics.formula_lookups,
+        },
+        timings: TimingsOutput {
+            evaluation: result.timings.evaluation.as_secs_f64(),
+            segmentation: result.timings.segmentation.as_secs_f64(),
+            alternatives: result.timings.alternatives.as_secs_f64(),
+        },
+        segmentation: SegmentOutput::from_segmentation(&result.segmentation, mapping),
+        alternatives: result
+            .alternatives
+            .iter()
+            .map(|alternative| AlternativeOutput {
+                remaining_tau_dif: alternative.remaining_tau_dif,
+                robustness: alternative.robustness,
+                segmentation: SegmentOutput::from_segmentation(&alternative.segmentation, mapping),
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&output).map_err(|e| e.to_string())
+}
+
+/// Returns one CSV row per segment. The column segmentation is 0 for the best segmentation
+/// and i for the i-th alternative. The column parent refers to the position of the parent segment.
+/// # Arguments
+/// * `result` - Result returned by evaluate()
+/// * `mapping` - Used to compute the original indices and timestamps of segments
+/// * `only_leaves` - If true, only segments of leaves are written
+pub fn result_to_csv(
+    result: &EvaluationResult,
+    mapping: &SampleMapping,
+    only_leaves: bool,
+) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer
+        .write_record([
+            "segmentation",
+            "position",
+            "parent",
+            "node_id",
+            "kind",
+            "name",
+            "lower",
+            "upper",
+            "original_lower",
+            "original_upper",
+            "start_time",
+            "end_time",
+            "robustness",
+        ])
+        .map_err(|e| e.to_string())?;
+    let segmentations = std::iter::once(&result.segmentation).chain(
+        result
+            .alternatives
+            .iter()
+            .map(|alternative| &alternative.segmentation),
+    );
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    for (number, segmentation) in segmentations.enumerate() {
+        for (position, segment) in segmentation.iter().enumerate() {
+            if only_leaves && !segment.is_leaf() {
+                continue;
+            }
+            writer
+                .write_record([
+                    number.to_string(),
+                    position.to_string(),
+                    optional(segment.parent.map(|p| p.to_string())),
+                    segment.node_id.to_string(),
+                    segment.kind.to_string(),
+                    optional(segment.name.clone()),
+                    segment.lower.to_string(),
+                    segment.upper.to_string(),
+                    mapping.original_index(segment.lower).to_string(),
+                    mapping.original_index(segment.upper).to_string(),
+                    optional(mapping.timestamp(segment.lower).map(|t| t.to_string())),
+                    optional(mapping.timestamp(segment.upper).map(|t| t.to_string())),
+                    segment.value.to_string(),
+                ])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// Writes the result in the given format to a file or, if no file is given, to stdout
+/// # Arguments
+/// * `result` - Result returned by evaluate()
+/// * `mapping` - Used to compute the original indices and timestamps of segments
+/// * `format` - Output format
+/// * `output_file` - Location of the output file, stdout is used if None
+/// * `only_leaves` - If true, only segments of leaves are written (text and CSV)
+pub fn write_result(
+    result: &EvaluationResult,
+    mapping: &SampleMapping,
+    format: OutputFormat,
+    output_file: Option<&str>,
+    only_leaves: bool,
+) -> Result<(), String> {
+    let content = match format {
+        OutputFormat::Text => result_to_string(result, only_leaves),
+        OutputFormat::Json => result_to_json(result, mapping)?,
+        OutputFormat::Csv => result_to_csv(result, mapping, only_leaves)?,
+    };
+    match output_file {
+        Some(output_file) => std::fs::write(output_file, content)
+            .map_err(|e| format!("Could not write output {output_file}: {e}")),
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
+}
+
+/*******************************
+ * JUnit Report
+ *******************************/
+/// Escapes the characters of a text that are not allowed in XML attributes and elements
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Returns a JUnit XML report with one test suite for the logfile. The first test case checks the
+/// robustness of the TBT and each leaf segment of the best segmentation is a further test case,
+/// which fails if the robustness of the segment is below the threshold.
+/// # Arguments
+/// * `result` - Result returned by evaluate()
+/// * `mapping` - Used to compute the original indices of segments
+/// * `logfile` - Location of logfile, used as name of the test suite
+/// * `threshold` - Minimal robustness that passes
+pub fn result_to_junit(
+    result: &EvaluationResult,
+    mapping: &SampleMapping,
+    logfile: &str,
+    threshold: f32,
+) -> String {
+    let mut test_cases = vec![("robustness".to_string(), result.robustness)];
+    test_cases.extend(
+        result
+            .segmentation
+            .iter()
+            .filter(|segment| segment.is_leaf())
+            .map(|segment| {
+                // Leaves may occur several times, hence, the segment is part of the name
+                let name = format!(
+                    "{} [{}, {}]",
+                    segment.name.as_deref().unwrap_or(&segment.description),
+                    mapping.original_index(segment.lower),
+                    mapping.original_index(segment.upper)
+                );
+                (name, segment.value)
+            }),
+    );
+    // NaN values fail
+    let passes = |value: f32| value >= threshold;
+    let failures = test_cases
+        .iter()
+        .filter(|(_, value)| !passes(*value))
+        .count();
+    let logfile = escape_xml(logfile);
+    let time = result.timings.evaluation.as_secs_f64();
+    let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+    xml += &format!(
+        "<testsuites name=\"tbt-segmentation\" tests=\"{}\" failures=\"{failures}\" time=\"{time}\">\n",
+        test_cases.len()
+    );
+    xml += &format!(
+        "  <testsuite name=\"{logfile}\" tests=\"{}\" failures=\"{failures}\" time=\"{time}\">\n",
+        test_cases.len()
+    );
+    for (name, value) in test_cases {
+        let name = escape_xml(&name);
+        if passes(value) {
+            xml += &format!("    <testcase name=\"{name}\" classname=\"{logfile}\"/>\n");
+        } else {
+            xml += &format!(
+                "    <testcase name=\"{name}\" classname=\"{logfile}\">\n      <failure type=\"robustness\" message=\"robustness {value} is below the threshold {threshold}\"/>\n    </testcase>\n"
+            );
+        }
+    }
+    xml += "  </testsuite>\n</testsuites>\n";
+    xml
+}
+
+/// Writes the JUnit XML report to a file, see result_to_junit()
+/// # Arguments
+/// * `result` - Result returned by evaluate()
+/// * `mapping` - Used to compute the original indices of segments
+/// * `logfile` - Location of logfile, used as name of the test suite
+/// * `threshold` - Minimal robustness that passes
+/// * `output_file` - Location of the report
+pub fn write_junit(
+    result: &EvaluationResult,
+    mapping: &SampleMapping,
+    logfile: &str,
+    threshold: f32,
+    output_file: &str,
+) -> Result<(), String> {
+    std::fs::write(
+        output_file,
+        result_to_junit(result, mapping, logfile, threshold),
+    )
+    .map_err(|e| format!("Could not write JUnit report {output_file}: {e}"))
+}
+
+/*******************************
+ * Batch Summary
+ *******************************/
+/// Returns the leaf segments as name[original_lower, original_upper] separated by spaces
+fn segments_to_string(entry: &BatchEntry) -> String {
+    match &entry.outcome {
+        Ok(summary) => summary
+            .segments
+            .iter()
+            .map(|segment| {
+                format!(
+                    "{}[{}, {}]",
+                    segment.name, segment.original_lower, segment.original_upper
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Err(_) => String::new(),
+    }
+}
+
+/// Returns the columns of an entry: log, robustness, branch, segments, runtime, and error
+fn batch_row(entry: &BatchEntry) -> [String; 6] {
+    let runtime = format!("{:.3}", entry.runtime.as_secs_f64());
+    match &entry.outcome {
+        Ok(summary) => [
+            entry.log.clone(),
+            summary.robustness.to_string(),
+            summary.branch.clone().unwrap_or_default(),
+            segments_to_string(entry),
+            runtime,
+            String::new(),
+        ],
+        Err(e) => [
+            entry.log.clone(),
+            String::new(),
+            String::new(),
+            String::new(),
+            runtime,
+            e.clone(),
+        ],
+    }
+}
+
+const BATCH_HEADER: [&str; 6] = [
+    "log",
+    "robustness",
+    "branch",
+    "segments",
+    "runtime",
+    "error",
+];
+
+/// Returns an aligned table with one row per log and the number of failed logs
+/// # Arguments
+/// * `entries` - Entries returned by run_batch()
+pub fn batch_to_string(entries: &[BatchEntry]) -> String {
+    let rows: Vec<[String; 6]> = entries.iter().map(batch_row).collect();
+    let mut widths = BATCH_HEADER.map(|column| column.len());
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+    let header = BATCH_HEADER.map(|column| column.to_string());
+    let mut text = String::new();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{column:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        text += line.trim_end();
+        text += "\n";
+    }
+    let failed = entries
+        .iter()
+        .filter(|entry| entry.outcome.is_err())
+        .count();
+    text += &format!("{} logs evaluated, {failed} failed.\n", entries.len());
+    text
+}
+
+/// Returns one CSV row per log, segments are separated by spaces
+/// # Arguments
+/// * `entries` - Entries returned by run_batch()
+pub fn batch_to_csv(entries: &[BatchEntry]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer
+        .write_record(BATCH_HEADER)
+        .map_err(|e| e.to_string())?;
+    for entry in entries {
+        writer
+            .write_record(batch_row(entry))
+            .map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+#[derive(Serialize)]
+struct BatchEntryOutput<'a> {
+    log: &'a str,
+    runtime: f64,
+    robustness: Option<f32>,
+    is_approximate: Option<bool>,
+    branch: Option<&'a str>,
+    segments: Vec<LeafSegmentOutput<'a>>,
+    error: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct LeafSegmentOutput<'a> {
+    name: &'a str,
+    original_lower: usize,
+    original_upper: usize,
+    start_time: Option<f32>,
+    end_time: Option<f32>,
+}
+
+/// Returns the entries as JSON array
+/// # Arguments
+/// * `entries` - Entries returned by run_batch()
+pub fn batch_to_json(entries: &[BatchEntry]) -> Result<String, String> {
+    let output: Vec<BatchEntryOutput> = entries
+        .iter()
+        .map(|entry| match &entry.outcome {
+            Ok(summary) => BatchEntryOutput {
+                log: &entry.log,
+                runtime: entry.runtime.as_secs_f64(),
+                robustness: Some(summary.robustness),
+                is_approximate: Some(summary.is_approximate),
+                branch: summary.branch.as_deref(),
+                segments: summary
+                    .segments
+                    .iter()
+                    .map(|segment| LeafSegmentOutput {
+                        name: &segment.name,
+                        original_lower: segment.original_lower,
+                        original_upper: segment.original_upper,
+                        start_time: segment.start_time,
+                        end_time: segment.end_time,
+                    })
+                    .collect(),
+                error: None,
+            },
+            Err(e) => BatchEntryOutput {
+                log: &entry.log,
+                runtime: entry.runtime.as_secs_f64(),
+                robustness: None,
+                is_approximate: None,
+                branch: None,
+                segments: vec![],
+                error: Some(e),
+            },
+        })
+        .collect();
+    serde_json::to_string_pretty(&output).map_err(|e| e.to_string())
+}
+
+/// Writes the summary of a batch in the given format to a file or, if no file is given, to stdout
+/// # Arguments
+/// * `entries` - Entries returned by run_batch()
+/// * `format` - Output format
+/// * `output_file` - Location of the output file, stdout is used if None
+pub fn write_batch(
+    entries: &[BatchEntry],
+    format: OutputFormat,
+    output_file: Option<&str>,
+) -> Result<(), String> {
+    let content = match format {
+        OutputFormat::Text => batch_to_string(entries),
+        OutputFormat::Json => batch_to_json(entries)?,
+        OutputFormat::Csv => batch_to_csv(entries)?,
+    };
+    match output_file {
+        Some(output_file) => std::fs::write(output_file, content)
+            .map_err(|e| format!("Could not write output {output_file}: {e}")),
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
+}
+
+/*******************************
+ * Monitor Output
+ *******************************/
+#[derive(Serialize)]
+struct VerdictOutput<'a> {
+    sample: usize,
+    robustness: f32,
+    worst_case: f32,
+    best_case: f32,
+    active_leaf: Option<&'a str>,
+    active_since: Option<usize>,
+}
+
+impl<'a> VerdictOutput<'a> {
+    fn new(verdict: &'a MonitorVerdict) -> Self {
+        VerdictOutput {
+            sample: verdict.sample,
+            robustness: verdict.robustness,
+            worst_case: verdict.worst_case,
+            best_case: verdict.best_case,
+            active_leaf: verdict
+                .active_leaf
+                .as_ref()
+                .map(|leaf| leaf.name.as_deref().unwrap_or(&leaf.description)),
+            active_since: verdict.active_leaf.as_ref().map(|leaf| leaf.lower),
+        }
+    }
+}
+
+/// Returns the header of the lines written by the monitor, only CSV has a header
+/// # Arguments
+/// * `format` - Output format
+pub fn monitor_header(format: OutputFormat) -> Option<String> {
+    (format == OutputFormat::Csv)
+        .then(|| "sample,robustness,worst_case,best_case,active_leaf,active_since\n".to_string())
+}
+
+/// Returns the verdict of a sample as a single line, JSON is written as one object per line
+/// # Arguments
+/// * `verdict` - Verdict returned by OnlineMonitor::push()
+/// * `format` - Output format
+pub fn verdict_to_line(verdict: &MonitorVerdict, format: OutputFormat) -> Result<String, String> {
+    let output = VerdictOutput::new(verdict);
+    match format {
+        OutputFormat::Text => Ok(format!(
+            "sample: {:10}   robustness: {:15}   worst case: {:15}   best case: {:15}   active leaf: {}\n",
+            output.sample,
+            output.robustness,
+            output.worst_case,
+            output.best_case,
+            match (output.active_leaf, output.active_since) {
+                (Some(leaf), Some(since)) => format!("{leaf} since {since}"),
+                _ => "none".to_string(),
+            }
+        )),
+        OutputFormat::Json => serde_json::to_string(&output)
+            .map(|line| line + "\n")
+            .map_err(|e| e.to_string()),
+        OutputFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(vec![]);
+            writer.serialize(&output).map_err(|e| e.to_string())?;
+            let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+            String::from_utf8(bytes).map_err(|e| e.to_string())
+        }
+    }
+}
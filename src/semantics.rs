@@ -0,0 +1,189 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{fmt, str::FromStr};
+
+/*******************************
+ * Robustness Semantics
+ *******************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Defines how robustness values are combined by STL operators and TBT nodes.
+/// Conjunctive operators, e.g., Conjunction, Globally, or the two parts of a Sequence, use
+/// the conjunction of the semantics and disjunctive operators, e.g., Disjunction, Eventually,
+/// or the choices of a Fallback, use the disjunction.
+pub enum Semantics {
+    /// Spatial robustness using minimum and maximum
+    Classic,
+    /// Atomic propositions are mapped to +1 (satisfied) or -1 (violated), then minimum and maximum are used
+    Boolean,
+    /// Conjunctions are replaced by the arithmetic mean of the values, disjunctions use the maximum
+    Averaged,
+    /// Minimum and maximum are replaced by the log-sum-exp approximation with the given temperature
+    Smooth(f32),
+}
+
+/// Temperature of the smooth semantics if none is given, higher values approximate the classic semantics closer
+pub const DEFAULT_TEMPERATURE: f32 = 10.0;
+
+impl Semantics {
+    /// Maps the value of an atomic proposition
+    pub fn atomic(&self, value: f32) -> f32 {
+        match self {
+            Semantics::Boolean if value > 0.0 => 1.0,
+            Semantics::Boolean => -1.0,
+            _ => value,
+        }
+    }
+
+    /// Returns an empty conjunction, i.e., it evaluates to +inf until a value is added
+    pub fn conjunction(&self) -> Aggregation {
+        Aggregation::new(*self, true)
+    }
+
+    /// Returns an empty disjunction, i.e., it evaluates to -inf until a value is added
+    pub fn disjunction(&self) -> Aggregation {
+        Aggregation::new(*self, false)
+    }
+
+    /// Conjunction of two values
+    pub fn min(&self, left: f32, right: f32) -> f32 {
+        let mut conjunction = self.conjunction();
+        conjunction.add(left);
+        conjunction.add(right);
+        conjunction.value()
+    }
+
+    /// Disjunction of two values
+    pub fn max(&self, left: f32, right: f32) -> f32 {
+        let mut disjunction = self.disjunction();
+        disjunction.add(left);
+        disjunction.add(right);
+        disjunction.value()
+    }
+
+    /// True if conjunctions and disjunctions are minimum and maximum, which allows sliding windows
+    pub fn is_min_max(&self) -> bool {
+        matches!(self, Semantics::Classic | Semantics::Boolean)
+    }
+
+    /// Lazy evaluation stops as soon as the sign of a conjunction or disjunction is known,
+    /// which is not possible if values are averaged
+    pub fn supports_lazy_evaluation(&self) -> bool {
+        !matches!(self, Semantics::Averaged)
+    }
+}
+
+/// Accepts classic, boolean, averaged, smooth, or smooth:TEMPERATURE
+impl FromStr for Semantics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "classic" => Ok(Semantics::Classic),
+            None if s == "boolean" => Ok(Semantics::Boolean),
+            None if s == "averaged" => Ok(Semantics::Averaged),
+            None if s == "smooth" => Ok(Semantics::Smooth(DEFAULT_TEMPERATURE)),
+            Some(("smooth", temperature)) => match temperature.parse::<f32>() {
+                Ok(temperature) if temperature > 0.0 => Ok(Semantics::Smooth(temperature)),
+                _ => Err(format!(
+                    "temperature must be a positive number but was '{temperature}'"
+                )),
+            },
+            _ => Err(format!(
+                "unknown semantics '{s}', expected classic, boolean, averaged, or smooth"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Semantics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Semantics::Classic => write!(f, "classic"),
+            Semantics::Boolean => write!(f, "boolean"),
+            Semantics::Averaged => write!(f, "averaged"),
+            Semantics::Smooth(temperature) => write!(f, "smooth:{temperature}"),
+        }
+    }
+}
+
+/*******************************
+ * Aggregation
+ *******************************/
+#[derive(Clone, Copy, Debug)]
+/// Conjunction or disjunction of robustness values that are added one after another, e.g., by Globally
+/// # Arguments
+/// * `semantics` - Defines how values are combined
+/// * `is_conjunction` - Distinguishes conjunction and disjunction
+/// * `value` - Combination of the added values, the sum of the finite values if they are averaged
+/// * `count` - Number of finite values that are averaged
+pub struct Aggregation {
+    semantics: Semantics,
+    is_conjunction: bool,
+    value: f32,
+    count: usize,
+}
+
+impl Aggregation {
+    fn new(semantics: Semantics, is_conjunction: bool) -> Aggregation {
+        let value = if is_conjunction {
+            f32::INFINITY
+        } else {
+            f32::NEG_INFINITY
+        };
+        Aggregation {
+            semantics,
+            is_conjunction,
+            value,
+            count: 0,
+        }
+    }
+
+    pub fn add(&mut self, value: f32) {
+        self.value = match (self.semantics, self.is_conjunction) {
+            (Semantics::Classic | Semantics::Boolean, true) => f32::min(self.value, value),
+            (Semantics::Classic | Semantics::Boolean, false) | (Semantics::Averaged, false) => {
+                f32::max(self.value, value)
+            }
+            // +inf is neutral and -inf cannot be compensated by other values
+            (Semantics::Averaged, true) => {
+                if value == f32::INFINITY || self.value == f32::NEG_INFINITY {
+                    self.value
+                } else if value == f32::NEG_INFINITY {
+                    f32::NEG_INFINITY
+                } else {
+                    self.count += 1;
+                    if self.count == 1 {
+                        value
+                    } else {
+                        self.value + value
+                    }
+                }
+            }
+            (Semantics::Smooth(temperature), true) => {
+                -log_sum_exp(-self.value, -value, temperature)
+            }
+            (Semantics::Smooth(temperature), false) => log_sum_exp(self.value, value, temperature),
+        };
+    }
+
+    pub fn value(&self) -> f32 {
+        match self.semantics {
+            Semantics::Averaged if self.is_conjunction && self.count > 1 => {
+                self.value / self.count as f32
+            }
+            _ => self.value,
+        }
+    }
+}
+
+/// Smooth maximum of two values that is numerically stable and exact if a value is infinite
+fn log_sum_exp(left: f32, right: f32, temperature: f32) -> f32 {
+    let max = f32::max(left, right);
+    if max.is_infinite() {
+        max
+    } else {
+        max + (f32::exp(temperature * (left - max)) + f32::exp(temperature * (right - max))).ln()
+            / temperature
+    }
+}
@@ -0,0 +1,598 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    behaviortree::{Tbt, TbtBuilder, TbtNode},
+    parser::parse_expression,
+    stl::{ApSource, Bound, Stl},
+    AtomicRegistry,
+};
+use serde::{Deserialize, Serialize};
+
+/*******************************
+ * Serializable Syntax
+ *******************************/
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+/// Serializable representation of a TbtNode. Node indices are not stored, they are
+/// reassigned when the tree is rebuilt.
+pub enum TbtDefinition {
+    Leaf {
+        name: String,
+        formula: StlDefinition,
+    },
+    Fallback {
+        children: Vec<TbtDefinition>,
+    },
+    Parallel {
+        m: usize,
+        children: Vec<TbtDefinition>,
+    },
+    Sequence {
+        left: Box<TbtDefinition>,
+        right: Box<TbtDefinition>,
+    },
+    Timeout {
+        t: BoundDefinition,
+        child: Box<TbtDefinition>,
+    },
+    Kleene {
+        n: usize,
+        child: Box<TbtDefinition>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+/// Serializable representation of an STL formula. Formula indices are not stored, they are
+/// reassigned when the formula is rebuilt.
+pub enum StlDefinition {
+    Atomic {
+        #[serde(flatten)]
+        atomic: AtomicDefinition,
+    },
+    Conjunction {
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    Disjunction {
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    Implies {
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    Iff {
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    Neg {
+        child: Box<StlDefinition>,
+    },
+    Next {
+        child: Box<StlDefinition>,
+    },
+    Eventually {
+        child: Box<StlDefinition>,
+    },
+    Globally {
+        child: Box<StlDefinition>,
+    },
+    Until {
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    EventuallyInterval {
+        lower: BoundDefinition,
+        upper: BoundDefinition,
+        child: Box<StlDefinition>,
+    },
+    GloballyInterval {
+        lower: BoundDefinition,
+        upper: BoundDefinition,
+        child: Box<StlDefinition>,
+    },
+    UntilInterval {
+        lower: BoundDefinition,
+        upper: BoundDefinition,
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    Release {
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    ReleaseInterval {
+        lower: BoundDefinition,
+        upper: BoundDefinition,
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    Previous {
+        child: Box<StlDefinition>,
+    },
+    Once {
+        child: Box<StlDefinition>,
+    },
+    Historically {
+        child: Box<StlDefinition>,
+    },
+    Since {
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+    OnceInterval {
+        lower: BoundDefinition,
+        upper: BoundDefinition,
+        child: Box<StlDefinition>,
+    },
+    HistoricallyInterval {
+        lower: BoundDefinition,
+        upper: BoundDefinition,
+        child: Box<StlDefinition>,
+    },
+    SinceInterval {
+        lower: BoundDefinition,
+        upper: BoundDefinition,
+        left: Box<StlDefinition>,
+        right: Box<StlDefinition>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// An atomic proposition is either an arithmetic expression, e.g., "2.5 - abs(x)",
+/// or the name of an atomic proposition in an AtomicRegistry
+pub enum AtomicDefinition {
+    Expression(String),
+    Name(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+/// A bound is either a number of samples, e.g., 1000, or a duration, e.g., {"seconds": 5.0}
+pub enum BoundDefinition {
+    Samples(usize),
+    Seconds { seconds: f32 },
+}
+
+impl From<Bound> for BoundDefinition {
+    fn from(bound: Bound) -> Self {
+        match bound {
+            Bound::Samples(samples) => BoundDefinition::Samples(samples),
+            Bound::Seconds(seconds) => BoundDefinition::Seconds { seconds },
+        }
+    }
+}
+
+impl From<BoundDefinition> for Bound {
+    fn from(bound: BoundDefinition) -> Self {
+        match bound {
+            BoundDefinition::Samples(samples) => Bound::Samples(samples),
+            BoundDefinition::Seconds { seconds } => Bound::Seconds(seconds),
+        }
+    }
+}
+
+/*******************************
+ * Conversion from Tbt and Stl
+ *******************************/
+impl TbtDefinition {
+    /// Converts a TBT into its serializable representation.
+    /// Fails if an atomic proposition is only given as Rust function, i.e., without name or expression.
+    pub fn from_tbt(tbt: &Tbt) -> Result<Self, String> {
+        TbtDefinition::from_node(&tbt.tree)
+    }
+
+    fn from_node(node: &TbtNode) -> Result<Self, String> {
+        Ok(match node {
+            TbtNode::Leaf(_, formula, name) => TbtDefinition::Leaf {
+                name: name.clone(),
+                formula: StlDefinition::from_stl(formula)?,
+            },
+            TbtNode::Fallback(_, children) => TbtDefinition::Fallback {
+                children: TbtDefinition::from_nodes(children)?,
+            },
+            TbtNode::Parallel(_, m, children) => TbtDefinition::Parallel {
+                m: *m,
+                children: TbtDefinition::from_nodes(children)?,
+            },
+            TbtNode::Sequence(_, l_child, r_child) => TbtDefinition::Sequence {
+                left: Box::new(TbtDefinition::from_node(l_child)?),
+                right: Box::new(TbtDefinition::from_node(r_child)?),
+            },
+            TbtNode::Timeout(_, t, child) => TbtDefinition::Timeout {
+                t: (*t).into(),
+                child: Box::new(TbtDefinition::from_node(child)?),
+            },
+            // The successor Kleene nodes are derived from n, hence, they are not stored
+            TbtNode::Kleene(_, n, _, child) => TbtDefinition::Kleene {
+                n: *n,
+                child: Box::new(TbtDefinition::from_node(child)?),
+            },
+        })
+    }
+
+    fn from_nodes(nodes: &[TbtNode]) -> Result<Vec<Self>, String> {
+        nodes.iter().map(TbtDefinition::from_node).collect()
+    }
+}
+
+impl StlDefinition {
+    /// Converts an STL formula into its serializable representation
+    pub fn from_stl(formula: &Stl) -> Result<Self, String> {
+        let boxed = |f: &Stl| StlDefinition::from_stl(f).map(Box::new);
+        Ok(match formula {
+            Stl::Atomic(index, _, _, source) => match source {
+                ApSource::Function => {
+                    return Err(format!(
+                        "atomic proposition AP({index}) is a Rust function and cannot be serialized"
+                    ))
+                }
+                ApSource::Named(name) => StlDefinition::Atomic {
+                    atomic: AtomicDefinition::Name(name.clone()),
+                },
+                ApSource::Expression(expression) => StlDefinition::Atomic {
+                    atomic: AtomicDefinition::Expression(format!("{expression}")),
+                },
+            },
+            Stl::Conjunction(_, l_child, r_child) => StlDefinition::Conjunction {
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::Disjunction(_, l_child, r_child) => StlDefinition::Disjunction {
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::Implies(_, l_child, r_child) => StlDefinition::Implies {
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::Iff(_, l_child, r_child) => StlDefinition::Iff {
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::Neg(_, child) => StlDefinition::Neg {
+                child: boxed(child)?,
+            },
+            Stl::Next(_, child) => StlDefinition::Next {
+                child: boxed(child)?,
+            },
+            Stl::Eventually(_, child) => StlDefinition::Eventually {
+                child: boxed(child)?,
+            },
+            Stl::Globally(_, child) => StlDefinition::Globally {
+                child: boxed(child)?,
+            },
+            Stl::Until(_, l_child, r_child) => StlDefinition::Until {
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::EventuallyInterval(_, lower, upper, child) => StlDefinition::EventuallyInterval {
+                lower: (*lower).into(),
+                upper: (*upper).into(),
+                child: boxed(child)?,
+            },
+            Stl::GloballyInterval(_, lower, upper, child) => StlDefinition::GloballyInterval {
+                lower: (*lower).into(),
+                upper: (*upper).into(),
+                child: boxed(child)?,
+            },
+            Stl::UntilInterval(_, lower, upper, l_child, r_child) => StlDefinition::UntilInterval {
+                lower: (*lower).into(),
+                upper: (*upper).into(),
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::Release(_, l_child, r_child) => StlDefinition::Release {
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::ReleaseInterval(_, lower, upper, l_child, r_child) => {
+                StlDefinition::ReleaseInterval {
+                    lower: (*lower).into(),
+                    upper: (*upper).into(),
+                    left: boxed(l_child)?,
+                    right: boxed(r_child)?,
+                }
+            }
+            Stl::Previous(_, child) => StlDefinition::Previous {
+                child: boxed(child)?,
+            },
+            Stl::Once(_, child) => StlDefinition::Once {
+                child: boxed(child)?,
+            },
+            Stl::Historically(_, child) => StlDefinition::Historically {
+                child: boxed(child)?,
+            },
+            Stl::Since(_, l_child, r_child) => StlDefinition::Since {
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+            Stl::OnceInterval(_, lower, upper, child) => StlDefinition::OnceInterval {
+                lower: (*lower).into(),
+                upper: (*upper).into(),
+                child: boxed(child)?,
+            },
+            Stl::HistoricallyInterval(_, lower, upper, child) => {
+                StlDefinition::HistoricallyInterval {
+                    lower: (*lower).into(),
+                    upper: (*upper).into(),
+                    child: boxed(child)?,
+                }
+            }
+            Stl::SinceInterval(_, lower, upper, l_child, r_child) => StlDefinition::SinceInterval {
+                lower: (*lower).into(),
+                upper: (*upper).into(),
+                left: boxed(l_child)?,
+                right: boxed(r_child)?,
+            },
+        })
+    }
+}
+
+/*******************************
+ * Conversion to Tbt and Stl
+ *******************************/
+impl TbtDefinition {
+    /// Rebuilds the TBT using the constructors, i.e., node and formula indices are newly assigned.
+    /// # Arguments
+    /// * `atomics` - Used to resolve atomic propositions that are referenced by name
+    pub fn to_tbt(&self, atomics: &AtomicRegistry) -> Result<Tbt, String> {
+        let builder = TbtBuilder::new();
+        Ok(builder.build(self.to_node(atomics, &builder)?))
+    }
+
+    fn to_node(&self, atomics: &AtomicRegistry, builder: &TbtBuilder) -> Result<TbtNode, String> {
+        Ok(match self {
+            TbtDefinition::Leaf { name, formula } => {
+                builder.leaf(formula.to_stl(atomics, builder)?, name.clone())
+            }
+            TbtDefinition::Fallback { children } => {
+                if children.is_empty() {
+                    return Err("Fallback requires at least one child".to_string());
+                }
+                builder.fallback(TbtDefinition::to_nodes(children, atomics, builder)?)
+            }
+            TbtDefinition::Parallel { m, children } => {
+                if *m == 0 || *m > children.len() {
+                    return Err(format!(
+                        "Parallel requires 1 <= m <= {} but m is {m}",
+                        children.len()
+                    ));
+                }
+                builder.parallel(*m, TbtDefinition::to_nodes(children, atomics, builder)?)
+            }
+            TbtDefinition::Sequence { left, right } => builder.sequence(
+                left.to_node(atomics, builder)?,
+                right.to_node(atomics, builder)?,
+            ),
+            TbtDefinition::Timeout { t, child } => {
+                if matches!(t, BoundDefinition::Samples(0))
+                    || matches!(t, BoundDefinition::Seconds { seconds } if *seconds <= 0.0)
+                {
+                    return Err("Timeout requires t > 0".to_string());
+                }
+                builder.timeout_bound((*t).into(), child.to_node(atomics, builder)?)
+            }
+            TbtDefinition::Kleene { n, child } => {
+                builder.kleene(*n, child.to_node(atomics, builder)?)
+            }
+        })
+    }
+
+    fn to_nodes(
+        definitions: &[TbtDefinition],
+        atomics: &AtomicRegistry,
+        builder: &TbtBuilder,
+    ) -> Result<Vec<TbtNode>, String> {
+        definitions
+            .iter()
+            .map(|d| d.to_node(atomics, builder))
+            .collect()
+    }
+}
+
+impl StlDefinition {
+    /// Rebuilds the STL formula using the constructors, i.e., formula indices are newly assigned
+    /// # Arguments
+    /// * `atomics` - Used to resolve atomic propositions that are referenced by name
+    /// * `builder` - Allocates the formula indices
+    pub fn to_stl(&self, atomics: &AtomicRegistry, builder: &TbtBuilder) -> Result<Stl, String> {
+        Ok(match self {
+            StlDefinition::Atomic {
+                atomic: AtomicDefinition::Name(name),
+            } => match atomics.get(name) {
+                Some((names, function)) => {
+                    builder.atomic_named(name, names.clone(), function.clone())
+                }
+                None => return Err(format!("unknown atomic proposition '{name}'")),
+            },
+            StlDefinition::Atomic {
+                atomic: AtomicDefinition::Expression(expression),
+            } => builder.atomic_expression(
+                parse_expression(expression)
+                    .map_err(|e| format!("in expression '{expression}': {e}"))?,
+            ),
+            StlDefinition::Conjunction { left, right } => builder.conjunction(
+                left.to_stl(atomics, builder)?,
+                right.to_stl(atomics, builder)?,
+            ),
+            StlDefinition::Disjunction { left, right } => builder.disjunction(
+                left.to_stl(atomics, builder)?,
+                right.to_stl(atomics, builder)?,
+            ),
+            StlDefinition::Implies { left, right } => builder.implies(
+                left.to_stl(atomics, builder)?,
+                right.to_stl(atomics, builder)?,
+            ),
+            StlDefinition::Iff { left, right } => builder.iff(
+                left.to_stl(atomics, builder)?,
+                right.to_stl(atomics, builder)?,
+            ),
+            StlDefinition::Neg { child } => builder.neg(child.to_stl(atomics, builder)?),
+            StlDefinition::Next { child } => builder.next(child.to_stl(atomics, builder)?),
+            StlDefinition::Eventually { child } => {
+                builder.eventually(child.to_stl(atomics, builder)?)
+            }
+            StlDefinition::Globally { child } => builder.globally(child.to_stl(atomics, builder)?),
+            StlDefinition::Until { left, right } => builder.until(
+                left.to_stl(atomics, builder)?,
+                right.to_stl(atomics, builder)?,
+            ),
+            StlDefinition::EventuallyInterval {
+                lower,
+                upper,
+                child,
+            } => {
+                check_interval(*lower, *upper)?;
+                builder.eventually_interval_bounds(
+                    (*lower).into(),
+                    (*upper).into(),
+                    child.to_stl(atomics, builder)?,
+                )
+            }
+            StlDefinition::GloballyInterval {
+                lower,
+                upper,
+                child,
+            } => {
+                check_interval(*lower, *upper)?;
+                builder.globally_interval_bounds(
+                    (*lower).into(),
+                    (*upper).into(),
+                    child.to_stl(atomics, builder)?,
+                )
+            }
+            StlDefinition::UntilInterval {
+                lower,
+                upper,
+                left,
+                right,
+            } => {
+                check_interval(*lower, *upper)?;
+                builder.until_interval_bounds(
+                    (*lower).into(),
+                    (*upper).into(),
+                    left.to_stl(atomics, builder)?,
+                    right.to_stl(atomics, builder)?,
+                )
+            }
+            StlDefinition::Release { left, right } => builder.release(
+                left.to_stl(atomics, builder)?,
+                right.to_stl(atomics, builder)?,
+            ),
+            StlDefinition::ReleaseInterval {
+                lower,
+                upper,
+                left,
+                right,
+            } => {
+                check_interval(*lower, *upper)?;
+                builder.release_interval_bounds(
+                    (*lower).into(),
+                    (*upper).into(),
+                    left.to_stl(atomics, builder)?,
+                    right.to_stl(atomics, builder)?,
+                )
+            }
+            StlDefinition::Previous { child } => builder.previous(child.to_stl(atomics, builder)?),
+            StlDefinition::Once { child } => builder.once(child.to_stl(atomics, builder)?),
+            StlDefinition::Historically { child } => {
+                builder.historically(child.to_stl(atomics, builder)?)
+            }
+            StlDefinition::Since { left, right } => builder.since(
+                left.to_stl(atomics, builder)?,
+                right.to_stl(atomics, builder)?,
+            ),
+            StlDefinition::OnceInterval {
+                lower,
+                upper,
+                child,
+            } => {
+                check_interval(*lower, *upper)?;
+                builder.once_interval_bounds(
+                    (*lower).into(),
+                    (*upper).into(),
+                    child.to_stl(atomics, builder)?,
+                )
+            }
+            StlDefinition::HistoricallyInterval {
+                lower,
+                upper,
+                child,
+            } => {
+                check_interval(*lower, *upper)?;
+                builder.historically_interval_bounds(
+                    (*lower).into(),
+                    (*upper).into(),
+                    child.to_stl(atomics, builder)?,
+                )
+            }
+            StlDefinition::SinceInterval {
+                lower,
+                upper,
+                left,
+                right,
+            } => {
+                check_interval(*lower, *upper)?;
+                builder.since_interval_bounds(
+                    (*lower).into(),
+                    (*upper).into(),
+                    left.to_stl(atomics, builder)?,
+                    right.to_stl(atomics, builder)?,
+                )
+            }
+        })
+    }
+}
+
+/// Bounds of different units are checked when they are resolved against the sampling period
+fn check_interval(lower: BoundDefinition, upper: BoundDefinition) -> Result<(), String> {
+    let is_malformed = match (lower, upper) {
+        (BoundDefinition::Samples(l), BoundDefinition::Samples(u)) => l > u,
+        (BoundDefinition::Seconds { seconds: l }, BoundDefinition::Seconds { seconds: u }) => l > u,
+        _ => false,
+    };
+    if is_malformed {
+        Err(format!(
+            "malformed interval [{},{}]: lower bound exceeds upper bound",
+            Bound::from(lower),
+            Bound::from(upper)
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/*******************************
+ * JSON and YAML
+ *******************************/
+/// Serializes a TBT into pretty-printed JSON
+pub fn tbt_to_json(tbt: &Tbt) -> Result<String, String> {
+    serde_json::to_string_pretty(&TbtDefinition::from_tbt(tbt)?).map_err(|e| e.to_string())
+}
+
+/// Deserializes a TBT from JSON
+/// # Arguments
+/// * `input` - JSON representation of a TbtDefinition
+/// * `atomics` - Used to resolve atomic propositions that are referenced by name
+pub fn tbt_from_json(input: &str, atomics: &AtomicRegistry) -> Result<Tbt, String> {
+    let definition: TbtDefinition = serde_json::from_str(input).map_err(|e| e.to_string())?;
+    definition.to_tbt(atomics)
+}
+
+/// Serializes a TBT into YAML
+pub fn tbt_to_yaml(tbt: &Tbt) -> Result<String, String> {
+    serde_yaml::to_string(&TbtDefinition::from_tbt(tbt)?).map_err(|e| e.to_string())
+}
+
+/// Deserializes a TBT from YAML
+/// # Arguments
+/// * `input` - YAML representation of a TbtDefinition
+/// * `atomics` - Used to resolve atomic propositions that are referenced by name
+pub fn tbt_from_yaml(input: &str, atomics: &AtomicRegistry) -> Result<Tbt, String> {
+    let definition: TbtDefinition = serde_yaml::from_str(input).map_err(|e| e.to_string())?;
+    definition.to_tbt(atomics)
+}
@@ -0,0 +1,55 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::VecDeque;
+
+/*******************************
+ * Sliding Window
+ *******************************/
+/// Returns the minimum (or maximum) of each window of the given width using a monotonic deque (Lemire's algorithm),
+/// i.e., the i-th result is the minimum of values[i..i + width]. NaN values are ignored as by f32::min and f32::max
+/// and a window without other values results in +inf (or -inf).
+/// # Arguments
+/// * `values` - Values that are slided over
+/// * `width` - Number of values of a window, must be positive
+/// * `is_minimum` - Computes the minimum if true and the maximum otherwise
+pub fn sliding_window(values: &[f32], width: usize, is_minimum: bool) -> Vec<f32> {
+    assert!(width > 0, "sliding window must not be empty");
+    if values.len() < width {
+        return vec![];
+    }
+    let is_dominated = |older: f32, newer: f32| {
+        if is_minimum {
+            older >= newer
+        } else {
+            older <= newer
+        }
+    };
+    let identity = if is_minimum {
+        f32::INFINITY
+    } else {
+        f32::NEG_INFINITY
+    };
+    // Indices of the values that can still become the minimum (maximum), their values are monotonic
+    let mut deque = VecDeque::<usize>::new();
+    let mut result = Vec::with_capacity(values.len() - width + 1);
+    for (i, value) in values.iter().enumerate() {
+        if !value.is_nan() {
+            while deque
+                .back()
+                .is_some_and(|&j| is_dominated(values[j], *value))
+            {
+                deque.pop_back();
+            }
+            deque.push_back(i);
+        }
+        if i + 1 >= width {
+            let start = i + 1 - width;
+            while deque.front().is_some_and(|&j| j < start) {
+                deque.pop_front();
+            }
+            result.push(deque.front().map_or(identity, |&j| values[j]));
+        }
+    }
+    result
+}
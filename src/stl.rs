@@ -1,78 +1,260 @@
 // SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{table::Table, ApF, Trace};
-use std::collections::HashMap;
+use crate::{
+    behaviortree::TbtBuilder, expression::Expression, semantics::Semantics,
+    sliding_window::sliding_window, table::Table, ApF, Trace,
+};
+use std::{fmt, sync::Arc};
 
 type SubformulaIdx = usize;
 
-pub static mut FORMULACOUNT: SubformulaIdx = 0;
-/// Get the formula count ie index count
-fn gfc() -> SubformulaIdx {
-    unsafe {
-        FORMULACOUNT += 1;
-        FORMULACOUNT - 1
+#[derive(Clone)]
+/// Describes the origin of the function of an atomic proposition, used for printing
+pub enum ApSource {
+    /// Function provided in Rust code, printed as AP(index)
+    Function,
+    /// Function registered under a name, e.g., in an AtomicRegistry
+    Named(String),
+    /// Function compiled from an arithmetic expression
+    Expression(Expression),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Bound of an interval or of a timeout
+pub enum Bound {
+    /// Number of samples of the trace
+    Samples(usize),
+    /// Duration in seconds that is resolved against the sampling period of the trace before evaluation
+    Seconds(f32),
+}
+
+impl Bound {
+    /// Returns the number of samples, bounds in seconds must have been resolved before
+    pub fn samples(&self) -> usize {
+        match self {
+            Bound::Samples(samples) => *samples,
+            Bound::Seconds(seconds) => {
+                panic!("Bound of {seconds}s has not been resolved against the sampling period")
+            }
+        }
+    }
+
+    /// Converts a bound in seconds into samples, i.e., the number of sampling periods rounded to the nearest integer
+    /// # Arguments
+    /// * `sampling_period` - Time between two samples in seconds, None if the trace has no time axis
+    pub fn resolve(&self, sampling_period: Option<f32>) -> Result<Bound, String> {
+        match (self, sampling_period) {
+            (Bound::Samples(_), _) => Ok(*self),
+            (Bound::Seconds(seconds), _) if seconds.is_infinite() => Ok(Bound::Samples(usize::MAX)),
+            (Bound::Seconds(seconds), Some(period)) if period > 0.0 => {
+                Ok(Bound::Samples((seconds / period).round() as usize))
+            }
+            (Bound::Seconds(seconds), _) => Err(format!(
+                "bound of {seconds}s requires a trace with time axis"
+            )),
+        }
     }
 }
-pub fn stl_reset_count() {
-    unsafe { FORMULACOUNT = 0 }
+
+/// Bounds in samples are printed as number, bounds in seconds with suffix s, e.g., 5s
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bound::Samples(samples) => write!(f, "{samples}"),
+            Bound::Seconds(seconds) => write!(f, "{seconds}s"),
+        }
+    }
 }
+
 #[derive(Clone)]
 #[allow(dead_code)]
 /// STL Syntax
 pub enum Stl {
-    Atomic(SubformulaIdx, Vec<String>, ApF),
+    Atomic(SubformulaIdx, Vec<String>, ApF, ApSource),
     Conjunction(SubformulaIdx, Box<Stl>, Box<Stl>),
     Disjunction(SubformulaIdx, Box<Stl>, Box<Stl>),
+    Implies(SubformulaIdx, Box<Stl>, Box<Stl>),
+    Iff(SubformulaIdx, Box<Stl>, Box<Stl>),
     Neg(SubformulaIdx, Box<Stl>),
     Next(SubformulaIdx, Box<Stl>),
     Eventually(SubformulaIdx, Box<Stl>),
     Globally(SubformulaIdx, Box<Stl>),
     Until(SubformulaIdx, Box<Stl>, Box<Stl>),
-    EventuallyInterval(SubformulaIdx, usize, usize, Box<Stl>),
-    GloballyInterval(SubformulaIdx, usize, usize, Box<Stl>),
-    UntilInterval(SubformulaIdx, usize, usize, Box<Stl>, Box<Stl>),
+    EventuallyInterval(SubformulaIdx, Bound, Bound, Box<Stl>),
+    GloballyInterval(SubformulaIdx, Bound, Bound, Box<Stl>),
+    UntilInterval(SubformulaIdx, Bound, Bound, Box<Stl>, Box<Stl>),
+    Release(SubformulaIdx, Box<Stl>, Box<Stl>),
+    ReleaseInterval(SubformulaIdx, Bound, Bound, Box<Stl>, Box<Stl>),
+    Previous(SubformulaIdx, Box<Stl>),
+    Once(SubformulaIdx, Box<Stl>),
+    Historically(SubformulaIdx, Box<Stl>),
+    Since(SubformulaIdx, Box<Stl>, Box<Stl>),
+    OnceInterval(SubformulaIdx, Bound, Bound, Box<Stl>),
+    HistoricallyInterval(SubformulaIdx, Bound, Bound, Box<Stl>),
+    SinceInterval(SubformulaIdx, Bound, Bound, Box<Stl>, Box<Stl>),
 }
 
 /// STL Constructors
 #[allow(dead_code)]
-impl Stl {
-    pub fn get_number_formulas() -> usize {
-        unsafe { FORMULACOUNT }
+impl TbtBuilder {
+    pub fn atomic(&self, name: Vec<String>, f: ApF) -> Stl {
+        Stl::Atomic(self.gfc(), name, f, ApSource::Function)
+    }
+    pub fn atomic_named(&self, ap_name: &str, name: Vec<String>, f: ApF) -> Stl {
+        Stl::Atomic(self.gfc(), name, f, ApSource::Named(ap_name.to_string()))
+    }
+    pub fn atomic_expression(&self, expression: Expression) -> Stl {
+        let (name, f) = expression.compile();
+        Stl::Atomic(self.gfc(), name, f, ApSource::Expression(expression))
     }
-    pub fn atomic(name: Vec<String>, f: ApF) -> Self {
-        Stl::Atomic(gfc(), name, f)
+    pub fn conjunction(&self, left_child: Stl, right_child: Stl) -> Stl {
+        Stl::Conjunction(self.gfc(), Box::new(left_child), Box::new(right_child))
     }
-    pub fn conjunction(left_child: Stl, right_child: Stl) -> Self {
-        Stl::Conjunction(gfc(), Box::new(left_child), Box::new(right_child))
+    pub fn disjunction(&self, left_child: Stl, right_child: Stl) -> Stl {
+        Stl::Disjunction(self.gfc(), Box::new(left_child), Box::new(right_child))
     }
-    pub fn disjunction(left_child: Stl, right_child: Stl) -> Self {
-        Stl::Disjunction(gfc(), Box::new(left_child), Box::new(right_child))
+    pub fn implies(&self, left_child: Stl, right_child: Stl) -> Stl {
+        Stl::Implies(self.gfc(), Box::new(left_child), Box::new(right_child))
     }
-    pub fn neg(child: Stl) -> Self {
-        Stl::Neg(gfc(), Box::new(child))
+    pub fn iff(&self, left_child: Stl, right_child: Stl) -> Stl {
+        Stl::Iff(self.gfc(), Box::new(left_child), Box::new(right_child))
     }
-    pub fn next(child: Stl) -> Self {
-        Stl::Next(gfc(), Box::new(child))
+    pub fn neg(&self, child: Stl) -> Stl {
+        Stl::Neg(self.gfc(), Box::new(child))
     }
-    pub fn eventually(child: Stl) -> Self {
-        Stl::Eventually(gfc(), Box::new(child))
+    pub fn next(&self, child: Stl) -> Stl {
+        Stl::Next(self.gfc(), Box::new(child))
     }
-    pub fn globally(child: Stl) -> Self {
-        Stl::Globally(gfc(), Box::new(child))
+    pub fn eventually(&self, child: Stl) -> Stl {
+        Stl::Eventually(self.gfc(), Box::new(child))
     }
-    pub fn until(left_child: Stl, right_child: Stl) -> Self {
-        Stl::Until(gfc(), Box::new(left_child), Box::new(right_child))
+    pub fn globally(&self, child: Stl) -> Stl {
+        Stl::Globally(self.gfc(), Box::new(child))
+    }
+    pub fn until(&self, left_child: Stl, right_child: Stl) -> Stl {
+        Stl::Until(self.gfc(), Box::new(left_child), Box::new(right_child))
+    }
+    pub fn eventually_interval(&self, lower: usize, upper: usize, child: Stl) -> Stl {
+        self.eventually_interval_bounds(Bound::Samples(lower), Bound::Samples(upper), child)
+    }
+    pub fn globally_interval(&self, lower: usize, upper: usize, child: Stl) -> Stl {
+        self.globally_interval_bounds(Bound::Samples(lower), Bound::Samples(upper), child)
+    }
+    pub fn until_interval(
+        &self,
+        lower: usize,
+        upper: usize,
+        left_child: Stl,
+        right_child: Stl,
+    ) -> Stl {
+        self.until_interval_bounds(
+            Bound::Samples(lower),
+            Bound::Samples(upper),
+            left_child,
+            right_child,
+        )
     }
-    pub fn eventually_interval(lower: usize, upper: usize, child: Stl) -> Self {
-        Stl::EventuallyInterval(gfc(), lower, upper, Box::new(child))
+    pub fn eventually_interval_bounds(&self, lower: Bound, upper: Bound, child: Stl) -> Stl {
+        Stl::EventuallyInterval(self.gfc(), lower, upper, Box::new(child))
     }
-    pub fn globally_interval(lower: usize, upper: usize, child: Stl) -> Self {
-        Stl::GloballyInterval(gfc(), lower, upper, Box::new(child))
+    pub fn globally_interval_bounds(&self, lower: Bound, upper: Bound, child: Stl) -> Stl {
+        Stl::GloballyInterval(self.gfc(), lower, upper, Box::new(child))
     }
-    pub fn until_interval(lower: usize, upper: usize, left_child: Stl, right_child: Stl) -> Self {
+    pub fn until_interval_bounds(
+        &self,
+        lower: Bound,
+        upper: Bound,
+        left_child: Stl,
+        right_child: Stl,
+    ) -> Stl {
         Stl::UntilInterval(
-            gfc(),
+            self.gfc(),
+            lower,
+            upper,
+            Box::new(left_child),
+            Box::new(right_child),
+        )
+    }
+    pub fn release(&self, left_child: Stl, right_child: Stl) -> Stl {
+        Stl::Release(self.gfc(), Box::new(left_child), Box::new(right_child))
+    }
+    pub fn release_interval(
+        &self,
+        lower: usize,
+        upper: usize,
+        left_child: Stl,
+        right_child: Stl,
+    ) -> Stl {
+        self.release_interval_bounds(
+            Bound::Samples(lower),
+            Bound::Samples(upper),
+            left_child,
+            right_child,
+        )
+    }
+    pub fn release_interval_bounds(
+        &self,
+        lower: Bound,
+        upper: Bound,
+        left_child: Stl,
+        right_child: Stl,
+    ) -> Stl {
+        Stl::ReleaseInterval(
+            self.gfc(),
+            lower,
+            upper,
+            Box::new(left_child),
+            Box::new(right_child),
+        )
+    }
+    pub fn previous(&self, child: Stl) -> Stl {
+        Stl::Previous(self.gfc(), Box::new(child))
+    }
+    pub fn once(&self, child: Stl) -> Stl {
+        Stl::Once(self.gfc(), Box::new(child))
+    }
+    pub fn historically(&self, child: Stl) -> Stl {
+        Stl::Historically(self.gfc(), Box::new(child))
+    }
+    pub fn since(&self, left_child: Stl, right_child: Stl) -> Stl {
+        Stl::Since(self.gfc(), Box::new(left_child), Box::new(right_child))
+    }
+    pub fn once_interval(&self, lower: usize, upper: usize, child: Stl) -> Stl {
+        self.once_interval_bounds(Bound::Samples(lower), Bound::Samples(upper), child)
+    }
+    pub fn historically_interval(&self, lower: usize, upper: usize, child: Stl) -> Stl {
+        self.historically_interval_bounds(Bound::Samples(lower), Bound::Samples(upper), child)
+    }
+    pub fn since_interval(
+        &self,
+        lower: usize,
+        upper: usize,
+        left_child: Stl,
+        right_child: Stl,
+    ) -> Stl {
+        self.since_interval_bounds(
+            Bound::Samples(lower),
+            Bound::Samples(upper),
+            left_child,
+            right_child,
+        )
+    }
+    pub fn once_interval_bounds(&self, lower: Bound, upper: Bound, child: Stl) -> Stl {
+        Stl::OnceInterval(self.gfc(), lower, upper, Box::new(child))
+    }
+    pub fn historically_interval_bounds(&self, lower: Bound, upper: Bound, child: Stl) -> Stl {
+        Stl::HistoricallyInterval(self.gfc(), lower, upper, Box::new(child))
+    }
+    pub fn since_interval_bounds(
+        &self,
+        lower: Bound,
+        upper: Bound,
+        left_child: Stl,
+        right_child: Stl,
+    ) -> Stl {
+        Stl::SinceInterval(
+            self.gfc(),
             lower,
             upper,
             Box::new(left_child),
@@ -86,12 +268,18 @@ impl Stl {
     /// Returns a list of all atomic propositions
     pub fn get_atomics(&self) -> Vec<&Stl> {
         match self {
-            Stl::Atomic(_, _, _) => {
+            Stl::Atomic(_, _, _, _) => {
                 vec![self]
             }
             Stl::Conjunction(_, l_child, r_child)
             | Stl::Until(_, l_child, r_child)
             | Stl::UntilInterval(_, _, _, l_child, r_child)
+            | Stl::Implies(_, l_child, r_child)
+            | Stl::Iff(_, l_child, r_child)
+            | Stl::Release(_, l_child, r_child)
+            | Stl::ReleaseInterval(_, _, _, l_child, r_child)
+            | Stl::Since(_, l_child, r_child)
+            | Stl::SinceInterval(_, _, _, l_child, r_child)
             | Stl::Disjunction(_, l_child, r_child) => {
                 let mut atomics = l_child.get_atomics();
                 atomics.append(&mut r_child.get_atomics());
@@ -102,21 +290,178 @@ impl Stl {
             | Stl::Eventually(_, child)
             | Stl::Globally(_, child)
             | Stl::EventuallyInterval(_, _, _, child)
-            | Stl::GloballyInterval(_, _, _, child) => child.get_atomics(),
+            | Stl::GloballyInterval(_, _, _, child)
+            | Stl::Previous(_, child)
+            | Stl::Once(_, child)
+            | Stl::Historically(_, child)
+            | Stl::OnceInterval(_, _, _, child)
+            | Stl::HistoricallyInterval(_, _, _, child) => child.get_atomics(),
+        }
+    }
+
+    /// Converts all bounds in seconds into samples
+    /// # Arguments
+    /// * `sampling_period` - Time between two samples in seconds, None if the trace has no time axis
+    pub fn resolve_bounds(&mut self, sampling_period: Option<f32>) -> Result<(), String> {
+        match self {
+            Stl::Atomic(_, _, _, _) => Ok(()),
+            Stl::Conjunction(_, l_child, r_child)
+            | Stl::Disjunction(_, l_child, r_child)
+            | Stl::Implies(_, l_child, r_child)
+            | Stl::Iff(_, l_child, r_child)
+            | Stl::Until(_, l_child, r_child)
+            | Stl::Release(_, l_child, r_child)
+            | Stl::Since(_, l_child, r_child) => {
+                l_child.resolve_bounds(sampling_period)?;
+                r_child.resolve_bounds(sampling_period)
+            }
+            Stl::Neg(_, child)
+            | Stl::Next(_, child)
+            | Stl::Eventually(_, child)
+            | Stl::Globally(_, child)
+            | Stl::Previous(_, child)
+            | Stl::Once(_, child)
+            | Stl::Historically(_, child) => child.resolve_bounds(sampling_period),
+            Stl::EventuallyInterval(_, l, u, child)
+            | Stl::GloballyInterval(_, l, u, child)
+            | Stl::OnceInterval(_, l, u, child)
+            | Stl::HistoricallyInterval(_, l, u, child) => {
+                resolve_interval(l, u, sampling_period)?;
+                child.resolve_bounds(sampling_period)
+            }
+            Stl::UntilInterval(_, l, u, l_child, r_child)
+            | Stl::ReleaseInterval(_, l, u, l_child, r_child)
+            | Stl::SinceInterval(_, l, u, l_child, r_child) => {
+                resolve_interval(l, u, sampling_period)?;
+                l_child.resolve_bounds(sampling_period)?;
+                r_child.resolve_bounds(sampling_period)
+            }
+        }
+    }
+
+    /// Computes the horizon of this formula and its subformulas, i.e., the number of samples after lower that
+    /// the robustness depends on. Evaluating a formula for any upper >= lower + horizon results in the same
+    /// robustness, hence, its table only needs horizon + 1 entries per lower. The horizon is None if the robustness
+    /// depends on upper, e.g., for Eventually. Bounds in seconds must have been resolved before.
+    /// # Arguments
+    /// * `horizons` - Horizons indexed by subformula that are set for this formula and its subformulas
+    pub fn set_horizons(&self, horizons: &mut [Option<usize>]) -> Option<usize> {
+        // The horizon of a formula with two children is None if one of them is None
+        let max_horizon = |l: Option<usize>, r: Option<usize>| l.zip(r).map(|(l, r)| l.max(r));
+        let (index, horizon) = match self {
+            Stl::Atomic(index, _, _, _) => (index, Some(0)),
+            Stl::Conjunction(index, l_child, r_child)
+            | Stl::Disjunction(index, l_child, r_child)
+            | Stl::Implies(index, l_child, r_child)
+            | Stl::Iff(index, l_child, r_child)
+            | Stl::Since(index, l_child, r_child)
+            | Stl::SinceInterval(index, _, _, l_child, r_child) => (
+                index,
+                max_horizon(
+                    l_child.set_horizons(horizons),
+                    r_child.set_horizons(horizons),
+                ),
+            ),
+            // Past-time operators only look at positions before lower
+            Stl::Neg(index, child)
+            | Stl::Previous(index, child)
+            | Stl::Once(index, child)
+            | Stl::Historically(index, child)
+            | Stl::OnceInterval(index, _, _, child)
+            | Stl::HistoricallyInterval(index, _, _, child) => {
+                (index, child.set_horizons(horizons))
+            }
+            Stl::Next(index, child) => (
+                index,
+                child.set_horizons(horizons).and_then(|h| h.checked_add(1)),
+            ),
+            Stl::Eventually(index, child) | Stl::Globally(index, child) => {
+                child.set_horizons(horizons);
+                (index, None)
+            }
+            Stl::Until(index, l_child, r_child) | Stl::Release(index, l_child, r_child) => {
+                l_child.set_horizons(horizons);
+                r_child.set_horizons(horizons);
+                (index, None)
+            }
+            Stl::EventuallyInterval(index, _, u, child)
+            | Stl::GloballyInterval(index, _, u, child) => (
+                index,
+                child
+                    .set_horizons(horizons)
+                    .and_then(|h| h.checked_add(u.samples())),
+            ),
+            Stl::UntilInterval(index, _, u, l_child, r_child)
+            | Stl::ReleaseInterval(index, _, u, l_child, r_child) => (
+                index,
+                max_horizon(
+                    l_child.set_horizons(horizons),
+                    r_child.set_horizons(horizons),
+                )
+                .and_then(|h| h.checked_add(u.samples())),
+            ),
+        };
+        horizons[*index] = horizon;
+        horizon
+    }
+
+    /// Makes the atomic propositions return the given robustness for samples where the signal is not 0, e.g.,
+    /// samples that are not known yet. Below a negation or as premise of an implication, the negated robustness is
+    /// returned, i.e., +inf evaluates unknown samples optimistically and -inf pessimistically. Atomic propositions
+    /// below an equivalence are treated like the others, hence, the result is not a bound for equivalences.
+    /// # Arguments
+    /// * `signal` - Signal that is appended to the signals read by each atomic proposition
+    /// * `robustness` - Robustness of an atomic proposition for an unknown sample
+    pub fn set_unknown_robustness(&mut self, signal: &str, robustness: f32) {
+        match self {
+            Stl::Atomic(_, names, function, _) => {
+                let known = function.clone();
+                names.push(signal.to_string());
+                *function = Arc::new(move |values: &[f32]| match values.split_last() {
+                    Some((unknown, values)) if *unknown == 0.0 => known(values),
+                    _ => robustness,
+                });
+            }
+            Stl::Neg(_, child) => child.set_unknown_robustness(signal, -robustness),
+            Stl::Implies(_, l_child, r_child) => {
+                l_child.set_unknown_robustness(signal, -robustness);
+                r_child.set_unknown_robustness(signal, robustness);
+            }
+            Stl::Conjunction(_, l_child, r_child)
+            | Stl::Disjunction(_, l_child, r_child)
+            | Stl::Iff(_, l_child, r_child)
+            | Stl::Until(_, l_child, r_child)
+            | Stl::Release(_, l_child, r_child)
+            | Stl::Since(_, l_child, r_child)
+            | Stl::UntilInterval(_, _, _, l_child, r_child)
+            | Stl::ReleaseInterval(_, _, _, l_child, r_child)
+            | Stl::SinceInterval(_, _, _, l_child, r_child) => {
+                l_child.set_unknown_robustness(signal, robustness);
+                r_child.set_unknown_robustness(signal, robustness);
+            }
+            Stl::Next(_, child)
+            | Stl::Eventually(_, child)
+            | Stl::Globally(_, child)
+            | Stl::EventuallyInterval(_, _, _, child)
+            | Stl::GloballyInterval(_, _, _, child)
+            | Stl::Previous(_, child)
+            | Stl::Once(_, child)
+            | Stl::Historically(_, child)
+            | Stl::OnceInterval(_, _, _, child)
+            | Stl::HistoricallyInterval(_, _, _, child) => {
+                child.set_unknown_robustness(signal, robustness)
+            }
         }
     }
 
     /// Transforms STL formula into String that can be printed
     pub fn pretty_print(&self) -> String {
         match self {
-            Stl::Atomic(index, _, _) => {
-                // let mut s_concat = String::from("");
-                // for parameter in s {
-                //     s_concat.push_str(parameter);
-                //     s_concat.push(',');
-                // }
-                format!("AP({})", index)
-            }
+            Stl::Atomic(index, _, _, source) => match source {
+                ApSource::Function => format!("AP({})", index),
+                ApSource::Named(name) => name.clone(),
+                ApSource::Expression(expression) => format!("{expression}"),
+            },
             Stl::Conjunction(_, l_child, r_child) => format!(
                 "({} and {})",
                 l_child.pretty_print(),
@@ -125,6 +470,16 @@ impl Stl {
             Stl::Disjunction(_, l_child, r_child) => {
                 format!("({} or {})", l_child.pretty_print(), r_child.pretty_print())
             }
+            Stl::Implies(_, l_child, r_child) => {
+                format!("({} -> {})", l_child.pretty_print(), r_child.pretty_print())
+            }
+            Stl::Iff(_, l_child, r_child) => {
+                format!(
+                    "({} <-> {})",
+                    l_child.pretty_print(),
+                    r_child.pretty_print()
+                )
+            }
             Stl::Neg(_, child) => format!("!({})", child.pretty_print()),
             Stl::Next(_, child) => format!("X({})", child.pretty_print()),
             Stl::Eventually(_, child) => format!("F({})", child.pretty_print()),
@@ -145,10 +500,58 @@ impl Stl {
                     r_child.pretty_print()
                 )
             }
+            Stl::Release(_, l_child, r_child) => {
+                format!("({} R {})", l_child.pretty_print(), r_child.pretty_print())
+            }
+            Stl::ReleaseInterval(_, l, u, l_child, r_child) => {
+                format!(
+                    "({} R[{l},{u}] {})",
+                    l_child.pretty_print(),
+                    r_child.pretty_print()
+                )
+            }
+            Stl::Previous(_, child) => format!("Y({})", child.pretty_print()),
+            Stl::Once(_, child) => format!("O({})", child.pretty_print()),
+            Stl::Historically(_, child) => format!("H({})", child.pretty_print()),
+            Stl::Since(_, l_child, r_child) => {
+                format!("({} S {})", l_child.pretty_print(), r_child.pretty_print())
+            }
+            Stl::OnceInterval(_, l, u, child) => {
+                format!("O[{l},{u}]({})", child.pretty_print())
+            }
+            Stl::HistoricallyInterval(_, l, u, child) => {
+                format!("H[{l},{u}]({})", child.pretty_print())
+            }
+            Stl::SinceInterval(_, l, u, l_child, r_child) => {
+                format!(
+                    "({} S[{l},{u}] {})",
+                    l_child.pretty_print(),
+                    r_child.pretty_print()
+                )
+            }
         }
     }
 }
 
+/// Resolves both bounds of an interval. If a bound was given in seconds, the lower bound must not exceed the upper bound.
+fn resolve_interval(
+    lower: &mut Bound,
+    upper: &mut Bound,
+    sampling_period: Option<f32>,
+) -> Result<(), String> {
+    let (l, u) = (
+        lower.resolve(sampling_period)?,
+        upper.resolve(sampling_period)?,
+    );
+    if (l, u) != (*lower, *upper) && l.samples() > u.samples() {
+        return Err(format!(
+            "malformed interval [{lower},{upper}]: lower bound exceeds upper bound"
+        ));
+    }
+    (*lower, *upper) = (l, u);
+    Ok(())
+}
+
 /*
     STL Robustness Semantics
 */
@@ -162,13 +565,13 @@ impl Stl {
     pub fn evaluate_fnc(
         &self,
         names: &Vec<String>,
-        trace: &(usize, HashMap<String, Vec<f32>>),
+        trace: &Trace,
         lower: usize,
         function: &ApF,
     ) -> f32 {
         let mut values = Vec::<f32>::new();
         for name in names {
-            let trace_for_atomic = trace.1.get(name).unwrap();
+            let trace_for_atomic = trace.get(name).unwrap();
             let v = match trace_for_atomic.get(lower) {
                 Some(v) => *v,
                 None => f32::NEG_INFINITY,
@@ -185,20 +588,24 @@ impl Stl {
     /// * `lower` - Segment start
     /// * `upper` - Segment end
     /// * `is_lazy` - Enables/disables lazy evaluation
+    /// * `semantics` - Defines how robustness values are combined
     pub fn evaluate(
         &self,
-        table: &mut Table,
+        table: &Table,
         trace: &Trace,
         lower: usize,
         upper: usize,
         is_lazy: bool,
+        semantics: Semantics,
     ) -> f32 {
         // Lookup table
         let res = if lower <= upper {
             match self {
-                Stl::Atomic(index, _, _)
+                Stl::Atomic(index, _, _, _)
                 | Stl::Conjunction(index, _, _)
                 | Stl::Disjunction(index, _, _)
+                | Stl::Implies(index, _, _)
+                | Stl::Iff(index, _, _)
                 | Stl::Neg(index, _)
                 | Stl::Next(index, _)
                 | Stl::Eventually(index, _)
@@ -206,7 +613,16 @@ impl Stl {
                 | Stl::Until(index, _, _)
                 | Stl::EventuallyInterval(index, _, _, _)
                 | Stl::GloballyInterval(index, _, _, _)
-                | Stl::UntilInterval(index, _, _, _, _) => table.lookup(*index, lower, upper),
+                | Stl::UntilInterval(index, _, _, _, _)
+                | Stl::Release(index, _, _)
+                | Stl::ReleaseInterval(index, _, _, _, _)
+                | Stl::Previous(index, _)
+                | Stl::Once(index, _)
+                | Stl::Historically(index, _)
+                | Stl::Since(index, _, _)
+                | Stl::OnceInterval(index, _, _, _)
+                | Stl::HistoricallyInterval(index, _, _, _)
+                | Stl::SinceInterval(index, _, _, _, _) => table.lookup(*index, lower, upper),
             }
         } else {
             None
@@ -214,119 +630,308 @@ impl Stl {
         // Return previous computed result or start computation otherwise
         if let Some(value) = res {
             value
+        } else if let Some(v) =
+            self.evaluate_all_lowers(table, trace, lower, upper, is_lazy, semantics)
+        {
+            v
         } else {
             let (v, index) = match self {
-                Stl::Atomic(index, names, function) => {
+                Stl::Atomic(index, names, function, _) => {
                     let v = if lower <= upper {
-                        self.evaluate_fnc(names, trace, lower, function)
+                        semantics.atomic(self.evaluate_fnc(names, trace, lower, function))
                     } else {
                         f32::NEG_INFINITY
                     };
                     (v, *index)
                 }
                 Stl::Conjunction(index, l_child, r_child) => {
-                    let v = f32::min(
-                        (l_child).evaluate(table, trace, lower, upper, is_lazy),
-                        (r_child).evaluate(table, trace, lower, upper, is_lazy),
+                    let v = semantics.min(
+                        (l_child).evaluate(table, trace, lower, upper, is_lazy, semantics),
+                        (r_child).evaluate(table, trace, lower, upper, is_lazy, semantics),
                     );
                     (v, *index)
                 }
                 Stl::Disjunction(index, l_child, r_child) => {
-                    let v = f32::max(
-                        (l_child).evaluate(table, trace, lower, upper, is_lazy),
-                        (r_child).evaluate(table, trace, lower, upper, is_lazy),
+                    let v = semantics.max(
+                        (l_child).evaluate(table, trace, lower, upper, is_lazy, semantics),
+                        (r_child).evaluate(table, trace, lower, upper, is_lazy, semantics),
+                    );
+                    (v, *index)
+                }
+                Stl::Implies(index, l_child, r_child) => {
+                    let v = semantics.max(
+                        -(l_child).evaluate(table, trace, lower, upper, is_lazy, semantics),
+                        (r_child).evaluate(table, trace, lower, upper, is_lazy, semantics),
                     );
                     (v, *index)
                 }
+                Stl::Iff(index, l_child, r_child) => {
+                    let l_v = l_child.evaluate(table, trace, lower, upper, is_lazy, semantics);
+                    let r_v = r_child.evaluate(table, trace, lower, upper, is_lazy, semantics);
+                    let v = semantics.min(semantics.max(-l_v, r_v), semantics.max(l_v, -r_v));
+                    (v, *index)
+                }
                 Stl::Neg(index, child) => {
-                    let v = -1.0 * child.evaluate(table, trace, lower, upper, is_lazy);
+                    let v = -1.0 * child.evaluate(table, trace, lower, upper, is_lazy, semantics);
                     (v, *index)
                 }
                 Stl::Next(index, child) => {
-                    let v = child.evaluate(table, trace, lower + 1, upper, is_lazy);
+                    let v = child.evaluate(table, trace, lower + 1, upper, is_lazy, semantics);
                     (v, *index)
                 }
                 Stl::Eventually(index, child) => {
-                    let mut v = f32::NEG_INFINITY;
+                    let mut v = semantics.disjunction();
                     for i in lower..(upper + 1) {
-                        v = f32::max(v, child.evaluate(table, trace, i, upper, is_lazy));
-                        if is_lazy && v > 0.0 {
+                        v.add(child.evaluate(table, trace, i, upper, is_lazy, semantics));
+                        if is_lazy && v.value() > 0.0 {
                             break;
                         }
                     }
-                    (v, *index)
+                    (v.value(), *index)
                 }
                 Stl::Globally(index, child) => {
-                    let mut v = f32::INFINITY;
+                    let mut v = semantics.conjunction();
                     for i in lower..(upper + 1) {
-                        v = f32::min(v, child.evaluate(table, trace, i, upper, is_lazy));
-                        if is_lazy && v < 0.0 {
+                        v.add(child.evaluate(table, trace, i, upper, is_lazy, semantics));
+                        if is_lazy && v.value() < 0.0 {
                             break;
                         }
                     }
-                    (v, *index)
+                    (v.value(), *index)
                 }
                 Stl::Until(index, l_child, r_child) => {
-                    let mut v: f32 = f32::NEG_INFINITY;
+                    let mut v = semantics.disjunction();
                     for i in lower..(upper + 1) {
-                        let mut min_v = r_child.evaluate(table, trace, i, upper, is_lazy);
+                        let mut min_v = semantics.conjunction();
+                        min_v.add(r_child.evaluate(table, trace, i, upper, is_lazy, semantics));
                         for j in lower..i {
-                            let l_v = l_child.evaluate(table, trace, j, upper, is_lazy);
-                            min_v = f32::min(min_v, l_v);
+                            let l_v = l_child.evaluate(table, trace, j, upper, is_lazy, semantics);
+                            min_v.add(l_v);
                         }
-                        v = f32::max(v, min_v);
-                        if is_lazy && v > 0.0 {
+                        v.add(min_v.value());
+                        if is_lazy && v.value() > 0.0 {
                             break;
                         }
                     }
-                    (v, *index)
+                    (v.value(), *index)
                 }
                 Stl::EventuallyInterval(index, l, u, child) => {
-                    let mut v = f32::NEG_INFINITY;
-                    let u = usize::min(upper, *u);
-                    for i in *l..(u + 1) {
+                    let mut v = semantics.disjunction();
+                    let u = usize::min(upper, u.samples());
+                    for i in l.samples()..(u + 1) {
                         let child_robustness =
-                            child.evaluate(table, trace, lower + i, upper, is_lazy);
-                        v = f32::max(v, child_robustness);
-                        if is_lazy && v > 0.0 {
+                            child.evaluate(table, trace, lower + i, upper, is_lazy, semantics);
+                        v.add(child_robustness);
+                        if is_lazy && v.value() > 0.0 {
                             break;
                         }
                     }
-                    (v, *index)
+                    (v.value(), *index)
                 }
                 Stl::GloballyInterval(index, l, u, child) => {
-                    let mut v = f32::INFINITY;
-                    let u = usize::min(upper, *u);
-                    if *l > u {
-                        v = f32::NEG_INFINITY;
+                    let mut v = semantics.conjunction();
+                    let (l, u) = (l.samples(), usize::min(upper, u.samples()));
+                    if l > u {
+                        v.add(f32::NEG_INFINITY);
                     } else {
-                        for i in *l..(u + 1) {
+                        for i in l..(u + 1) {
                             let child_robustness =
-                                child.evaluate(table, trace, lower + i, upper, is_lazy);
-                            v = f32::min(v, child_robustness);
-                            if is_lazy && v < 0.0 {
+                                child.evaluate(table, trace, lower + i, upper, is_lazy, semantics);
+                            v.add(child_robustness);
+                            if is_lazy && v.value() < 0.0 {
                                 break;
                             }
                         }
                     }
-                    (v, *index)
+                    (v.value(), *index)
                 }
                 Stl::UntilInterval(index, l, u, l_child, r_child) => {
-                    let mut v: f32 = f32::NEG_INFINITY;
-                    let u = usize::min(upper, *u);
-                    for i in *l..(u + 1) {
-                        let mut min_v = r_child.evaluate(table, trace, lower + i, upper, is_lazy);
-                        for j in *l..i {
-                            let l_v = l_child.evaluate(table, trace, lower + j, upper, is_lazy);
-                            min_v = f32::min(min_v, l_v);
+                    let mut v = semantics.disjunction();
+                    let (l, u) = (l.samples(), usize::min(upper, u.samples()));
+                    for i in l..(u + 1) {
+                        let mut min_v = semantics.conjunction();
+                        min_v.add(r_child.evaluate(
+                            table,
+                            trace,
+                            lower + i,
+                            upper,
+                            is_lazy,
+                            semantics,
+                        ));
+                        for j in l..i {
+                            let l_v = l_child.evaluate(
+                                table,
+                                trace,
+                                lower + j,
+                                upper,
+                                is_lazy,
+                                semantics,
+                            );
+                            min_v.add(l_v);
+                        }
+                        v.add(min_v.value());
+                        if is_lazy && v.value() > 0.0 {
+                            break;
                         }
-                        v = f32::max(v, min_v);
-                        if is_lazy && v > 0.0 {
+                    }
+                    (v.value(), *index)
+                }
+                // Release is the dual of until, i.e., the right child must hold until and including the first position where the left child holds
+                Stl::Release(index, l_child, r_child) => {
+                    let mut v = semantics.conjunction();
+                    for i in lower..(upper + 1) {
+                        let mut max_v = semantics.disjunction();
+                        max_v.add(r_child.evaluate(table, trace, i, upper, is_lazy, semantics));
+                        for j in lower..i {
+                            let l_v = l_child.evaluate(table, trace, j, upper, is_lazy, semantics);
+                            max_v.add(l_v);
+                        }
+                        v.add(max_v.value());
+                        if is_lazy && v.value() < 0.0 {
                             break;
                         }
                     }
+                    (v.value(), *index)
+                }
+                Stl::ReleaseInterval(index, l, u, l_child, r_child) => {
+                    let mut v = semantics.conjunction();
+                    let (l, u) = (l.samples(), usize::min(upper, u.samples()));
+                    if l > u {
+                        v.add(f32::NEG_INFINITY);
+                    } else {
+                        for i in l..(u + 1) {
+                            let mut max_v = semantics.disjunction();
+                            max_v.add(r_child.evaluate(
+                                table,
+                                trace,
+                                lower + i,
+                                upper,
+                                is_lazy,
+                                semantics,
+                            ));
+                            for j in l..i {
+                                let l_v = l_child.evaluate(
+                                    table,
+                                    trace,
+                                    lower + j,
+                                    upper,
+                                    is_lazy,
+                                    semantics,
+                                );
+                                max_v.add(l_v);
+                            }
+                            v.add(max_v.value());
+                            if is_lazy && v.value() < 0.0 {
+                                break;
+                            }
+                        }
+                    }
+                    (v.value(), *index)
+                }
+                // Past-time operators look back from position lower until the start of the trace
+                Stl::Previous(index, child) => {
+                    let v = if lower == 0 {
+                        f32::NEG_INFINITY
+                    } else {
+                        child.evaluate(table, trace, lower - 1, upper, is_lazy, semantics)
+                    };
                     (v, *index)
                 }
+                Stl::Once(index, child) => {
+                    let mut v = semantics.disjunction();
+                    for i in (0..(lower + 1)).rev() {
+                        v.add(child.evaluate(table, trace, i, upper, is_lazy, semantics));
+                        if is_lazy && v.value() > 0.0 {
+                            break;
+                        }
+                    }
+                    (v.value(), *index)
+                }
+                Stl::Historically(index, child) => {
+                    let mut v = semantics.conjunction();
+                    for i in (0..(lower + 1)).rev() {
+                        v.add(child.evaluate(table, trace, i, upper, is_lazy, semantics));
+                        if is_lazy && v.value() < 0.0 {
+                            break;
+                        }
+                    }
+                    (v.value(), *index)
+                }
+                Stl::Since(index, l_child, r_child) => {
+                    let mut v = semantics.disjunction();
+                    // Conjunction of the left child from i + 1 to lower
+                    let mut l_v = semantics.conjunction();
+                    for i in (0..(lower + 1)).rev() {
+                        let mut min_v = l_v;
+                        min_v.add(r_child.evaluate(table, trace, i, upper, is_lazy, semantics));
+                        v.add(min_v.value());
+                        if is_lazy && v.value() > 0.0 {
+                            break;
+                        }
+                        l_v.add(l_child.evaluate(table, trace, i, upper, is_lazy, semantics));
+                    }
+                    (v.value(), *index)
+                }
+                Stl::OnceInterval(index, l, u, child) => {
+                    let mut v = semantics.disjunction();
+                    let u = usize::min(lower, u.samples());
+                    for i in l.samples()..(u + 1) {
+                        v.add(child.evaluate(table, trace, lower - i, upper, is_lazy, semantics));
+                        if is_lazy && v.value() > 0.0 {
+                            break;
+                        }
+                    }
+                    (v.value(), *index)
+                }
+                Stl::HistoricallyInterval(index, l, u, child) => {
+                    let mut v = semantics.conjunction();
+                    let (l, u) = (l.samples(), usize::min(lower, u.samples()));
+                    if l > u {
+                        v.add(f32::NEG_INFINITY);
+                    } else {
+                        for i in l..(u + 1) {
+                            let child_robustness =
+                                child.evaluate(table, trace, lower - i, upper, is_lazy, semantics);
+                            v.add(child_robustness);
+                            if is_lazy && v.value() < 0.0 {
+                                break;
+                            }
+                        }
+                    }
+                    (v.value(), *index)
+                }
+                Stl::SinceInterval(index, l, u, l_child, r_child) => {
+                    let mut v = semantics.disjunction();
+                    let (l, u) = (l.samples(), usize::min(lower, u.samples()));
+                    // Conjunction of the left child from lower - i + 1 to lower
+                    let mut l_v = semantics.conjunction();
+                    for i in 0..(u + 1) {
+                        if i >= l {
+                            let mut min_v = l_v;
+                            min_v.add(r_child.evaluate(
+                                table,
+                                trace,
+                                lower - i,
+                                upper,
+                                is_lazy,
+                                semantics,
+                            ));
+                            v.add(min_v.value());
+                            if is_lazy && v.value() > 0.0 {
+                                break;
+                            }
+                        }
+                        l_v.add(l_child.evaluate(
+                            table,
+                            trace,
+                            lower - i,
+                            upper,
+                            is_lazy,
+                            semantics,
+                        ));
+                    }
+                    (v.value(), *index)
+                }
             };
             // Store result in table for next access
             if lower <= upper {
@@ -336,3 +941,92 @@ impl Stl {
         }
     }
 }
+
+/*
+    Evaluation of all segment starts at once
+*/
+impl Stl {
+    /// Computes temporal operators for all segment starts from 0 to upper at once and stores them in the table.
+    /// Eventually and Globally are suffix maxima and minima, their interval versions use sliding windows,
+    /// and Until and Release are computed backwards, e.g., (a U b) at i is max(b(i), min(a(i), (a U b)(i + 1))).
+    /// Returns None if the operator is not supported, lazy evaluation is enabled, or the semantics does not use minimum and maximum.
+    /// # Arguments
+    /// * `table` - Data structure used for dynamic programming
+    /// * `trace` - Provided trace that is evaluated
+    /// * `lower` - Segment start whose value is returned
+    /// * `upper` - Segment end
+    /// * `is_lazy` - Lazy evaluation stops early and, hence, does not need all segment starts
+    /// * `semantics` - Defines how robustness values are combined
+    fn evaluate_all_lowers(
+        &self,
+        table: &Table,
+        trace: &Trace,
+        lower: usize,
+        upper: usize,
+        is_lazy: bool,
+        semantics: Semantics,
+    ) -> Option<f32> {
+        if lower > upper || is_lazy || !semantics.is_min_max() {
+            return None;
+        }
+        let evaluate_child = |child: &Stl, positions: std::ops::RangeInclusive<usize>| {
+            positions
+                .map(|i| child.evaluate(table, trace, i, upper, false, semantics))
+                .collect::<Vec<f32>>()
+        };
+        let (index, values) = match self {
+            Stl::Eventually(index, child) | Stl::Globally(index, child) => {
+                let is_minimum = matches!(self, Stl::Globally(_, _));
+                let mut values = evaluate_child(child, 0..=upper);
+                for i in (0..upper).rev() {
+                    values[i] = if is_minimum {
+                        f32::min(values[i], values[i + 1])
+                    } else {
+                        f32::max(values[i], values[i + 1])
+                    };
+                }
+                (*index, values)
+            }
+            Stl::EventuallyInterval(index, l, u, child)
+            | Stl::GloballyInterval(index, l, u, child) => {
+                let is_minimum = matches!(self, Stl::GloballyInterval(_, _, _, _));
+                let (l, u) = (l.samples(), usize::min(upper, u.samples()));
+                let values = if l > u {
+                    vec![f32::NEG_INFINITY; upper + 1]
+                } else {
+                    // Windows of segments that start close to upper exceed upper
+                    let child_values = evaluate_child(child, l..=(upper + u));
+                    sliding_window(&child_values, u - l + 1, is_minimum)
+                };
+                (*index, values)
+            }
+            Stl::Until(index, l_child, r_child) | Stl::Release(index, l_child, r_child) => {
+                let is_until = matches!(self, Stl::Until(_, _, _));
+                let l_values = evaluate_child(l_child, 0..=upper);
+                let r_values = evaluate_child(r_child, 0..=upper);
+                let mut values = vec![0.0; upper + 1];
+                let mut next = if is_until {
+                    f32::NEG_INFINITY
+                } else {
+                    f32::INFINITY
+                };
+                for i in (0..(upper + 1)).rev() {
+                    next = if is_until {
+                        f32::max(r_values[i], f32::min(l_values[i], next))
+                    } else {
+                        f32::min(r_values[i], f32::max(l_values[i], next))
+                    };
+                    values[i] = next;
+                }
+                (*index, values)
+            }
+            _ => return None,
+        };
+        for (i, v) in values.iter().enumerate().take(upper + 1) {
+            if !table.contains(index, i, upper) {
+                table.set(index, i, upper, *v);
+            }
+        }
+        Some(values[lower])
+    }
+}
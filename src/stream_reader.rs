@@ -0,0 +1,178 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    csv_reader::{read_csv_trace_from, BadValuePolicy, CsvColumns, NUMERIC_DETECTION_ROWS},
+    trace::TIME_SIGNAL,
+    Trace,
+};
+use serde_json::{Map, Value};
+use std::{fs::File, io::Read};
+
+/// Logfile location that reads the trace from stdin instead of a log folder
+pub const STDIN_LOGFILE: &str = "-";
+
+/*******************************
+ * Stream Format
+ *******************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Format of a trace that is read from a stream
+pub enum StreamFormat {
+    /// csv-file with header, each column with a number in any of the first rows is a signal, see NUMERIC_DETECTION_ROWS
+    Csv,
+    /// One JSON object per line that maps signal names to values, each key with a number in any of the first objects is a signal
+    JsonLines,
+}
+
+impl StreamFormat {
+    /// Detects the format by the first character that is not whitespace, i.e., JSON Lines start with {
+    pub fn detect(input: &str) -> StreamFormat {
+        if input.trim_start().starts_with('{') {
+            StreamFormat::JsonLines
+        } else {
+            StreamFormat::Csv
+        }
+    }
+}
+
+/*******************************
+ * Reader
+ *******************************/
+/// Returns true if the logfile is read as stream, i.e., it is - for stdin or a file such as a named pipe instead of a log folder
+/// # Arguments
+/// * `logfile` - Location of logfile
+pub fn is_stream(logfile: &str) -> bool {
+    logfile == STDIN_LOGFILE || std::fs::metadata(logfile).is_ok_and(|metadata| !metadata.is_dir())
+}
+
+/// Reads the trace from stdin or a file such as a named pipe, which can only be read once
+/// # Arguments
+/// * `logfile` - Location of the file or - for stdin
+/// * `number_skipped_entries` - used for subsampling ie only every n-th sample is read
+/// * `policy` - Defines how values that cannot be parsed are handled
+pub fn read_stream_trace(
+    logfile: &str,
+    number_skipped_entries: usize,
+    policy: BadValuePolicy,
+) -> Result<Trace, String> {
+    let name = if logfile == STDIN_LOGFILE {
+        "stdin"
+    } else {
+        logfile
+    };
+    let mut input = String::new();
+    let read = if logfile == STDIN_LOGFILE {
+        std::io::stdin().read_to_string(&mut input)
+    } else {
+        File::open(logfile).and_then(|mut file| file.read_to_string(&mut input))
+    };
+    read.map_err(|e| format!("Could not read {name}: {e}"))?;
+    parse_stream_trace(&input, name, number_skipped_entries, policy)
+}
+
+/// Parses a trace in csv or JSON Lines format, see StreamFormat. The signal time is used as time axis if present.
+/// # Arguments
+/// * `input` - Content of the stream
+/// * `name` - Name of the stream used in errors
+/// * `number_skipped_entries` - used for subsampling ie only every n-th sample is read
+/// * `policy` - Defines how values that cannot be parsed are handled, SkipRow skips a sample if any signal is bad
+pub fn parse_stream_trace(
+    input: &str,
+    name: &str,
+    number_skipped_entries: usize,
+    policy: BadValuePolicy,
+) -> Result<Trace, String> {
+    let trace = match StreamFormat::detect(input) {
+        StreamFormat::Csv => read_csv_trace_from(
+            input.as_bytes(),
+            name,
+            CsvColumns::AllNumeric,
+            "",
+            number_skipped_entries,
+            policy,
+        )
+        .map_err(|e| e.to_string())?,
+        StreamFormat::JsonLines => parse_json_lines(input, name, number_skipped_entries, policy)?,
+    };
+    if trace.get(TIME_SIGNAL).is_some() {
+        trace
+            .with_time_signal(TIME_SIGNAL)
+            .map_err(|e| format!("{name}: {e}"))
+    } else {
+        Ok(trace)
+    }
+}
+
+/// Parses one JSON object per line, empty lines are ignored
+fn parse_json_lines(
+    input: &str,
+    name: &str,
+    number_skipped_entries: usize,
+    policy: BadValuePolicy,
+) -> Result<Trace, String> {
+    let mut objects = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<Map<String, Value>>(line)
+                .map(|object| (index + 1, object))
+                .map_err(|e| format!("Malformed JSON Lines {name} at line {}: {e}", index + 1))
+        });
+    // Objects that are read to decide which keys are signals, they are evaluated afterwards
+    let first_objects = objects
+        .by_ref()
+        .take(NUMERIC_DETECTION_ROWS)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut signals: Vec<String> = vec![];
+    for (signal, value) in first_objects.iter().flat_map(|(_, object)| object) {
+        if to_number(value).is_some() && !signals.contains(signal) {
+            signals.push(signal.clone());
+        }
+    }
+    let objects = first_objects.into_iter().map(Ok).chain(objects);
+
+    // Iterate through the objects and collect the values of each signal
+    let mut values = vec![Vec::<f32>::new(); signals.len()];
+    let mut last_values = vec![None; signals.len()];
+    let mut row_values = vec![0.0; signals.len()];
+    let mut number_samples = 0;
+    'objects: for result in objects {
+        let (line, object) = result?;
+        for (i, signal) in signals.iter().enumerate() {
+            let value = object.get(signal);
+            row_values[i] = match (value.and_then(to_number), policy, last_values[i]) {
+                (Some(f), _, _) => f,
+                // Skipped samples must be skipped for all signals
+                (None, BadValuePolicy::SkipRow, _) => continue 'objects,
+                (None, BadValuePolicy::ForwardFill, Some(last_value)) => last_value,
+                (None, BadValuePolicy::Nan, _) => f32::NAN,
+                (None, BadValuePolicy::Fail, _) | (None, BadValuePolicy::ForwardFill, None) => {
+                    let value = value.map_or("missing".to_string(), |value| value.to_string());
+                    return Err(format!(
+                        "Could not parse value '{value}' of signal '{signal}' in {name} at line {line}"
+                    ));
+                }
+            };
+        }
+        for (last_value, value) in last_values.iter_mut().zip(&row_values) {
+            *last_value = Some(*value);
+        }
+        if number_samples % number_skipped_entries.max(1) == 0 {
+            for (signal_values, value) in values.iter_mut().zip(&row_values) {
+                signal_values.push(*value);
+            }
+        }
+        number_samples += 1;
+    }
+    Trace::new(signals.into_iter().zip(values).collect())
+}
+
+/// Returns the value as number if it is a number or a string that can be parsed as number
+fn to_number(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(number) => number.as_f64().map(|f| f as f32),
+        Value::String(string) => string.trim().parse::<f32>().ok(),
+        _ => None,
+    }
+}
@@ -2,6 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::behaviortree::TbtNode;
+use std::{
+    collections::HashMap,
+    fmt,
+    mem::size_of,
+    ops::Range,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        OnceLock, RwLock,
+    },
+};
 
 type Values<T> = Vec<Vec<Vec<T>>>;
 /*
@@ -14,73 +25,383 @@ type Values<T> = Vec<Vec<Vec<T>>>;
         1st index: phi
         2nd index: lower
         3rd index: upper
+
+    If the robustness of a subformula does not depend on upper once upper >= lower + horizon,
+    only the entries from lower to lower + horizon are stored, see Stl::set_horizons()
 */
 
-pub struct Table {
-    table: Box<Values<Option<f32>>>,
-    amount_first_idx: usize,
-    trace_length: usize,
-    pub total_lookups: usize,
-    pub total_set_calls: usize,
-    pub total_entries: usize,
+/// Number of entries that are stored for a lower index
+fn row_length(horizon: Option<usize>, trace_length: usize, lower_index: usize) -> usize {
+    let length = trace_length - lower_index;
+    horizon.map_or(length, |h| length.min(h.saturating_add(1)))
 }
 
-impl Table {
-    pub fn new(amount_first_idx: usize, trace_length: usize) -> Table {
-        let number_entries = amount_first_idx * ((trace_length * (trace_length + 1)) / 2);
-        let mut table = Box::new(Values::<Option<f32>>::with_capacity(amount_first_idx));
+/// Number of entries that are stored for a subformula (or node)
+fn number_entries(horizon: Option<usize>, trace_length: usize) -> usize {
+    // The first rows have the same length, the remaining ones get shorter towards the end of the trace
+    let length = row_length(horizon, trace_length, 0);
+    length * (trace_length + 1 - length) + (length * length.saturating_sub(1)) / 2
+}
+
+/*******************************
+ * Table Backends
+ *******************************/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Defines how the entries of a table are stored
+pub enum TableBackend {
+    /// All entries are allocated when the table is created, which is fastest but needs quadratic memory
+    #[default]
+    Dense,
+    /// The entries of a subformula (or node) and lower index are allocated when the first of them is set
+    Lazy,
+    /// Only entries that are set are stored in a hash map
+    Sparse,
+}
+
+/// Accepts dense, lazy, or sparse
+impl FromStr for TableBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dense" => Ok(TableBackend::Dense),
+            "lazy" => Ok(TableBackend::Lazy),
+            "sparse" => Ok(TableBackend::Sparse),
+            _ => Err(format!(
+                "unknown table backend '{s}', expected dense, lazy, or sparse"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TableBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableBackend::Dense => write!(f, "dense"),
+            TableBackend::Lazy => write!(f, "lazy"),
+            TableBackend::Sparse => write!(f, "sparse"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Estimated memory of tables in bytes
+/// # Arguments
+/// * `initial` - Memory that is allocated when the tables are created
+/// * `maximum` - Memory that is needed if every entry is set
+pub struct MemoryEstimate {
+    pub initial: usize,
+    pub maximum: usize,
+}
+
+impl std::ops::Add for MemoryEstimate {
+    type Output = MemoryEstimate;
+
+    fn add(self, other: MemoryEstimate) -> MemoryEstimate {
+        MemoryEstimate {
+            initial: self.initial + other.initial,
+            maximum: self.maximum + other.maximum,
+        }
+    }
+}
+
+impl TableBackend {
+    /// Estimates the memory of a table without allocating it
+    /// # Arguments
+    /// * `horizons` - Horizon of each subformula (or node), None if all entries are stored
+    /// * `trace_length` - Length of the trace
+    pub fn estimate_memory(
+        &self,
+        horizons: &[Option<usize>],
+        trace_length: usize,
+    ) -> MemoryEstimate {
+        let amount_first_idx = horizons.len();
+        let number_entries: usize = horizons
+            .iter()
+            .map(|horizon| number_entries(*horizon, trace_length))
+            .sum();
+        let number_rows = amount_first_idx * trace_length;
+        match self {
+            TableBackend::Dense => {
+                let memory = amount_first_idx * size_of::<Vec<Vec<Entry>>>()
+                    + number_rows * size_of::<Vec<Entry>>()
+                    + number_entries * size_of::<Entry>();
+                MemoryEstimate {
+                    initial: memory,
+                    maximum: memory,
+                }
+            }
+            TableBackend::Lazy => {
+                let initial = amount_first_idx * size_of::<Vec<OnceLock<Vec<Entry>>>>()
+                    + number_rows * size_of::<OnceLock<Vec<Entry>>>();
+                MemoryEstimate {
+                    initial,
+                    maximum: initial + number_entries * size_of::<Entry>(),
+                }
+            }
+            // A hash map stores one control byte per bucket and keeps at most 7/8 of the buckets occupied
+            TableBackend::Sparse => MemoryEstimate {
+                initial: 0,
+                maximum: number_entries * (size_of::<((usize, usize, usize), f32)>() + 1) * 8 / 7,
+            },
+        }
+    }
+}
+
+/*******************************
+ * Storages
+ *******************************/
+/// Entry of a table that can be read and set by several threads at the same time
+struct Entry(AtomicU32);
+
+/// Bits of an empty entry, a NaN that is not produced by arithmetic. A value with these bits is never
+/// stored, i.e., it is recomputed when it is needed again.
+const EMPTY: u32 = 0x7FC0_DEAD;
+
+impl Entry {
+    fn new() -> Entry {
+        Entry(AtomicU32::new(EMPTY))
+    }
+
+    fn get(&self) -> Option<f32> {
+        let bits = self.0.load(Ordering::Relaxed);
+        (bits != EMPTY).then(|| f32::from_bits(bits))
+    }
+
+    fn set(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.0.store(EMPTY, Ordering::Relaxed);
+    }
+}
+
+/// Common interface of the table backends, indices are checked by the table.
+/// Entries are set through a shared reference such that subtrees can be evaluated in parallel.
+pub trait Storage: Send + Sync {
+    /// Returns the entry if it has been set
+    fn get(&self, first_index: usize, lower_index: usize, upper_index: usize) -> Option<f32>;
+    /// Sets the entry
+    fn set(&self, first_index: usize, lower_index: usize, upper_index: usize, value: f32);
+    /// Removes the entry if it has been set
+    fn remove(&self, first_index: usize, lower_index: usize, upper_index: usize);
+}
+
+/// Allocates all entries when it is created
+struct DenseStorage {
+    values: Box<Values<Entry>>,
+}
+
+impl DenseStorage {
+    fn new(horizons: &[Option<usize>], trace_length: usize) -> DenseStorage {
+        let number_entries: usize = horizons
+            .iter()
+            .map(|horizon| number_entries(*horizon, trace_length))
+            .sum();
+        let mut values = Box::new(Values::<Entry>::with_capacity(horizons.len()));
         let mut created = 0;
-        for _ in 0..amount_first_idx {
-            let mut lower = Box::new(Vec::<Vec<Option<f32>>>::with_capacity(trace_length));
+        for horizon in horizons {
+            let mut lower = Box::new(Vec::<Vec<Entry>>::with_capacity(trace_length));
             for l in 0..trace_length {
-                let mut upper = Box::new(Vec::<Option<f32>>::with_capacity(trace_length - l));
-                for _ in 0..(trace_length - l) {
+                let length = row_length(*horizon, trace_length, l);
+                let mut upper = Box::new(Vec::<Entry>::with_capacity(length));
+                for _ in 0..length {
                     created += 1;
-                    upper.push(None);
+                    upper.push(Entry::new());
                 }
                 lower.push(*upper);
             }
-            table.push(*lower);
+            values.push(*lower);
         }
         assert_eq!(created, number_entries);
+        DenseStorage { values }
+    }
+}
+
+impl Storage for DenseStorage {
+    fn get(&self, first_index: usize, lower_index: usize, upper_index: usize) -> Option<f32> {
+        self.values[first_index][lower_index][upper_index - lower_index].get()
+    }
+
+    fn set(&self, first_index: usize, lower_index: usize, upper_index: usize, value: f32) {
+        self.values[first_index][lower_index][upper_index - lower_index].set(value);
+    }
+
+    fn remove(&self, first_index: usize, lower_index: usize, upper_index: usize) {
+        self.values[first_index][lower_index][upper_index - lower_index].clear();
+    }
+}
+
+/// Allocates the entries of a subformula (or node) and lower index when the first of them is set
+struct LazyStorage {
+    values: Vec<Vec<OnceLock<Vec<Entry>>>>,
+    horizons: Vec<Option<usize>>,
+    trace_length: usize,
+}
+
+impl LazyStorage {
+    fn new(horizons: &[Option<usize>], trace_length: usize) -> LazyStorage {
+        LazyStorage {
+            values: horizons
+                .iter()
+                .map(|_| (0..trace_length).map(|_| OnceLock::new()).collect())
+                .collect(),
+            horizons: horizons.to_vec(),
+            trace_length,
+        }
+    }
+}
+
+impl Storage for LazyStorage {
+    fn get(&self, first_index: usize, lower_index: usize, upper_index: usize) -> Option<f32> {
+        self.values[first_index][lower_index]
+            .get()
+            .and_then(|row| row[upper_index - lower_index].get())
+    }
+
+    fn set(&self, first_index: usize, lower_index: usize, upper_index: usize, value: f32) {
+        let length = row_length(self.horizons[first_index], self.trace_length, lower_index);
+        let row = self.values[first_index][lower_index]
+            .get_or_init(|| (0..length).map(|_| Entry::new()).collect());
+        row[upper_index - lower_index].set(value);
+    }
+
+    fn remove(&self, first_index: usize, lower_index: usize, upper_index: usize) {
+        if let Some(row) = self.values[first_index][lower_index].get() {
+            row[upper_index - lower_index].clear();
+        }
+    }
+}
+
+/// Stores only the entries that are set
+#[derive(Default)]
+struct SparseStorage {
+    values: RwLock<HashMap<(usize, usize, usize), f32>>,
+}
+
+impl Storage for SparseStorage {
+    fn get(&self, first_index: usize, lower_index: usize, upper_index: usize) -> Option<f32> {
+        self.values
+            .read()
+            .unwrap()
+            .get(&(first_index, lower_index, upper_index))
+            .copied()
+    }
+
+    fn set(&self, first_index: usize, lower_index: usize, upper_index: usize, value: f32) {
+        self.values
+            .write()
+            .unwrap()
+            .insert((first_index, lower_index, upper_index), value);
+    }
+
+    fn remove(&self, first_index: usize, lower_index: usize, upper_index: usize) {
+        self.values
+            .write()
+            .unwrap()
+            .remove(&(first_index, lower_index, upper_index));
+    }
+}
+
+/*******************************
+ * Table
+ *******************************/
+/// Increments a statistic atomically, i.e., no increment is lost if threads evaluate in parallel
+fn increment(counter: &AtomicUsize) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+pub struct Table {
+    storage: Box<dyn Storage>,
+    horizons: Vec<Option<usize>>,
+    trace_length: usize,
+    total_lookups: AtomicUsize,
+    total_set_calls: AtomicUsize,
+    pub total_entries: usize,
+}
+
+impl Table {
+    pub fn new(amount_first_idx: usize, trace_length: usize, backend: TableBackend) -> Table {
+        Table::with_horizons(vec![None; amount_first_idx], trace_length, backend)
+    }
+
+    /// Creates a table that stores at most horizon + 1 entries per lower index of a subformula
+    /// # Arguments
+    /// * `horizons` - Horizon of each subformula, None if all entries are stored
+    /// * `trace_length` - Length of the trace
+    /// * `backend` - Defines how the entries are stored
+    pub fn with_horizons(
+        horizons: Vec<Option<usize>>,
+        trace_length: usize,
+        backend: TableBackend,
+    ) -> Table {
+        let number_entries = horizons
+            .iter()
+            .map(|horizon| number_entries(*horizon, trace_length))
+            .sum();
+        let storage: Box<dyn Storage> = match backend {
+            TableBackend::Dense => Box::new(DenseStorage::new(&horizons, trace_length)),
+            TableBackend::Lazy => Box::new(LazyStorage::new(&horizons, trace_length)),
+            TableBackend::Sparse => Box::<SparseStorage>::default(),
+        };
         Table {
-            table,
-            amount_first_idx,
+            storage,
+            horizons,
             trace_length,
-            total_lookups: 0,
-            total_set_calls: 0,
+            total_lookups: AtomicUsize::new(0),
+            total_set_calls: AtomicUsize::new(0),
             total_entries: number_entries,
         }
     }
 
-    #[allow(clippy::collapsible_match)]
+    /// Returns true if the indices address an entry of the table
+    fn is_valid(&self, first_index: usize, lower_index: usize, upper_index: usize) -> bool {
+        lower_index <= upper_index
+            && upper_index < self.trace_length
+            && first_index < self.horizons.len()
+    }
+
+    /// Returns the upper index of the entry that stores the robustness of the segment
+    fn stored_upper(&self, first_index: usize, lower_index: usize, upper_index: usize) -> usize {
+        match self.horizons[first_index] {
+            Some(h) => upper_index.min(lower_index.saturating_add(h)),
+            None => upper_index,
+        }
+    }
+
     pub fn lookup(
-        &mut self,
+        &self,
         first_index: usize,
         lower_index: usize,
         upper_index: usize,
     ) -> Option<f32> {
-        let res = match self.table.get(first_index) {
-            Some(entry) => match entry.get(lower_index) {
-                Some(entry) => match entry.get(upper_index - lower_index) {
-                    Some(entry) => match entry {
-                        Some(value) => {
-                            self.total_lookups += 1;
-                            Some(*value)
-                        }
-                        None => None,
-                    },
-                    None => None,
-                },
-                None => None,
-            },
-            None => None,
-        };
+        if !self.is_valid(first_index, lower_index, upper_index) {
+            return None;
+        }
+        let upper_index = self.stored_upper(first_index, lower_index, upper_index);
+        let res = self.storage.get(first_index, lower_index, upper_index);
+        if res.is_some() {
+            increment(&self.total_lookups);
+        }
         res
     }
 
+    /// Returns true if the entry has been set, it is not counted as lookup
+    pub fn contains(&self, first_index: usize, lower_index: usize, upper_index: usize) -> bool {
+        self.is_valid(first_index, lower_index, upper_index)
+            && self
+                .storage
+                .get(
+                    first_index,
+                    lower_index,
+                    self.stored_upper(first_index, lower_index, upper_index),
+                )
+                .is_some()
+    }
+
     pub fn lookup_segmentation_tree(
-        &mut self,
+        &self,
         tree: &TbtNode,
         lower_index: usize,
         upper_index: usize,
@@ -95,14 +416,12 @@ impl Table {
         }
     }
 
-    pub fn set(&mut self, first_index: usize, lower_index: usize, upper_index: usize, value: f32) {
-        if lower_index <= upper_index
-            && lower_index < self.trace_length
-            && upper_index < self.trace_length
-            && first_index < self.amount_first_idx
-        {
-            self.total_set_calls += 1;
-            self.table[first_index][lower_index][upper_index - lower_index] = Some(value);
+    pub fn set(&self, first_index: usize, lower_index: usize, upper_index: usize, value: f32) {
+        if self.is_valid(first_index, lower_index, upper_index) {
+            increment(&self.total_set_calls);
+            let upper_index = self.stored_upper(first_index, lower_index, upper_index);
+            self.storage
+                .set(first_index, lower_index, upper_index, value);
         } else {
             println!(
                 "\nOut of bounds, should not happen! Index: {first_index} Lower: {lower_index} Upper: {upper_index}"
@@ -111,7 +430,33 @@ impl Table {
         }
     }
 
+    /// Removes the entries of all segments whose upper index is in the range, e.g., segments that end in
+    /// samples that were only assumed. Entries of subformulas that do not depend on such an upper index are kept.
+    /// # Arguments
+    /// * `uppers` - Upper indices of the removed segments
+    pub fn remove_uppers(&self, uppers: Range<usize>) {
+        let start = uppers.start;
+        for first_index in 0..self.horizons.len() {
+            for upper_index in uppers.start..uppers.end.min(self.trace_length) {
+                for lower_index in 0..=upper_index {
+                    let stored_upper = self.stored_upper(first_index, lower_index, upper_index);
+                    if stored_upper >= start {
+                        self.storage.remove(first_index, lower_index, stored_upper);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Number of lookups that returned a value
+    pub fn total_lookups(&self) -> usize {
+        self.total_lookups.load(Ordering::Relaxed)
+    }
+
     pub fn progress(&self) -> (usize, usize) {
-        (self.total_set_calls, self.total_entries)
+        (
+            self.total_set_calls.load(Ordering::Relaxed),
+            self.total_entries,
+        )
     }
 }
@@ -2,31 +2,70 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    behaviortree::{Tbt, TbtNode},
-    evaluate,
+    arrow_reader::{read_arrow_trace, ArrowFormat},
+    batch::{find_logs, run_batch},
+    behaviortree::{Tbt, TbtBuilder, TbtNode},
+    command_line_parser::{CommandLineArguments, MonitorSetting},
+    csv_reader::{read_csv_file, read_csv_trace, BadValuePolicy, CsvColumns, CsvError, CsvSamples},
+    estimate_table_memory, evaluate,
+    expression::{BinaryOperator, Expression, UnaryOperator},
+    get_best_number_skipped_entries, get_tbt_and_trace,
+    monitor::{run_monitor, OnlineMonitor},
+    parser::{parse_expression, parse_tbt, ParseError},
+    read_trace,
+    reporter::{
+        batch_to_csv, result_to_csv, result_to_json, result_to_junit, OutputFormat, SampleMapping,
+    },
+    semantics::Semantics,
+    serialization::{tbt_from_json, tbt_from_yaml, tbt_to_json, tbt_to_yaml, TbtDefinition},
+    sliding_window::sliding_window,
     stl::Stl,
-    Trace,
+    stream_reader::{is_stream, parse_stream_trace, StreamFormat},
+    table::{Table, TableBackend},
+    tlog_reader::{mavlink_crc, read_tlog_trace},
+    trace::{align, read_mapping, Interpolation},
+    ulog_reader::read_ulog_trace,
+    AtomicRegistry, ProvidesTraceAndTree, SegmentationSetting, ShipdeckLanding, Trace,
+    TraceSetting,
 };
-use std::{collections::HashMap, rc::Rc, time::SystemTime};
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
 
 fn run_test(
     traces_with_expected_value: Vec<(Vec<f32>, f32)>,
     signal_name: String,
     tbt: Tbt,
+) -> Result<(), String> {
+    run_test_with_semantics(
+        traces_with_expected_value,
+        signal_name,
+        tbt,
+        Semantics::Classic,
+    )
+}
+
+fn run_test_with_semantics(
+    traces_with_expected_value: Vec<(Vec<f32>, f32)>,
+    signal_name: String,
+    tbt: Tbt,
+    semantics: Semantics,
 ) -> Result<(), String> {
     for (trace, expected) in traces_with_expected_value {
-        let trace: Trace = (trace.len(), HashMap::from([(signal_name.clone(), trace)]));
+        let trace = Trace::new(HashMap::from([(signal_name.clone(), trace)])).unwrap();
         let robustness = evaluate(
             tbt.clone(),
             trace,
             SystemTime::now(),
             false,
             false,
+            semantics,
+            TableBackend::Dense,
+            1,
             0.0,
             false,
             None,
             false,
-        );
+        )?
+        .robustness;
         if robustness == expected {
             continue;
         } else {
@@ -77,11 +116,9 @@ fn test_globally() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::globally(Stl::atomic(
-            vec![signal_name.clone()],
-            Rc::new(|a: &[f32]| a[0]),
-        )),
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally(builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))),
         String::from("globally"),
     ));
     run_test(traces_with_expected_value, signal_name, tbt).unwrap();
@@ -128,11 +165,12 @@ fn test_globally_interval() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::globally_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally_interval(
             0,
             usize::MAX,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("globally_interval"),
     ));
@@ -179,11 +217,12 @@ fn test_globally_interval() {
         (vec![-2.0, 3.0], f32::NEG_INFINITY),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::globally_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally_interval(
             3,
             usize::MAX,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("globally_interval"),
     ));
@@ -232,11 +271,12 @@ fn test_globally_interval() {
         (vec![-2.0, 3.0], f32::NEG_INFINITY),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::globally_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally_interval(
             3,
             5,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("globally_interval"),
     ));
@@ -285,11 +325,12 @@ fn test_globally_interval() {
         (vec![-2.0, 3.0], -2.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::globally_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally_interval(
             0,
             5,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("globally_interval"),
     ));
@@ -312,11 +353,12 @@ fn test_globally_interval() {
         (vec![-2.0, 3.0], f32::NEG_INFINITY),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::globally_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally_interval(
             4,
             4,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("globally_interval"),
     ));
@@ -327,11 +369,12 @@ fn test_globally_interval() {
         f32::NEG_INFINITY,
     )];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::globally_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally_interval(
             4,
             3,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("globally_interval"),
     ));
@@ -379,11 +422,9 @@ fn test_eventually() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually(Stl::atomic(
-            vec![signal_name.clone()],
-            Rc::new(|a: &[f32]| a[0]),
-        )),
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually(builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))),
         String::from("eventually"),
     ));
     run_test(traces_with_expected_value, signal_name, tbt).unwrap();
@@ -430,11 +471,12 @@ fn test_eventually_interval() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually_interval(
             0,
             usize::MAX,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("eventually_interval"),
     ));
@@ -481,11 +523,12 @@ fn test_eventually_interval() {
         (vec![-2.0, 3.0], f32::NEG_INFINITY),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually_interval(
             3,
             usize::MAX,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("eventually_interval"),
     ));
@@ -540,11 +583,12 @@ fn test_eventually_interval() {
         (vec![-2.0, 3.0], f32::NEG_INFINITY),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually_interval(
             3,
             5,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("eventually_interval"),
     ));
@@ -590,11 +634,12 @@ fn test_eventually_interval() {
         (vec![5.0, 3.0, 3.0, -4.0], 5.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually_interval(
             0,
             5,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("eventually_interval"),
     ));
@@ -613,11 +658,12 @@ fn test_eventually_interval() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually_interval(
             4,
             4,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("eventually_interval"),
     ));
@@ -628,11 +674,12 @@ fn test_eventually_interval() {
         f32::NEG_INFINITY,
     )];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually_interval(
             4,
             3,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         ),
         String::from("eventually_interval"),
     ));
@@ -690,10 +737,11 @@ fn test_until() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::until(
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| -a[0])),
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.until(
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
         ),
         String::from("until"),
     ));
@@ -751,12 +799,13 @@ fn test_until_interval() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::until_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.until_interval(
             0,
             usize::MAX,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| -a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
         ),
         String::from("until_interval"),
     ));
@@ -823,12 +872,13 @@ fn test_until_interval() {
         (vec![-2.0, 3.0], f32::NEG_INFINITY),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::until_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.until_interval(
             3,
             usize::MAX,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| -a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
         ),
         String::from("until_interval"),
     ));
@@ -893,12 +943,13 @@ fn test_until_interval() {
         (vec![1.0, -13.0, 2.0, -2.0], 1.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::until_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.until_interval(
             0,
             5,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| -a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
         ),
         String::from("until_interval"),
     ));
@@ -975,12 +1026,13 @@ fn test_until_interval() {
         (vec![-2.0, 3.0], f32::NEG_INFINITY),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::until_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.until_interval(
             3,
             8,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| -a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
         ),
         String::from("until_interval"),
     ));
@@ -999,12 +1051,13 @@ fn test_until_interval() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::until_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.until_interval(
             4,
             4,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| -a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
         ),
         String::from("until_interval"),
     ));
@@ -1015,18 +1068,252 @@ fn test_until_interval() {
         f32::NEG_INFINITY,
     )];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::until_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.until_interval(
             4,
             3,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| -a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
         ),
         String::from("until_interval"),
     ));
     run_test(traces_with_expected_value.clone(), signal_name, tbt).unwrap();
 }
 
+#[test]
+fn test_release() {
+    let signal_name = "a".to_string();
+    // Release is the dual of until, i.e., (-a R a) is the negation of (a U -a), see test_until
+    let traces_with_expected_value = vec![
+        (
+            vec![
+                1.0, 3.0, 3.0, 4.0, 5.0, 1.0, 2.0, -1.5, -2.0, -3.0, -4.0, -5.0,
+            ],
+            -1.0,
+        ),
+        (
+            vec![
+                2.0, 3.0, 3.0, 4.0, 5.0, 3.0, 2.0, -1.5, -2.0, -3.0, -4.0, -5.0,
+            ],
+            -1.5,
+        ),
+        (
+            vec![-2.0, 3.0, 3.0, 4.0, 5.0, 3.0, 2.0, 1.5, 2.0, 3.0, 4.0, 1.0],
+            -2.0,
+        ),
+        (
+            vec![2.0, 3.0, 3.0, 4.0, 5.0, 3.0, 2.0, 3.5, 2.0, 3.0, 4.0, 3.0],
+            2.0,
+        ),
+        (
+            vec![2.0, 3.0, 3.0, 4.0, 5.0, 3.0, 2.0, 1.5, 2.0, 3.0, 4.0, 10.0],
+            1.5,
+        ),
+    ];
+    for is_bounded in [false, true] {
+        let builder = TbtBuilder::new();
+        let left_child = builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0]));
+        let right_child = builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]));
+        let formula = if is_bounded {
+            builder.release_interval(0, usize::MAX, left_child, right_child)
+        } else {
+            builder.release(left_child, right_child)
+        };
+        let tbt = builder.build(builder.leaf(formula, String::from("release")));
+        run_test(traces_with_expected_value.clone(), signal_name.clone(), tbt).unwrap();
+    }
+    // Bounded release only considers the positions within the interval, i.e., a > 0 at 1 to 3 or released by a > 2
+    let traces_with_expected_value = vec![
+        (vec![0.0, 3.0, -1.0, 4.0, -5.0], 1.0),
+        (vec![2.0, 1.0, -1.0, 4.0, -5.0], -1.0),
+    ];
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.release_interval(
+            1,
+            3,
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0] - 2.0)),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+        ),
+        String::from("release_interval"),
+    ));
+    run_test(traces_with_expected_value, signal_name, tbt).unwrap();
+}
+
+#[test]
+fn test_implies_iff() {
+    let trace = Trace::new(HashMap::from([
+        ("a".to_string(), vec![2.0, -1.0, 3.0]),
+        ("b".to_string(), vec![1.0, -4.0, -2.0]),
+    ]))
+    .unwrap();
+    let atomics = AtomicRegistry::new();
+    for (specification, expected) in [
+        // min over positions of max(-a, b): max(-2, 1), max(1, -4), max(-3, -2)
+        (
+            "Leaf(implies, Globally(Implies(Atomic(a), Atomic(b))))",
+            -2.0,
+        ),
+        // min over positions of min(max(-a, b), max(a, -b)): min(1, 2), min(1, 4), min(-2, 3)
+        ("Leaf(iff, Globally(Iff(Atomic(a), Atomic(b))))", -2.0),
+        ("Leaf(iff, Iff(Atomic(a), Atomic(b)))", 1.0),
+    ] {
+        let tbt = parse_tbt(specification, &atomics).unwrap();
+        let robustness = evaluate(
+            tbt,
+            trace.clone(),
+            SystemTime::now(),
+            false,
+            false,
+            Semantics::Classic,
+            TableBackend::Dense,
+            1,
+            0.0,
+            false,
+            None,
+            false,
+        )
+        .unwrap()
+        .robustness;
+        assert_eq!(robustness, expected, "{specification}");
+    }
+    let tbt = parse_tbt(
+        "Leaf(ops, Implies(Atomic(a), Iff(Atomic(b), ReleaseInterval(1, 2s, Atomic(a), Release(Atomic(a), Atomic(b))))))",
+        &atomics,
+    )
+    .unwrap();
+    assert_eq!(
+        tbt.tree.pretty_print(true, 0),
+        "Leaf(0 ops)[(a -> (b <-> (a R[1,2s] (a R b))))]"
+    );
+    let restored = tbt_from_yaml(&tbt_to_yaml(&tbt).unwrap(), &atomics).unwrap();
+    assert_eq!(
+        TbtDefinition::from_tbt(&restored),
+        TbtDefinition::from_tbt(&tbt)
+    );
+}
+
+#[test]
+fn test_previous() {
+    let signal_name = "a".to_string();
+    // Eventually(Previous(a)) is the maximum without the last value
+    let traces_with_expected_value = vec![
+        (vec![1.0, 2.0, -1.0, 7.0], 2.0),
+        (vec![-3.0, -1.0, -2.0, 9.0], -1.0),
+        (vec![5.0], f32::NEG_INFINITY),
+    ];
+    // Define Tree
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually(
+            builder.previous(builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))),
+        ),
+        String::from("previous"),
+    ));
+    run_test(traces_with_expected_value, signal_name, tbt).unwrap();
+}
+
+#[test]
+fn test_once_historically() {
+    let signal_name = "a".to_string();
+    let atomic = |builder: &TbtBuilder| {
+        builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))
+    };
+    // Next(Globally(Once(a))) is the maximum of the first two values
+    let traces_with_expected_value =
+        vec![(vec![-1.0, 2.0, -5.0], 2.0), (vec![-1.0, -2.0, -5.0], -1.0)];
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.next(builder.globally(builder.once(atomic(&builder)))),
+        String::from("once"),
+    ));
+    run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
+    // Next(Eventually(Historically(a))) is the minimum of the first two values
+    let traces_with_expected_value = vec![(vec![3.0, 1.0, 5.0], 1.0), (vec![3.0, 4.0, 5.0], 3.0)];
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.next(builder.eventually(builder.historically(atomic(&builder)))),
+        String::from("historically"),
+    ));
+    run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
+    // Next(Globally(OnceInterval(1, 2, a))) considers the two previous values
+    let traces_with_expected_value = vec![
+        (vec![1.0, 3.0, 4.0, -2.0, 5.0], 1.0),
+        (vec![-1.0, 3.0, 4.0, -2.0, 5.0], -1.0),
+        (vec![2.0, -1.0, -3.0, 5.0, 1.0], -1.0),
+    ];
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.next(builder.globally(builder.once_interval(1, 2, atomic(&builder)))),
+        String::from("once_interval"),
+    ));
+    run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
+    // Eventually(HistoricallyInterval(0, 1, a)) is the maximum of two consecutive values
+    let traces_with_expected_value = vec![
+        (vec![1.0, 3.0, 4.0, -2.0, 5.0], 3.0),
+        (vec![-1.0, -3.0, 4.0, -2.0, -5.0], -1.0),
+    ];
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually(builder.historically_interval(0, 1, atomic(&builder))),
+        String::from("historically_interval"),
+    ));
+    run_test(traces_with_expected_value, signal_name, tbt).unwrap();
+}
+
+#[test]
+fn test_since() {
+    let signal_name = "a".to_string();
+    // Globally(Since(a, -a)), i.e., a is positive since it was negative
+    let traces_with_expected_value = vec![
+        (vec![-2.0, 1.0, 3.0, -1.0, 4.0], 1.0),
+        (vec![2.0, 1.0, 3.0, 1.0, 4.0], -2.0),
+        (vec![-2.0, 1.0, -3.0, 4.0], 1.0),
+    ];
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.globally(builder.since(
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
+        )),
+        String::from("since"),
+    ));
+    run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
+    // Next(Globally(SinceInterval(1, 2, a, -a)))
+    let traces_with_expected_value = vec![
+        (vec![-2.0, 1.0, -3.0, 4.0, 5.0], -3.0),
+        (vec![-2.0, 1.0, 3.0, -4.0, 5.0], -4.0),
+    ];
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.next(builder.globally(builder.since_interval(
+            1,
+            2,
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
+        ))),
+        String::from("since_interval"),
+    ));
+    run_test(traces_with_expected_value, signal_name, tbt).unwrap();
+    // Past-time operators can be parsed and serialized
+    let atomics = AtomicRegistry::new();
+    let tbt = parse_tbt(
+        "Leaf(past, SinceInterval(0, 2s, Once(Atomic(a)), HistoricallyInterval(1, 3, Previous(Atomic(a)))))",
+        &atomics,
+    )
+    .unwrap();
+    assert_eq!(
+        tbt.tree.pretty_print(true, 0),
+        "Leaf(0 past)[(O(a) S[0,2s] H[1,3](Y(a)))]"
+    );
+    let restored = tbt_from_yaml(&tbt_to_yaml(&tbt).unwrap(), &atomics).unwrap();
+    assert_eq!(
+        TbtDefinition::from_tbt(&restored),
+        TbtDefinition::from_tbt(&tbt)
+    );
+}
+
 #[test]
 fn test_eventually_globally() {
     let signal_name = "a".to_string();
@@ -1046,17 +1333,368 @@ fn test_eventually_globally() {
         ),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::leaf(
-        Stl::eventually(Stl::globally_interval(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(
+        builder.eventually(builder.globally_interval(
             3,
             5,
-            Stl::atomic(vec![signal_name.clone()], Rc::new(|a: &[f32]| a[0])),
+            builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
         )),
         String::from("eventually_globally"),
     ));
     run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
 }
 
+#[test]
+fn test_semantics() {
+    let signal_name = "a".to_string();
+    let atomic = |builder: &TbtBuilder| {
+        builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))
+    };
+    let trace = vec![1.0, 2.0, 6.0];
+    // Globally and Eventually
+    for (semantics, globally, eventually) in [
+        (Semantics::Classic, 1.0, 6.0),
+        (Semantics::Boolean, 1.0, 1.0),
+        (Semantics::Averaged, 3.0, 6.0),
+    ] {
+        let builder = TbtBuilder::new();
+        let tbt =
+            builder.build(builder.leaf(builder.globally(atomic(&builder)), String::from("g")));
+        run_test_with_semantics(
+            vec![(trace.clone(), globally)],
+            signal_name.clone(),
+            tbt,
+            semantics,
+        )
+        .unwrap();
+        let builder = TbtBuilder::new();
+        let tbt =
+            builder.build(builder.leaf(builder.eventually(atomic(&builder)), String::from("f")));
+        run_test_with_semantics(
+            vec![(trace.clone(), eventually)],
+            signal_name.clone(),
+            tbt,
+            semantics,
+        )
+        .unwrap();
+    }
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.leaf(builder.globally(atomic(&builder)), String::from("g")));
+    run_test_with_semantics(
+        vec![(vec![1.0, -2.0, 6.0], -1.0)],
+        signal_name.clone(),
+        tbt,
+        Semantics::Boolean,
+    )
+    .unwrap();
+    // Smooth semantics approximate minimum and maximum, e.g., smooth_min(0, 0) = -ln(2)/temperature
+    for (temperature, trace) in [(10.0, vec![0.0, 0.0]), (100.0, vec![1.0, 2.0, 6.0])] {
+        let builder = TbtBuilder::new();
+        let tbt =
+            builder.build(builder.leaf(builder.globally(atomic(&builder)), String::from("g")));
+        let classic = trace.iter().copied().fold(f32::INFINITY, f32::min);
+        let mut count = 0.0;
+        for v in &trace {
+            count += f32::exp(-temperature * (v - classic));
+        }
+        let expected = classic - f32::ln(count) / temperature;
+        let trace = Trace::new(HashMap::from([(signal_name.clone(), trace)])).unwrap();
+        let robustness = evaluate(
+            tbt,
+            trace,
+            SystemTime::now(),
+            false,
+            false,
+            Semantics::Smooth(temperature),
+            TableBackend::Dense,
+            1,
+            0.0,
+            false,
+            None,
+            false,
+        )
+        .unwrap()
+        .robustness;
+        assert!(
+            (robustness - expected).abs() < 1e-5,
+            "{robustness} != {expected}"
+        );
+        assert!(robustness <= classic);
+    }
+    // Semantics also apply to TBT nodes, e.g., the averaged Sequence chooses the split with the best mean
+    let traces_with_expected_value = vec![(vec![2.0, 4.0, -1.0, -3.0], 1.0)];
+    for (semantics, traces_with_expected_value) in [
+        (Semantics::Classic, traces_with_expected_value.clone()),
+        (Semantics::Boolean, traces_with_expected_value.clone()),
+        (Semantics::Averaged, vec![(vec![2.0, 4.0, -1.0, -3.0], 2.5)]),
+    ] {
+        let builder = TbtBuilder::new();
+        let tbt = builder.build(builder.sequence(
+            builder.leaf(builder.globally(atomic(&builder)), String::from("positive")),
+            builder.leaf(
+                builder.globally(builder.neg(atomic(&builder))),
+                String::from("negative"),
+            ),
+        ));
+        run_test_with_semantics(
+            traces_with_expected_value,
+            signal_name.clone(),
+            tbt.clone(),
+            semantics,
+        )
+        .unwrap();
+        // Lazy evaluation is an error instead of a panic for the averaged semantics
+        let trace = Trace::new(HashMap::from([(
+            signal_name.clone(),
+            vec![2.0, 4.0, -1.0, -3.0],
+        )]))
+        .unwrap();
+        let result = evaluate(
+            tbt,
+            trace,
+            SystemTime::now(),
+            false,
+            true,
+            semantics,
+            TableBackend::Dense,
+            1,
+            0.0,
+            false,
+            None,
+            false,
+        );
+        assert_eq!(result.is_ok(), semantics.supports_lazy_evaluation());
+    }
+    // Parsing of the command line value
+    assert_eq!("smooth:5".parse::<Semantics>(), Ok(Semantics::Smooth(5.0)));
+    assert_eq!("averaged".parse::<Semantics>(), Ok(Semantics::Averaged));
+    assert!("smooth:-1".parse::<Semantics>().is_err());
+    assert!("fuzzy".parse::<Semantics>().is_err());
+}
+
+/// Pseudo-random values in [-10, 10) for differential tests
+fn random_values(seed: &mut u64, number: usize) -> Vec<f32> {
+    (0..number)
+        .map(|_| {
+            *seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((*seed >> 40) % 2000) as f32 / 100.0 - 10.0
+        })
+        .collect()
+}
+
+/// Evaluates a formula by looping over all positions without table, i.e., the reference for evaluate_all_lowers
+fn evaluate_naive(formula: &Stl, trace: &Trace, lower: usize, upper: usize) -> f32 {
+    match formula {
+        Stl::Atomic(_, names, function, _) => {
+            if lower <= upper {
+                formula.evaluate_fnc(names, trace, lower, function)
+            } else {
+                f32::NEG_INFINITY
+            }
+        }
+        Stl::Neg(_, child) => -evaluate_naive(child, trace, lower, upper),
+        Stl::Conjunction(_, l_child, r_child) => f32::min(
+            evaluate_naive(l_child, trace, lower, upper),
+            evaluate_naive(r_child, trace, lower, upper),
+        ),
+        Stl::Eventually(_, child) => (lower..(upper + 1))
+            .map(|i| evaluate_naive(child, trace, i, upper))
+            .fold(f32::NEG_INFINITY, f32::max),
+        Stl::Globally(_, child) => (lower..(upper + 1))
+            .map(|i| evaluate_naive(child, trace, i, upper))
+            .fold(f32::INFINITY, f32::min),
+        Stl::EventuallyInterval(_, l, u, child) => (l.samples()
+            ..(usize::min(upper, u.samples()) + 1))
+            .map(|i| evaluate_naive(child, trace, lower + i, upper))
+            .fold(f32::NEG_INFINITY, f32::max),
+        Stl::GloballyInterval(_, l, u, child) => {
+            let (l, u) = (l.samples(), usize::min(upper, u.samples()));
+            if l > u {
+                f32::NEG_INFINITY
+            } else {
+                (l..(u + 1))
+                    .map(|i| evaluate_naive(child, trace, lower + i, upper))
+                    .fold(f32::INFINITY, f32::min)
+            }
+        }
+        Stl::Until(_, l_child, r_child) | Stl::Release(_, l_child, r_child) => {
+            let is_until = matches!(formula, Stl::Until(_, _, _));
+            let mut v = if is_until {
+                f32::NEG_INFINITY
+            } else {
+                f32::INFINITY
+            };
+            for i in lower..(upper + 1) {
+                let mut inner = evaluate_naive(r_child, trace, i, upper);
+                for j in lower..i {
+                    let l_v = evaluate_naive(l_child, trace, j, upper);
+                    inner = if is_until {
+                        f32::min(inner, l_v)
+                    } else {
+                        f32::max(inner, l_v)
+                    };
+                }
+                v = if is_until {
+                    f32::max(v, inner)
+                } else {
+                    f32::min(v, inner)
+                };
+            }
+            v
+        }
+        _ => panic!("operator is not supported by the reference"),
+    }
+}
+
+#[test]
+fn test_sliding_window() {
+    let mut seed = 7;
+    for length in 1..30 {
+        let mut values = random_values(&mut seed, length);
+        if length % 3 == 0 {
+            values[length / 2] = f32::NAN;
+        }
+        for width in 1..(length + 1) {
+            for is_minimum in [true, false] {
+                let expected: Vec<f32> = values
+                    .windows(width)
+                    .map(|window| {
+                        if is_minimum {
+                            window.iter().copied().fold(f32::INFINITY, f32::min)
+                        } else {
+                            window.iter().copied().fold(f32::NEG_INFINITY, f32::max)
+                        }
+                    })
+                    .collect();
+                assert_eq!(sliding_window(&values, width, is_minimum), expected);
+            }
+        }
+    }
+    assert!(sliding_window(&[1.0, 2.0], 3, true).is_empty());
+}
+
+#[test]
+fn test_evaluate_all_lowers() {
+    let formulas = [
+        "Eventually(Atomic(a))",
+        "Globally(Atomic(a))",
+        "EventuallyInterval(1, 3, Atomic(a))",
+        "GloballyInterval(0, 2, Atomic(a))",
+        "GloballyInterval(2, 1000, Atomic(a))",
+        "GloballyInterval(5, 1000, Atomic(a))",
+        "Until(Atomic(a), Neg(Atomic(a - 2)))",
+        "Release(Atomic(a - 2), Atomic(a))",
+        // Nested operators use the tables of their children
+        "GloballyInterval(1, 4, Until(Atomic(a - 2), EventuallyInterval(0, 2, Atomic(a))))",
+        "Eventually(Conjunction(GloballyInterval(0, 1, Atomic(a)), Release(Atomic(a), Atomic(a - 2))))",
+    ];
+    let atomics = AtomicRegistry::new();
+    let mut seed = 42;
+    for length in [1, 2, 5, 9, 14] {
+        let trace = Trace::new(HashMap::from([(
+            "a".to_string(),
+            random_values(&mut seed, length),
+        )]))
+        .unwrap();
+        for formula in formulas {
+            let tbt = parse_tbt(&format!("Leaf(differential, {formula})"), &atomics).unwrap();
+            let formula = match &tbt.tree {
+                TbtNode::Leaf(_, formula, _) => formula,
+                _ => unreachable!(),
+            };
+            let table = Table::new(tbt.get_number_formulas(), length, TableBackend::Dense);
+            // Segments are evaluated in an order that mixes table hits and misses
+            for upper in (0..length).rev() {
+                for lower in (0..(upper + 1))
+                    .step_by(2)
+                    .chain((1..(upper + 1)).step_by(2))
+                {
+                    let expected = evaluate_naive(formula, &trace, lower, upper);
+                    let v =
+                        formula.evaluate(&table, &trace, lower, upper, false, Semantics::Classic);
+                    assert_eq!(
+                        v,
+                        expected,
+                        "{} at [{lower}, {upper}]",
+                        formula.pretty_print()
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_formula_horizons() {
+    let formulas = [
+        ("Atomic(a)", Some(0)),
+        ("Conjunction(Atomic(a), Next(Next(Atomic(a))))", Some(2)),
+        ("Eventually(Atomic(a))", None),
+        ("Disjunction(Atomic(a), Globally(Atomic(a)))", None),
+        ("EventuallyInterval(1, 3, Atomic(a))", Some(3)),
+        ("GloballyInterval(2, 4, Next(Atomic(a)))", Some(5)),
+        (
+            "UntilInterval(0, 2, Atomic(a), Next(Atomic(a - 1)))",
+            Some(3),
+        ),
+        ("ReleaseInterval(1, 3, Atomic(a - 2), Atomic(a))", Some(3)),
+        ("Once(EventuallyInterval(0, 2, Atomic(a)))", Some(2)),
+        (
+            "SinceInterval(1, 3, Atomic(a), Previous(Atomic(a - 1)))",
+            Some(0),
+        ),
+        ("Historically(Until(Atomic(a), Atomic(a - 1)))", None),
+    ];
+    let atomics = AtomicRegistry::new();
+    let mut seed = 3;
+    let length = 9;
+    let trace = Trace::new(HashMap::from([(
+        "a".to_string(),
+        random_values(&mut seed, length),
+    )]))
+    .unwrap();
+    for (formula, expected) in formulas {
+        let tbt = parse_tbt(&format!("Leaf(horizon, {formula})"), &atomics).unwrap();
+        let horizons = tbt.get_formula_horizons();
+        let formula = match &tbt.tree {
+            TbtNode::Leaf(_, formula, _) => formula,
+            _ => unreachable!(),
+        };
+        assert_eq!(formula.set_horizons(&mut horizons.clone()), expected);
+        // Tables with horizons store fewer entries but return the same robustness
+        for is_lazy in [false, true] {
+            let table = Table::new(tbt.get_number_formulas(), length, TableBackend::Dense);
+            let reduced_table = Table::with_horizons(horizons.clone(), length, TableBackend::Dense);
+            if expected.is_some() {
+                assert!(reduced_table.total_entries < table.total_entries);
+            }
+            for upper in (0..length).rev() {
+                for lower in 0..(upper + 1) {
+                    let v = formula.evaluate(
+                        &reduced_table,
+                        &trace,
+                        lower,
+                        upper,
+                        is_lazy,
+                        Semantics::Classic,
+                    );
+                    let expected =
+                        formula.evaluate(&table, &trace, lower, upper, is_lazy, Semantics::Classic);
+                    assert_eq!(
+                        v,
+                        expected,
+                        "{} at [{lower}, {upper}]",
+                        formula.pretty_print()
+                    );
+                }
+            }
+        }
+    }
+}
+
 #[test]
 fn test_sequence() {
     let signal_name = "a".to_string();
@@ -1102,22 +1740,22 @@ fn test_sequence() {
         (vec![-1.0, 1.0], -1.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::sequence(
-        TbtNode::leaf(
-            Stl::globally(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| a[0]),
-            )),
-            String::from("globally"),
-        ),
-        TbtNode::leaf(
-            Stl::globally(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| -a[0]),
-            )),
-            String::from("globally"),
-        ),
-    ));
+    let builder = TbtBuilder::new();
+    let tbt =
+        builder.build(builder.sequence(
+            builder.leaf(
+                builder.globally(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+                ),
+                String::from("globally"),
+            ),
+            builder.leaf(
+                builder.globally(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
+                ),
+                String::from("globally"),
+            ),
+        ));
     // Run test
     run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
 }
@@ -1152,22 +1790,21 @@ fn test_fallback() {
         (vec![-1.0, 1.0], 1.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::fallback(vec![
-        TbtNode::leaf(
-            Stl::globally(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| a[0]),
-            )),
-            String::from("globally"),
-        ),
-        TbtNode::leaf(
-            Stl::globally(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| -a[0]),
-            )),
-            String::from("globally"),
-        ),
-    ]));
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.fallback(vec![
+            builder.leaf(
+                builder.globally(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+                ),
+                String::from("globally"),
+            ),
+            builder.leaf(
+                builder.globally(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
+                ),
+                String::from("globally"),
+            ),
+        ]));
     // Run test
     run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
 }
@@ -1196,21 +1833,20 @@ fn test_parallel() {
         (vec![-1.0, 1.0], 1.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::parallel(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.parallel(
         1,
         vec![
-            TbtNode::leaf(
-                Stl::globally(Stl::atomic(
-                    vec![signal_name.clone()],
-                    Rc::new(|a: &[f32]| a[0] - 0.5),
-                )),
+            builder.leaf(
+                builder.globally(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0] - 0.5)),
+                ),
                 String::from("globally"),
             ),
-            TbtNode::leaf(
-                Stl::eventually(Stl::atomic(
-                    vec![signal_name.clone()],
-                    Rc::new(|a: &[f32]| -a[0]),
-                )),
+            builder.leaf(
+                builder.eventually(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
+                ),
                 String::from("globally"),
             ),
         ],
@@ -1238,21 +1874,20 @@ fn test_parallel() {
         (vec![-1.0, 1.0], -1.5),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::parallel(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.parallel(
         2,
         vec![
-            TbtNode::leaf(
-                Stl::globally(Stl::atomic(
-                    vec![signal_name.clone()],
-                    Rc::new(|a: &[f32]| a[0] - 0.5),
-                )),
+            builder.leaf(
+                builder.globally(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0] - 0.5)),
+                ),
                 String::from("globally"),
             ),
-            TbtNode::leaf(
-                Stl::eventually(Stl::atomic(
-                    vec![signal_name.clone()],
-                    Rc::new(|a: &[f32]| -a[0]),
-                )),
+            builder.leaf(
+                builder.eventually(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
+                ),
                 String::from("globally"),
             ),
         ],
@@ -1289,13 +1924,11 @@ fn test_timeout() {
         (vec![-1.0, 1.0], -1.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::timeout(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.timeout(
         4,
-        TbtNode::leaf(
-            Stl::globally(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| a[0]),
-            )),
+        builder.leaf(
+            builder.globally(builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))),
             String::from("globally"),
         ),
     ));
@@ -1312,13 +1945,11 @@ fn test_kleene() {
         (vec![1.0, 1.0, 1.0, 1.0, 3.0, 4.0], 1.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::kleene(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.kleene(
         2,
-        TbtNode::leaf(
-            Stl::globally(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| a[0]),
-            )),
+        builder.leaf(
+            builder.globally(builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))),
             String::from("globally"),
         ),
     ));
@@ -1333,31 +1964,33 @@ fn test_kleene() {
         (vec![1.0, 2.0], 2.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::kleene(
-        2,
-        TbtNode::leaf(
-            Stl::eventually(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| a[0]),
-            )),
-            String::from("eventually"),
-        ),
-    ));
+    let builder = TbtBuilder::new();
+    let tbt =
+        builder.build(builder.kleene(
+            2,
+            builder.leaf(
+                builder.eventually(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+                ),
+                String::from("eventually"),
+            ),
+        ));
     // Run test
     run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
     // Defines traces to test
     let traces_with_expected_value = vec![(vec![-1.0, -1.0, -1.0, 1.0, -3.0, 4.0], 4.0)];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::kleene_inf(
-        TbtNode::leaf(
-            Stl::eventually(Stl::atomic(
-                vec![signal_name.clone()],
-                Rc::new(|a: &[f32]| a[0]),
-            )),
-            String::from("eventually"),
-        ),
-        6,
-    ));
+    let builder = TbtBuilder::new();
+    let tbt =
+        builder.build(builder.kleene_inf(
+            builder.leaf(
+                builder.eventually(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+                ),
+                String::from("eventually"),
+            ),
+            6,
+        ));
     // Run test
     run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
     // Defines traces to test
@@ -1368,20 +2001,19 @@ fn test_kleene() {
         (vec![1.0, 1.0, 1.0, 1.0, 3.0, 4.0, 4.0, 4.0], -1.0),
     ];
     // Define Tree
-    let tbt = Tbt::new(TbtNode::kleene_inf(
-        TbtNode::sequence(
-            TbtNode::leaf(
-                Stl::globally(Stl::atomic(
-                    vec![signal_name.clone()],
-                    Rc::new(|a: &[f32]| a[0]),
-                )),
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.kleene_inf(
+        builder.sequence(
+            builder.leaf(
+                builder.globally(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+                ),
                 String::from("globally"),
             ),
-            TbtNode::leaf(
-                Stl::eventually(Stl::atomic(
-                    vec![signal_name.clone()],
-                    Rc::new(|a: &[f32]| -a[0]),
-                )),
+            builder.leaf(
+                builder.eventually(
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0])),
+                ),
                 String::from("eventually"),
             ),
         ),
@@ -1406,13 +2038,14 @@ fn test_even() {
         (vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0], 1.0),
         (vec![2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0], 1.0),
     ];
-    let tbt = Tbt::new(TbtNode::kleene_inf(
-        TbtNode::sequence(
-            TbtNode::leaf(
-                Stl::conjunction(
-                    Stl::atomic(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.kleene_inf(
+        builder.sequence(
+            builder.leaf(
+                builder.conjunction(
+                    builder.atomic(
                         vec![signal_name.clone()],
-                        Rc::new(|a: &[f32]| {
+                        Arc::new(|a: &[f32]| {
                             let a = a[0] as i32;
                             if a % 2 == 0 {
                                 1.0
@@ -1421,19 +2054,19 @@ fn test_even() {
                             }
                         }),
                     ),
-                    Stl::neg(Stl::eventually_interval(
+                    builder.neg(builder.eventually_interval(
                         1,
                         1,
-                        Stl::atomic(vec![signal_name.clone()], Rc::new(|_: &[f32]| 1.0)),
+                        builder.atomic(vec![signal_name.clone()], Arc::new(|_: &[f32]| 1.0)),
                     )),
                 ),
                 String::from("even"),
             ),
-            TbtNode::leaf(
-                Stl::neg(Stl::eventually_interval(
+            builder.leaf(
+                builder.neg(builder.eventually_interval(
                     1,
                     1,
-                    Stl::atomic(vec![signal_name.clone()], Rc::new(|_: &[f32]| 1.0)),
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|_: &[f32]| 1.0)),
                 )),
                 String::from("true"),
             ),
@@ -1451,13 +2084,14 @@ fn test_even() {
         (vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0], 1.0),
         (vec![2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 2.0], 1.0),
     ];
-    let tbt = Tbt::new(TbtNode::kleene_inf(
-        TbtNode::sequence(
-            TbtNode::leaf(
-                Stl::conjunction(
-                    Stl::atomic(
+    let builder = TbtBuilder::new();
+    let tbt = builder.build(builder.kleene_inf(
+        builder.sequence(
+            builder.leaf(
+                builder.conjunction(
+                    builder.atomic(
                         vec![signal_name.clone()],
-                        Rc::new(|a: &[f32]| {
+                        Arc::new(|a: &[f32]| {
                             let a = a[0] as i32;
                             if a % 2 == 0 {
                                 1.0
@@ -1466,19 +2100,19 @@ fn test_even() {
                             }
                         }),
                     ),
-                    Stl::neg(Stl::eventually_interval(
+                    builder.neg(builder.eventually_interval(
                         1,
                         1,
-                        Stl::atomic(vec![signal_name.clone()], Rc::new(|_: &[f32]| 1.0)),
+                        builder.atomic(vec![signal_name.clone()], Arc::new(|_: &[f32]| 1.0)),
                     )),
                 ),
                 String::from("even"),
             ),
-            TbtNode::leaf(
-                Stl::neg(Stl::eventually_interval(
+            builder.leaf(
+                builder.neg(builder.eventually_interval(
                     1,
                     1,
-                    Stl::atomic(vec![signal_name.clone()], Rc::new(|_: &[f32]| 1.0)),
+                    builder.atomic(vec![signal_name.clone()], Arc::new(|_: &[f32]| 1.0)),
                 )),
                 // ),
                 String::from("true"),
@@ -1489,3 +2123,1672 @@ fn test_even() {
     // Run test
     run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
 }
+
+#[test]
+fn test_parser() {
+    let signal_name = "a".to_string();
+    let atomics: AtomicRegistry = HashMap::from([
+        (
+            "positive".to_string(),
+            (vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]) as _),
+        ),
+        (
+            "negative".to_string(),
+            (vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0]) as _),
+        ),
+    ]);
+    // Same tree as in test_sequence
+    let traces_with_expected_value = vec![
+        (
+            vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -0.5, -1.0],
+            0.5,
+        ),
+        (
+            vec![1.0, 1.0, 1.0, 1.0, -1.5, 1.5, -1.0, -1.0, -1.0, -1.0, -1.0],
+            -1.5,
+        ),
+        (vec![-1.0, 1.0], -1.0),
+    ];
+    let tbt = parse_tbt(
+        "# first a positive then a negative phase
+        Sequence([
+            Leaf(globally, Globally(Atomic(positive))),
+            Leaf(\"globally\", Globally(Atomic(negative))), // quoted names are allowed
+        ])",
+        &atomics,
+    )
+    .unwrap();
+    run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
+    // Same tree as the first one in test_parallel
+    let traces_with_expected_value = vec![
+        (
+            vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.1, 1.0, 1.0, 1.0],
+            -0.1,
+        ),
+        (vec![1.0, -1.0], 1.0),
+    ];
+    let atomics: AtomicRegistry = HashMap::from([
+        (
+            "above_half".to_string(),
+            (
+                vec![signal_name.clone()],
+                Arc::new(|a: &[f32]| a[0] - 0.5) as _,
+            ),
+        ),
+        (
+            "negative".to_string(),
+            (vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0]) as _),
+        ),
+    ]);
+    let tbt = parse_tbt(
+        "Parallel(1, [
+            Leaf(globally, Globally(Atomic(above_half))),
+            Leaf(eventually, Eventually(Atomic(negative)))
+        ])",
+        &atomics,
+    )
+    .unwrap();
+    run_test(traces_with_expected_value, signal_name.clone(), tbt).unwrap();
+    // Interval operators
+    let traces_with_expected_value = vec![(
+        vec![
+            -1.0, -3.0, -3.0, -4.0, -5.0, -1.0, 2.0, 1.5, 2.0, -3.0, -4.0, -5.0,
+        ],
+        1.5,
+    )];
+    let tbt = parse_tbt(
+        "Leaf(eventually_globally, Eventually(GloballyInterval(3, 5, Atomic(negative))))",
+        &HashMap::from([(
+            "negative".to_string(),
+            (vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]) as _),
+        )]),
+    )
+    .unwrap();
+    run_test(traces_with_expected_value, signal_name, tbt).unwrap();
+}
+
+#[test]
+fn test_parser_errors() {
+    let atomics: AtomicRegistry = HashMap::from([(
+        "positive".to_string(),
+        (vec!["a".to_string()], Arc::new(|a: &[f32]| a[0]) as _),
+    )]);
+    let error = |input: &str| match parse_tbt(input, &atomics) {
+        Ok(_) => panic!("Expected an error for {input}"),
+        Err(e) => e,
+    };
+    assert_eq!(
+        error("Sequence([\n  Leaf(a, Atomic(positive)),\n  Leef(b, Atomic(positive))\n])"),
+        ParseError {
+            line: 3,
+            column: 3,
+            message: String::from("unknown TBT operator 'Leef'"),
+        }
+    );
+    assert_eq!(
+        error("Leaf(a, Eventually(Atomic(2.5 - dist(a))))"),
+        ParseError {
+            line: 1,
+            column: 33,
+            message: String::from("unknown function 'dist'"),
+        }
+    );
+    assert_eq!(
+        error("Leaf(a, GloballyInterval(5, 3, Atomic(positive)))").line,
+        1
+    );
+    assert_eq!(
+        error("Leaf(a, GloballyInterval(5, 3, Atomic(positive)))").message,
+        "malformed interval [5,3]: lower bound exceeds upper bound"
+    );
+    assert_eq!(
+        error("Leaf(a, EventuallyInterval(1.5, 3, Atomic(positive)))").message,
+        "expected a non-negative integer as lower bound but found '1.5'"
+    );
+    assert_eq!(
+        error("Leaf(a, EventuallyInterval(2s, 1s, Atomic(positive)))").message,
+        "malformed interval [2s,1s]: lower bound exceeds upper bound"
+    );
+    assert_eq!(
+        error("Parallel(3, [Leaf(a, Atomic(positive)), Leaf(b, Atomic(positive))])").message,
+        "Parallel requires 1 <= m <= 2 but m is 3"
+    );
+    assert_eq!(
+        error("Leaf(a, Atomic(positive)) Leaf(b, Atomic(positive))").message,
+        "expected end of input but found 'Leaf'"
+    );
+}
+
+#[test]
+fn test_expression() {
+    // Parsing, printing, and signals
+    let expression = parse_expression("2.5 - sqrt((x - y) * (x - y) + z / -2)").unwrap();
+    assert_eq!(
+        expression.signals(),
+        vec!["x".to_string(), "y".to_string(), "z".to_string()]
+    );
+    assert_eq!(
+        format!("{expression}"),
+        "2.5 - sqrt((x - y) * (x - y) + z / -2)"
+    );
+    assert_eq!(parse_expression(&format!("{expression}")), Ok(expression));
+    let expression = Expression::binary(
+        BinaryOperator::Sub,
+        Expression::signal("a"),
+        Expression::binary(
+            BinaryOperator::Sub,
+            Expression::signal("b"),
+            Expression::unary(UnaryOperator::Neg, Expression::signal("c")),
+        ),
+    );
+    assert_eq!(format!("{expression}"), "a - (b - -c)");
+    assert_eq!(parse_expression(&format!("{expression}")), Ok(expression));
+    // Compiled functions
+    let (names, function) = parse_expression("max(abs(b), a) - min(1, 2) * 3")
+        .unwrap()
+        .compile();
+    assert_eq!(names, vec!["b".to_string(), "a".to_string()]);
+    assert_eq!(function(&[-4.0, 2.0]), 1.0);
+    let (_, function) = parse_expression("wrap(a)").unwrap().compile();
+    assert!(
+        (function(&[3.0 * std::f32::consts::PI / 2.0]) + std::f32::consts::PI / 2.0).abs() < 1e-5
+    );
+    let (_, function) = parse_expression("atan2(a, b) - pi / 4").unwrap().compile();
+    assert!(function(&[1.0, 1.0]).abs() < 1e-6);
+    // Atomic propositions using expressions are evaluated as before (see test_parallel)
+    let signal_name = "a".to_string();
+    let traces_with_expected_value = vec![
+        (
+            vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+            0.5,
+        ),
+        (
+            vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.1, 1.0, 1.0, 1.0],
+            -0.1,
+        ),
+        (vec![-1.0, 1.0], 1.0),
+    ];
+    let tbt = parse_tbt(
+        "Parallel(1, [
+            Leaf(globally, Globally(Atomic(a - 0.5))),
+            Leaf(eventually, Eventually(Atomic(-a)))
+        ])",
+        &AtomicRegistry::new(),
+    )
+    .unwrap();
+    let printed = tbt.tree.pretty_print(true, 0);
+    assert!(printed.contains("globally)[G(a - 0.5)]"));
+    assert!(printed.contains("eventually)[F(-a)]"));
+    run_test(traces_with_expected_value, signal_name, tbt).unwrap();
+}
+
+#[test]
+fn test_serialization() {
+    let signal_name = "a".to_string();
+    let atomics: AtomicRegistry = HashMap::from([(
+        "negative".to_string(),
+        (vec![signal_name.clone()], Arc::new(|a: &[f32]| -a[0]) as _),
+    )]);
+    let tbt = parse_tbt(
+        "Fallback([
+            Sequence([
+                Leaf(up, EventuallyInterval(0, inf, Atomic(abs(a) - 0.5))),
+                Timeout(3, Leaf(down, UntilInterval(1, 2, Atomic(a), Neg(Atomic(negative))))),
+            ]),
+            Parallel(1, [
+                Kleene(2, Leaf(stay, GloballyInterval(0, 5, Atomic(a - 0.5)))),
+                Leaf(eventually, Eventually(Conjunction(Atomic(negative), Next(Atomic(a)))))
+            ])
+        ])",
+        &atomics,
+    )
+    .unwrap();
+    let definition = TbtDefinition::from_tbt(&tbt).unwrap();
+    // JSON and YAML round-trip to the same definition
+    let json = tbt_to_json(&tbt).unwrap();
+    assert!(json.contains("\"name\": \"negative\""));
+    assert!(json.contains("\"expression\": \"abs(a) - 0.5\""));
+    let from_json = tbt_from_json(&json, &atomics).unwrap();
+    assert_eq!(TbtDefinition::from_tbt(&from_json).unwrap(), definition);
+    let yaml = tbt_to_yaml(&tbt).unwrap();
+    let from_yaml = tbt_from_yaml(&yaml, &atomics).unwrap();
+    assert_eq!(TbtDefinition::from_tbt(&from_yaml).unwrap(), definition);
+    // Loaded trees are directly usable for evaluation
+    let traces_with_expected_value = vec![(vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0], 0.5)];
+    run_test(traces_with_expected_value, signal_name, from_json).unwrap();
+    // Signal names that are not identifiers are quoted, e.g., the default names of the ULog reader
+    let dotted = parse_tbt(
+        r#"Leaf(up, Eventually(Atomic("vehicle_local_position.x" - pi)))"#,
+        &atomics,
+    )
+    .unwrap();
+    let json = tbt_to_json(&dotted).unwrap();
+    assert!(json.contains(r#""expression": "\"vehicle_local_position.x\" - 3.1415927""#));
+    let from_json = tbt_from_json(&json, &atomics).unwrap();
+    let from_yaml = tbt_from_yaml(&tbt_to_yaml(&dotted).unwrap(), &atomics).unwrap();
+    for loaded in [from_json, from_yaml] {
+        assert_eq!(
+            TbtDefinition::from_tbt(&loaded).unwrap(),
+            TbtDefinition::from_tbt(&dotted).unwrap()
+        );
+    }
+    // Errors
+    let builder = TbtBuilder::new();
+    let rust_function = builder.build(builder.leaf(
+        builder.atomic(vec!["a".to_string()], Arc::new(|a: &[f32]| a[0])),
+        "leaf".to_string(),
+    ));
+    assert!(tbt_to_json(&rust_function).is_err());
+    let unknown_name =
+        r#"{"type": "Leaf", "name": "l", "formula": {"type": "Atomic", "name": "unknown"}}"#;
+    assert_eq!(
+        tbt_from_json(unknown_name, &atomics).err(),
+        Some("unknown atomic proposition 'unknown'".to_string())
+    );
+    let malformed = r#"{"type": "Leaf", "name": "l", "formula": {"type": "EventuallyInterval", "lower": 3, "upper": 1, "child": {"type": "Atomic", "expression": "a"}}}"#;
+    assert!(tbt_from_json(malformed, &atomics)
+        .err()
+        .unwrap()
+        .contains("malformed interval [3,1]"));
+}
+
+#[test]
+fn test_builder() {
+    let signal_name = "a".to_string();
+    // Two specifications that are built at the same time do not share indices
+    let first_builder = TbtBuilder::new();
+    let second_builder = TbtBuilder::new();
+    let first_leaf = first_builder.leaf(
+        first_builder
+            .globally(first_builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0]))),
+        String::from("globally"),
+    );
+    let second_leaf = second_builder.leaf(
+        second_builder.eventually(
+            second_builder.atomic(vec![signal_name.clone()], Arc::new(|a: &[f32]| a[0])),
+        ),
+        String::from("eventually"),
+    );
+    let second_tbt = second_builder.build(second_builder.kleene(1, second_leaf));
+    let first_tbt = first_builder.build(first_leaf);
+    assert_eq!(first_tbt.get_number_nodes(), 1);
+    assert_eq!(first_tbt.get_number_formulas(), 2);
+    // Kleene(1, T) consists of two Kleene nodes that share the leaf
+    assert_eq!(second_tbt.get_number_nodes(), 3);
+    assert_eq!(second_tbt.get_number_formulas(), 2);
+    let traces_with_expected_value = vec![(vec![1.0, 2.0, -1.0, 3.0], -1.0)];
+    run_test(traces_with_expected_value, signal_name.clone(), first_tbt).unwrap();
+    let traces_with_expected_value = vec![(vec![1.0, 2.0, -1.0, 3.0], 3.0)];
+    run_test(traces_with_expected_value, signal_name, second_tbt).unwrap();
+}
+
+/// Use case that is provided outside of the crate, the logfile is ignored
+struct PositiveThenNegative;
+
+impl ProvidesTraceAndTree for PositiveThenNegative {
+    fn get_trace(
+        _logfile: &str,
+        number_skipped_entries: usize,
+        _setting: &TraceSetting,
+    ) -> Result<Trace, String> {
+        let values: Vec<f32> = vec![1.0, 2.0, 1.0, 2.0, -1.0, -2.0]
+            .into_iter()
+            .step_by(number_skipped_entries + 1)
+            .collect();
+        Trace::new(HashMap::from([("a".to_string(), values)]))
+    }
+
+    fn get_tree(_number_skipped_entries: usize) -> Tbt {
+        parse_tbt(
+            "Sequence([
+                Leaf(positive, Globally(Atomic(positive))),
+                Leaf(negative, Globally(Atomic(-a)))
+            ])",
+            &PositiveThenNegative::get_atomics(),
+        )
+        .unwrap()
+    }
+
+    fn get_atomics() -> AtomicRegistry {
+        HashMap::from([(
+            "positive".to_string(),
+            (vec!["a".to_string()], Arc::new(|a: &[f32]| a[0]) as _),
+        )])
+    }
+}
+
+#[test]
+fn test_use_case() {
+    let trace = read_trace::<PositiveThenNegative>("", 0, &TraceSetting::default()).unwrap();
+    let (number_skipped_entries, delta_rho_skipped) =
+        get_best_number_skipped_entries::<PositiveThenNegative>(&trace, false, None);
+    assert_eq!((number_skipped_entries, delta_rho_skipped), (0, 0.0));
+    let (trace, tbt) =
+        get_tbt_and_trace::<PositiveThenNegative>(trace, number_skipped_entries, None).unwrap();
+    assert_eq!(trace.len(), 6);
+    // Signals of the tree that are missing in the trace are an error instead of a panic
+    let other = parse_stream_trace("y\n2\n0\n", "stdin", 0, BadValuePolicy::Fail).unwrap();
+    assert_eq!(
+        get_tbt_and_trace::<PositiveThenNegative>(other, 0, None)
+            .err()
+            .unwrap(),
+        "signal 'a' of the specification is not part of the trace, which has the signals y"
+    );
+    let result = evaluate(
+        tbt,
+        trace,
+        SystemTime::now(),
+        false,
+        false,
+        Semantics::Classic,
+        TableBackend::Dense,
+        1,
+        delta_rho_skipped,
+        false,
+        Some(SegmentationSetting {
+            tau_dif: 1,
+            rho_dif: 10.0,
+            amount: 1,
+        }),
+        false,
+    )
+    .unwrap();
+    assert_eq!(result.robustness, 1.0);
+    assert!(!result.is_approximate);
+    assert_eq!(result.statistics.tree_entries, 3 * 21);
+    // The segmentation is returned instead of printed
+    let leaves: Vec<_> = result
+        .segmentation
+        .iter()
+        .filter(|segment| segment.is_leaf())
+        .map(|segment| (segment.name.clone().unwrap(), segment.lower, segment.upper))
+        .collect();
+    assert_eq!(
+        leaves,
+        vec![
+            ("positive".to_string(), 0, 3),
+            ("negative".to_string(), 4, 5)
+        ]
+    );
+    assert_eq!(result.segmentation[0].kind, "Sequence");
+    assert_eq!(result.segmentation_robustness, 1.0);
+    assert_eq!(result.alternatives.len(), 1);
+    assert!(result.alternatives[0].robustness < 1.0);
+}
+
+#[test]
+fn test_table_backends() {
+    let atomics = AtomicRegistry::new();
+    let tbt = parse_tbt(
+        "Sequence([Leaf(up, Eventually(Atomic(a))), Fallback([Leaf(down, Globally(Neg(Atomic(a)))), Leaf(hold, GloballyInterval(0, 3, Atomic(a + 1)))])])",
+        &atomics,
+    )
+    .unwrap();
+    let mut seed = 7;
+    let trace = Trace::new(HashMap::from([(
+        "a".to_string(),
+        random_values(&mut seed, 12),
+    )]))
+    .unwrap();
+    let results: Vec<_> = [
+        TableBackend::Dense,
+        TableBackend::Lazy,
+        TableBackend::Sparse,
+    ]
+    .into_iter()
+    .map(|table_backend| {
+        evaluate(
+            tbt.clone(),
+            trace.clone(),
+            SystemTime::now(),
+            false,
+            false,
+            Semantics::Classic,
+            table_backend,
+            1,
+            0.0,
+            false,
+            Some(SegmentationSetting {
+                tau_dif: 2,
+                rho_dif: 10.0,
+                amount: 2,
+            }),
+            false,
+        )
+        .unwrap()
+    })
+    .collect();
+    for result in &results[1..] {
+        assert_eq!(result.robustness, results[0].robustness);
+        assert_eq!(result.segmentation, results[0].segmentation);
+        assert_eq!(result.alternatives.len(), results[0].alternatives.len());
+        for (alternative, expected) in result.alternatives.iter().zip(&results[0].alternatives) {
+            assert_eq!(alternative.robustness, expected.robustness);
+            assert_eq!(alternative.segmentation, expected.segmentation);
+        }
+        assert_eq!(
+            result.statistics.tree_lookups,
+            results[0].statistics.tree_lookups
+        );
+    }
+    // Dense tables are allocated at once, sparse tables only store entries that are set
+    let dense = estimate_table_memory(&tbt, 1000, TableBackend::Dense);
+    let lazy = estimate_table_memory(&tbt, 1000, TableBackend::Lazy);
+    let sparse = estimate_table_memory(&tbt, 1000, TableBackend::Sparse);
+    assert_eq!(dense.initial, dense.maximum);
+    let upper_dependent = tbt
+        .get_formula_horizons()
+        .iter()
+        .filter(|horizon| horizon.is_none())
+        .count();
+    let number_entries = (tbt.get_number_nodes() + upper_dependent) * 500_500;
+    assert!(dense.initial >= number_entries * std::mem::size_of::<f32>());
+    assert!(lazy.initial < dense.initial / 10);
+    assert!(lazy.maximum >= dense.maximum);
+    assert_eq!(sparse.initial, 0);
+    assert!(sparse.maximum > dense.maximum);
+}
+
+#[test]
+fn test_monitor() {
+    let atomics = AtomicRegistry::new();
+    let tbt = parse_tbt(
+        "Sequence([Leaf(up, Eventually(Atomic(a))), Fallback([Leaf(down, Globally(Neg(Atomic(a)))), Leaf(hold, GloballyInterval(0, 3, Atomic(a + 1)))])])",
+        &atomics,
+    )
+    .unwrap();
+    let mut seed = 11;
+    let values = random_values(&mut seed, 12);
+    let offline: Vec<f32> = (1..=values.len())
+        .map(|length| {
+            let trace = Trace::new(HashMap::from([(
+                "a".to_string(),
+                values[..length].to_vec(),
+            )]))
+            .unwrap();
+            evaluate(
+                tbt.clone(),
+                trace,
+                SystemTime::now(),
+                false,
+                false,
+                Semantics::Classic,
+                TableBackend::Dense,
+                1,
+                0.0,
+                false,
+                None,
+                false,
+            )
+            .unwrap()
+            .robustness
+        })
+        .collect();
+    let lookahead = 2;
+    for table_backend in [TableBackend::Dense, TableBackend::Sparse] {
+        let mut monitor = OnlineMonitor::new(
+            tbt.clone(),
+            None,
+            Semantics::Classic,
+            table_backend,
+            Some(lookahead),
+            None,
+        )
+        .unwrap();
+        assert_eq!(monitor.signals(), vec!["a".to_string()]);
+        let verdicts: Vec<_> = values
+            .iter()
+            .map(|value| {
+                monitor
+                    .push(&HashMap::from([("a".to_string(), *value)]))
+                    .unwrap()
+            })
+            .collect();
+        for (i, verdict) in verdicts.iter().enumerate() {
+            // The robustness is the one of the prefix and the interval contains the following prefixes
+            assert_eq!(verdict.sample, i);
+            assert_eq!(verdict.robustness, offline[i]);
+            for robustness in offline.iter().skip(i).take(lookahead + 1) {
+                assert!(verdict.worst_case <= *robustness && *robustness <= verdict.best_case);
+            }
+            let active_leaf = verdict.active_leaf.as_ref().unwrap();
+            assert!(active_leaf.lower <= i && i <= active_leaf.upper);
+        }
+        assert!(monitor
+            .push(&HashMap::from([("b".to_string(), 0.0)]))
+            .is_err());
+    }
+    // The tables grow beyond their initial capacity of 256 samples and the memory limit is checked
+    let mut monitor = OnlineMonitor::new(
+        parse_tbt("Leaf(up, Eventually(Atomic(a)))", &atomics).unwrap(),
+        None,
+        Semantics::Classic,
+        TableBackend::Sparse,
+        Some(0),
+        None,
+    )
+    .unwrap();
+    let mut maximum = f32::NEG_INFINITY;
+    for value in random_values(&mut seed, 300) {
+        maximum = maximum.max(value);
+        let verdict = monitor
+            .push(&HashMap::from([("a".to_string(), value)]))
+            .unwrap();
+        assert_eq!(verdict.robustness, maximum);
+        assert_eq!(verdict.best_case, maximum);
+    }
+    assert_eq!(monitor.len(), 300);
+    // The lookahead is the horizon of the TBT, which is required without bounded operators
+    assert_eq!(tbt.get_horizon(), Some(3));
+    assert!(OnlineMonitor::new(
+        parse_tbt("Leaf(up, Eventually(Atomic(a)))", &atomics).unwrap(),
+        None,
+        Semantics::Classic,
+        TableBackend::Dense,
+        None,
+        None
+    )
+    .is_err());
+    assert!(OnlineMonitor::new(
+        tbt,
+        None,
+        Semantics::Classic,
+        TableBackend::Dense,
+        None,
+        Some(1000)
+    )
+    .is_err());
+    // Bounds in seconds of a monitored csv-file are resolved against its time column instead of --rate
+    let file = std::env::temp_dir().join(format!("tbt_monitor_{}.csv", std::process::id()));
+    let tbt = parse_tbt("Leaf(up, EventuallyInterval(0, 1s, Atomic(a)))", &atomics).unwrap();
+    let mut arguments = CommandLineArguments {
+        logfile: String::new(),
+        specification: None,
+        lazy_evaluation: false,
+        semantics: Semantics::Classic,
+        table_backend: TableBackend::Dense,
+        memory_limit: None,
+        threads: 1,
+        sub_sampling: false,
+        debug_console: false,
+        print_leaf_segments_only: false,
+        segmentation_setting: None,
+        output_format: OutputFormat::Csv,
+        output: None,
+        trace_setting: TraceSetting {
+            rate: Some(1.0),
+            ..TraceSetting::default()
+        },
+        batch: None,
+        check: None,
+        monitor: None,
+    };
+    let setting = MonitorSetting {
+        source: Some(file.to_str().unwrap().to_string()),
+        lookahead: Some(0),
+    };
+    let run = |content: &str, arguments: &CommandLineArguments| {
+        std::fs::write(&file, content).unwrap();
+        let mut output = vec![];
+        run_monitor::<PositiveThenNegative>(Some(tbt.clone()), arguments, &setting, &mut output)
+            .map(|_| String::from_utf8(output).unwrap())
+    };
+    let output = run("time,a\n0,0\n0.5,0\n1,5\n1.5,0\n", &arguments).unwrap();
+    assert!(output.lines().nth(4).unwrap().starts_with("3,5.0,"));
+    let output = run("a\n0\n0\n5\n0\n", &arguments).unwrap();
+    assert!(output.lines().nth(4).unwrap().starts_with("3,0.0,"));
+    arguments.trace_setting.rate = None;
+    let error = run("a\n0\n0\n5\n0\n", &arguments).unwrap_err();
+    std::fs::remove_file(&file).unwrap();
+    assert!(error.contains("--rate"));
+}
+
+#[test]
+fn test_threads() {
+    let atomics = AtomicRegistry::new();
+    let tbt = parse_tbt(
+        "Sequence([
+            Kleene(2, Fallback([Leaf(up, Eventually(Atomic(a))), Leaf(low, Globally(Atomic(0.5 - a)))])),
+            Parallel(1, [Timeout(4, Leaf(hold, GloballyInterval(0, 2, Atomic(a + 1)))), Leaf(down, Eventually(Neg(Atomic(a))))])
+        ])",
+        &atomics,
+    )
+    .unwrap();
+    let mut seed = 11;
+    let trace = Trace::new(HashMap::from([(
+        "a".to_string(),
+        random_values(&mut seed, 16),
+    )]))
+    .unwrap();
+    // Parallel evaluation combines the values in the same order, i.e., the results are identical
+    for semantics in [Semantics::Classic, Semantics::Smooth(5.0)] {
+        let results: Vec<_> = [1, 4]
+            .into_iter()
+            .map(|threads| {
+                evaluate(
+                    tbt.clone(),
+                    trace.clone(),
+                    SystemTime::now(),
+                    false,
+                    false,
+                    semantics,
+                    TableBackend::Dense,
+                    threads,
+                    0.0,
+                    false,
+                    None,
+                    false,
+                )
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(results[0].robustness, results[1].robustness);
+        assert_eq!(results[0].segmentation, results[1].segmentation);
+    }
+    // A single thread evaluates the tree even inside another thread pool, e.g., of the batch command
+    let thread_ids = Arc::new(std::sync::Mutex::new(std::collections::HashSet::new()));
+    let atomics: AtomicRegistry = (0..4)
+        .map(|k| {
+            let recorded = thread_ids.clone();
+            let function = Arc::new(move |a: &[f32]| {
+                // Slow enough that idle threads of the pool would steal work
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                recorded.lock().unwrap().insert(std::thread::current().id());
+                a[0]
+            });
+            (
+                format!("recorded{k}"),
+                (vec!["a".to_string()], function as _),
+            )
+        })
+        .collect();
+    let tbt = parse_tbt(
+        "Parallel(1, [Leaf(a, Eventually(Atomic(recorded0))), Leaf(b, Eventually(Atomic(recorded1))), Leaf(c, Eventually(Atomic(recorded2))), Leaf(d, Eventually(Atomic(recorded3)))])",
+        &atomics,
+    )
+    .unwrap();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        evaluate(
+            tbt,
+            trace,
+            SystemTime::now(),
+            false,
+            false,
+            Semantics::Classic,
+            TableBackend::Dense,
+            1,
+            0.0,
+            false,
+            None,
+            false,
+        )
+        .unwrap()
+    });
+    assert_eq!(thread_ids.lock().unwrap().len(), 1);
+    // Statistics of tables that are shared by threads count every lookup
+    let table = Table::new(1, 4, TableBackend::Dense);
+    table.set(0, 0, 3, 1.0);
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..10_000 {
+                    table.lookup(0, 0, 3);
+                }
+            });
+        }
+    });
+    assert_eq!(table.total_lookups(), 40_000);
+}
+
+#[test]
+fn test_export() {
+    let trace = read_trace::<PositiveThenNegative>("", 0, &TraceSetting::default()).unwrap();
+    let (trace, tbt) = get_tbt_and_trace::<PositiveThenNegative>(trace, 2, None).unwrap();
+    let mapping = SampleMapping {
+        number_skipped_entries: 2,
+        time: Some(vec![0.0, 0.5, 1.0]),
+    };
+    let result = evaluate(
+        tbt,
+        trace,
+        SystemTime::now(),
+        true,
+        false,
+        Semantics::Classic,
+        TableBackend::Dense,
+        1,
+        0.0,
+        false,
+        None,
+        false,
+    )
+    .unwrap();
+    // The JSON contains the nested segmentation
+    let json: serde_json::Value =
+        serde_json::from_str(&result_to_json(&result, &mapping).unwrap()).unwrap();
+    assert_eq!(json["robustness"], 1.0);
+    let root = &json["segmentation"];
+    assert_eq!(root["kind"], "Sequence");
+    let children = root["children"].as_array().unwrap();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[1]["name"], "negative");
+    assert_eq!(children[1]["lower"], 2);
+    assert_eq!(children[1]["original_lower"], 4);
+    assert_eq!(children[1]["start_time"], 1.0);
+    // The CSV contains one row per segment and refers to the parent segment
+    let csv = result_to_csv(&result, &mapping, true).unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1], "0,1,0,0,Leaf,positive,0,1,0,2,0,0.5,1");
+    // The JUnit report contains the robustness and each leaf segment as test case
+    assert!(result.passes(0.0));
+    assert!(!result.passes(1.5));
+    let junit = result_to_junit(&result, &mapping, "logs<1>", 0.0);
+    assert!(junit.contains("tests=\"3\" failures=\"0\""));
+    assert!(junit.contains("<testcase name=\"negative [4, 4]\" classname=\"logs&lt;1&gt;\"/>"));
+    let junit = result_to_junit(&result, &mapping, "logs", 1.5);
+    assert!(junit.contains("tests=\"3\" failures=\"3\""));
+    assert_eq!(junit.matches("<failure ").count(), 3);
+}
+
+/// Use case that reads the trace from the file trace.csv of a log folder
+struct ClimbOrDescend;
+
+impl ProvidesTraceAndTree for ClimbOrDescend {
+    fn get_trace(
+        logfile: &str,
+        number_skipped_entries: usize,
+        setting: &TraceSetting,
+    ) -> Result<Trace, String> {
+        read_csv_trace(
+            &format!("{logfile}trace.csv"),
+            CsvColumns::AllNumeric,
+            "",
+            number_skipped_entries,
+            setting.bad_value_policy,
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    fn get_tree(_number_skipped_entries: usize) -> Tbt {
+        parse_tbt(
+            "Fallback([
+                Leaf(climb, Globally(Atomic(a))),
+                Leaf(descend, Globally(Atomic(-a)))
+            ])",
+            &AtomicRegistry::new(),
+        )
+        .unwrap()
+    }
+}
+
+#[test]
+fn test_batch() {
+    let directory = std::env::temp_dir().join(format!("tbt_batch_{}", std::process::id()));
+    for (log, content) in [
+        ("logs_climb", "a\n1\n2\n3\n"),
+        ("logs_descend", "a\n-1\n-2\n"),
+        ("logs_invalid", "a\n1\nx\n"),
+        ("other", "a\n1\n"),
+    ] {
+        std::fs::create_dir_all(directory.join(log)).unwrap();
+        std::fs::write(directory.join(log).join("trace.csv"), content).unwrap();
+    }
+    let directory_name = directory.to_str().unwrap();
+    // A directory with subdirectories contains log folders, wildcards select log folders
+    let logs = find_logs(&[directory_name.to_string()]).unwrap();
+    assert_eq!(logs.len(), 4);
+    assert_eq!(logs[0], format!("{directory_name}/logs_climb/"));
+    let logs = find_logs(&[format!("{directory_name}/logs_*")]).unwrap();
+    assert_eq!(logs.len(), 3);
+    assert_eq!(
+        find_logs(&[format!("{directory_name}/logs_?limb")]).unwrap(),
+        vec![format!("{directory_name}/logs_climb/")]
+    );
+    assert!(find_logs(&[format!("{directory_name}/missing_*")]).is_err());
+    let arguments = CommandLineArguments {
+        logfile: String::new(),
+        specification: None,
+        lazy_evaluation: false,
+        semantics: Semantics::Classic,
+        table_backend: TableBackend::Dense,
+        memory_limit: None,
+        threads: 1,
+        sub_sampling: false,
+        debug_console: false,
+        print_leaf_segments_only: false,
+        segmentation_setting: None,
+        output_format: OutputFormat::Csv,
+        output: None,
+        trace_setting: TraceSetting::default(),
+        batch: None,
+        check: None,
+        monitor: None,
+    };
+    // An invalid log does not abort the batch and the order of the logs is kept
+    for jobs in [1, 2] {
+        let entries = run_batch::<ClimbOrDescend>(&logs, None, &arguments, jobs).unwrap();
+        assert_eq!(entries.len(), 3);
+        let climb = entries[0].outcome.as_ref().unwrap();
+        // A Fallback may start its child at any position, i.e., the last value is the best
+        assert_eq!(climb.robustness, 3.0);
+        assert_eq!(climb.branch.as_deref(), Some("climb"));
+        assert_eq!(climb.segments.len(), 1);
+        assert_eq!(
+            (
+                climb.segments[0].original_lower,
+                climb.segments[0].original_upper
+            ),
+            (2, 2)
+        );
+        let descend = entries[1].outcome.as_ref().unwrap();
+        assert_eq!(descend.robustness, 2.0);
+        assert_eq!(descend.branch.as_deref(), Some("descend"));
+        assert!(entries[2]
+            .outcome
+            .as_ref()
+            .unwrap_err()
+            .contains("trace.csv"));
+        let csv = batch_to_csv(&entries).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        let row = csv.lines().nth(2).unwrap();
+        assert!(row.contains(",2,descend,\"descend[1, 1]\","));
+    }
+    // The branch is the chosen child of the top-level Fallback, nested Fallbacks are not reported
+    let specification = parse_tbt(
+        "Fallback([
+            Sequence([Leaf(rise, Globally(Atomic(a))), Fallback([Leaf(hold, Globally(Atomic(a))), Leaf(stop, Globally(Atomic(-a)))])]),
+            Leaf(sink, Globally(Atomic(-a)))
+        ])",
+        &AtomicRegistry::new(),
+    )
+    .unwrap();
+    let entries =
+        run_batch::<ClimbOrDescend>(&logs[..2], Some(&specification), &arguments, 1).unwrap();
+    let branches: Vec<_> = entries
+        .iter()
+        .map(|entry| entry.outcome.as_ref().unwrap().branch.clone())
+        .collect();
+    assert_eq!(
+        branches,
+        vec![Some("rise".to_string()), Some("sink".to_string())]
+    );
+    // Memory limits are checked for each log
+    let arguments = CommandLineArguments {
+        memory_limit: Some(1),
+        ..arguments
+    };
+    let entries = run_batch::<ClimbOrDescend>(&logs[..1], None, &arguments, 1).unwrap();
+    assert!(entries[0]
+        .outcome
+        .as_ref()
+        .unwrap_err()
+        .contains("memory limit"));
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_csv_reader() {
+    let file = std::env::temp_dir().join(format!("tbt_csv_reader_{}.csv", std::process::id()));
+    std::fs::write(&file, "time,a,b\n0,1,4\n1,x,5\n2,3,6\n3,4,y\n").unwrap();
+    let file_name = file.to_str().unwrap();
+    let read = |column: &str, policy: BadValuePolicy| read_csv_file(file_name, column, 0, policy);
+    // Errors are returned instead of panicking
+    assert_eq!(
+        read("a", BadValuePolicy::Fail),
+        Err(CsvError::Parse {
+            file: file_name.to_string(),
+            column: "a".to_string(),
+            row: 3,
+            value: "x".to_string(),
+        })
+    );
+    assert!(matches!(
+        read("c", BadValuePolicy::Fail),
+        Err(CsvError::MissingColumn { .. })
+    ));
+    assert!(matches!(
+        read_csv_file("missing.csv", "a", 0, BadValuePolicy::Fail),
+        Err(CsvError::Io { .. })
+    ));
+    // A bad value skips the row of the read column, other columns are not checked
+    assert_eq!(read("a", BadValuePolicy::SkipRow), Ok(vec![1.0, 3.0, 4.0]));
+    assert_eq!(
+        read("time", BadValuePolicy::SkipRow),
+        Ok(vec![0.0, 1.0, 2.0, 3.0])
+    );
+    assert_eq!(
+        read("a", BadValuePolicy::ForwardFill),
+        Ok(vec![1.0, 1.0, 3.0, 4.0])
+    );
+    assert_eq!(
+        read("b", BadValuePolicy::ForwardFill),
+        Ok(vec![4.0, 5.0, 6.0, 6.0])
+    );
+    assert!(read("a", BadValuePolicy::Nan).unwrap()[1].is_nan());
+    // Skipped rows are not counted for subsampling
+    assert_eq!(
+        read_csv_file(file_name, "b", 2, BadValuePolicy::SkipRow),
+        Ok(vec![4.0, 6.0])
+    );
+    // Text and empty columns do not skip rows
+    let text_file =
+        std::env::temp_dir().join(format!("tbt_csv_reader_text_{}.csv", std::process::id()));
+    std::fs::write(&text_file, "a,label,empty\n1,climb,\n2,descend,\n").unwrap();
+    assert_eq!(
+        read_csv_file(text_file.to_str().unwrap(), "a", 0, BadValuePolicy::SkipRow),
+        Ok(vec![1.0, 2.0])
+    );
+    std::fs::remove_file(&text_file).unwrap();
+    // Several columns are read in one pass and renamed
+    let trace = read_csv_trace(
+        file_name,
+        CsvColumns::Selected(&[("a", "x"), ("b", "y")]),
+        "uas_",
+        0,
+        BadValuePolicy::SkipRow,
+    )
+    .unwrap();
+    assert_eq!(trace.len(), 2);
+    assert_eq!(trace.get("uas_x"), Some(&vec![1.0, 3.0]));
+    assert_eq!(trace.get("uas_y"), Some(&vec![4.0, 6.0]));
+    let trace = read_csv_trace(
+        file_name,
+        CsvColumns::AllNumeric,
+        "",
+        1,
+        BadValuePolicy::Nan,
+    )
+    .unwrap();
+    assert_eq!(trace.len(), 4);
+    assert_eq!(trace.signals().len(), 3);
+    assert_eq!(trace.get("time"), Some(&vec![0.0, 1.0, 2.0, 3.0]));
+    assert!(read_csv_trace(
+        file_name,
+        CsvColumns::Selected(&[("c", "c")]),
+        "",
+        0,
+        BadValuePolicy::Fail
+    )
+    .is_err());
+    // Rows are read one at a time, e.g., from stdin
+    let samples: Vec<_> = CsvSamples::new(
+        std::fs::File::open(&file).unwrap(),
+        "stdin",
+        &["b".to_string(), "a".to_string()],
+        BadValuePolicy::SkipRow,
+    )
+    .unwrap()
+    .collect();
+    assert_eq!(
+        samples,
+        vec![
+            Ok(HashMap::from([
+                ("a".to_string(), 1.0),
+                ("b".to_string(), 4.0)
+            ])),
+            Ok(HashMap::from([
+                ("a".to_string(), 3.0),
+                ("b".to_string(), 6.0)
+            ])),
+        ]
+    );
+    assert!(matches!(
+        CsvSamples::new(
+            std::fs::File::open(&file).unwrap(),
+            "stdin",
+            &["c".to_string()],
+            BadValuePolicy::Fail
+        ),
+        Err(CsvError::MissingColumn { .. })
+    ));
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_stream_reader() {
+    let csv = "time,a,b\n0,1,4\n0.5,x,5\n1,3,6\n";
+    let json_lines = "{\"time\": 0, \"a\": 1, \"b\": 4, \"c\": \"text\"}\n\n{\"time\": 0.5, \"b\": 5}\n{\"time\": 1, \"a\": \"3\", \"b\": 6}\n";
+    assert_eq!(StreamFormat::detect(csv), StreamFormat::Csv);
+    assert_eq!(StreamFormat::detect(json_lines), StreamFormat::JsonLines);
+    assert!(is_stream("-"));
+    assert!(!is_stream(std::env::temp_dir().to_str().unwrap()));
+    // Both formats result in the same trace and time is used as time axis
+    for input in [csv, json_lines] {
+        let trace = parse_stream_trace(input, "stdin", 0, BadValuePolicy::ForwardFill).unwrap();
+        assert_eq!(trace.signals().len(), 2);
+        assert_eq!(trace.get("a"), Some(&vec![1.0, 1.0, 3.0]));
+        assert_eq!(trace.get("b"), Some(&vec![4.0, 5.0, 6.0]));
+        assert_eq!(trace.time(), Some(&[0.0, 0.5, 1.0][..]));
+        assert_eq!(trace.sampling_period(), Some(0.5));
+        let trace = parse_stream_trace(input, "stdin", 2, BadValuePolicy::SkipRow).unwrap();
+        assert_eq!(trace.get("a"), Some(&vec![1.0]));
+        assert_eq!(trace.time(), Some(&[0.0][..]));
+        assert!(parse_stream_trace(input, "stdin", 0, BadValuePolicy::Fail).is_err());
+    }
+    assert_eq!(
+        parse_stream_trace(json_lines, "stdin", 0, BadValuePolicy::Fail),
+        Err("Could not parse value 'missing' of signal 'a' in stdin at line 3".to_string())
+    );
+    assert!(
+        parse_stream_trace("{\"a\": 1}\n{\"a\": 2", "stdin", 0, BadValuePolicy::Fail)
+            .unwrap_err()
+            .contains("at line 2")
+    );
+    assert!(parse_stream_trace("", "stdin", 0, BadValuePolicy::Fail)
+        .unwrap()
+        .is_empty());
+    // A signal whose first value is empty or bad is kept and handled by the policy
+    let csv = "a,label,b\n,climb,1\n2,descend,x\n";
+    let json_lines =
+        "{\"a\": null, \"label\": \"climb\", \"b\": 1}\n{\"a\": 2, \"label\": \"descend\"}\n";
+    for input in [csv, json_lines] {
+        let trace = parse_stream_trace(input, "stdin", 0, BadValuePolicy::Nan).unwrap();
+        assert_eq!(trace.signals().len(), 2);
+        assert!(trace.get("a").unwrap()[0].is_nan());
+        assert_eq!(trace.get("a").unwrap()[1], 2.0);
+        assert!(trace.get("b").unwrap()[1].is_nan());
+    }
+    assert!(parse_stream_trace(csv, "stdin", 0, BadValuePolicy::Fail)
+        .unwrap_err()
+        .contains("column 'a'"));
+    // Empty streams, a header without rows, and empty JSON Lines are rejected before tables are created
+    let file = std::env::temp_dir().join(format!("tbt_empty_stream_{}", std::process::id()));
+    for input in ["", "x\n", "time,a\n", "\n\n"] {
+        std::fs::write(&file, input).unwrap();
+        assert_eq!(
+            read_trace::<ShipdeckLanding>(file.to_str().unwrap(), 0, &TraceSetting::default()),
+            Err("trace has no samples".to_string())
+        );
+    }
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_arrow_reader() {
+    use arrow_array::{
+        ArrayRef, Float64Array, Int32Array, RecordBatch, StringArray, TimestampMillisecondArray,
+    };
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "time",
+            Arc::new(TimestampMillisecondArray::from(vec![
+                1_700_000_000_000,
+                1_700_000_000_500,
+                1_700_000_001_000,
+            ])) as ArrayRef,
+        ),
+        (
+            "a",
+            Arc::new(Float64Array::from(vec![Some(1.0), None, Some(3.0)])) as ArrayRef,
+        ),
+        ("b", Arc::new(Int32Array::from(vec![4, 5, 6])) as ArrayRef),
+        (
+            "name",
+            Arc::new(StringArray::from(vec!["x", "y", "z"])) as ArrayRef,
+        ),
+    ])
+    .unwrap();
+    let directory = std::env::temp_dir();
+    let file = |extension: &str| {
+        directory
+            .join(format!(
+                "tbt_arrow_reader_{}.{extension}",
+                std::process::id()
+            ))
+            .to_str()
+            .unwrap()
+            .to_string()
+    };
+    let (parquet, ipc_file, ipc_stream) = (file("parquet"), file("arrow"), file("arrows"));
+    let mut writer = parquet::arrow::ArrowWriter::try_new(
+        std::fs::File::create(&parquet).unwrap(),
+        batch.schema(),
+        None,
+    )
+    .unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+    let mut writer = arrow_ipc::writer::FileWriter::try_new(
+        std::fs::File::create(&ipc_file).unwrap(),
+        &batch.schema(),
+    )
+    .unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+    let mut writer = arrow_ipc::writer::StreamWriter::try_new(
+        std::fs::File::create(&ipc_stream).unwrap(),
+        &batch.schema(),
+    )
+    .unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(ArrowFormat::from_path(&parquet), Some(ArrowFormat::Parquet));
+    assert_eq!(ArrowFormat::from_path(&ipc_file), Some(ArrowFormat::Ipc));
+    assert_eq!(ArrowFormat::from_path("trace.csv"), None);
+    for file_name in [&parquet, &ipc_file, &ipc_stream] {
+        // Numeric columns are cast to f32 and timestamps are seconds since the first row
+        let trace = read_arrow_trace(
+            file_name,
+            CsvColumns::AllNumeric,
+            "",
+            Some("time"),
+            0,
+            BadValuePolicy::ForwardFill,
+        )
+        .unwrap();
+        assert_eq!(trace.signals().len(), 2);
+        assert_eq!(trace.get("a"), Some(&vec![1.0, 1.0, 3.0]));
+        assert_eq!(trace.get("b"), Some(&vec![4.0, 5.0, 6.0]));
+        assert_eq!(trace.time(), Some(&[0.0, 0.5, 1.0][..]));
+        let trace = read_arrow_trace(
+            file_name,
+            CsvColumns::Selected(&[("a", "x")]),
+            "uas_",
+            None,
+            2,
+            BadValuePolicy::SkipRow,
+        )
+        .unwrap();
+        assert_eq!(trace.get("uas_x"), Some(&vec![1.0]));
+        assert_eq!(trace.time(), None);
+        assert_eq!(
+            read_arrow_trace(
+                file_name,
+                CsvColumns::AllNumeric,
+                "",
+                None,
+                0,
+                BadValuePolicy::Fail
+            ),
+            Err(format!(
+                "Could not read value of column 'a' in {file_name} at row 2: null or not a number"
+            ))
+        );
+        assert!(read_arrow_trace(
+            file_name,
+            CsvColumns::Selected(&[("c", "c")]),
+            "",
+            None,
+            0,
+            BadValuePolicy::Fail
+        )
+        .is_err());
+    }
+    // The logfile is read using the columns of the setting
+    let setting = TraceSetting {
+        columns: vec!["b".to_string()],
+        time_column: Some("time".to_string()),
+        ..TraceSetting::default()
+    };
+    let trace = read_trace::<ShipdeckLanding>(&parquet, 0, &setting).unwrap();
+    assert_eq!(trace.signals().len(), 1);
+    assert_eq!(trace.sampling_period(), Some(0.5));
+    for file_name in [parquet, ipc_file, ipc_stream] {
+        std::fs::remove_file(file_name).unwrap();
+    }
+}
+
+/// Appends a ULog message with its header
+fn push_ulog_message(ulog: &mut Vec<u8>, kind: u8, payload: &[u8]) {
+    ulog.extend((payload.len() as u16).to_le_bytes());
+    ulog.push(kind);
+    ulog.extend(payload);
+}
+
+#[test]
+fn test_ulog_reader() {
+    // Synthetic log that starts at 1 s with two instances of position (10 Hz) and attitude (5 Hz)
+    let mut ulog = vec![b'U', b'L', b'o', b'g', 0x01, 0x12, 0x35, 1];
+    ulog.extend(1_000_000_u64.to_le_bytes());
+    push_ulog_message(&mut ulog, b'B', &[0; 40]);
+    push_ulog_message(&mut ulog, b'I', b"\x0bchar[3] verabc");
+    for format in [
+        "position:uint64_t timestamp;float x;float y;double z;uint8_t[3] _padding0;",
+        "quat:float[2] q;",
+        "attitude:uint64_t timestamp;quat control;int16_t[2] rates;bool armed;",
+        "status:uint64_t timestamp;float v;",
+    ] {
+        push_ulog_message(&mut ulog, b'F', format.as_bytes());
+    }
+    for (multi_id, msg_id, name) in [
+        (0_u8, 1_u16, "position"),
+        (1, 2, "position"),
+        (0, 3, "attitude"),
+        (0, 4, "status"),
+    ] {
+        let mut payload = vec![multi_id];
+        payload.extend(msg_id.to_le_bytes());
+        payload.extend(name.as_bytes());
+        push_ulog_message(&mut ulog, b'A', &payload);
+    }
+    for k in 0..=10_u64 {
+        let mut payload = 1_u16.to_le_bytes().to_vec();
+        payload.extend((1_000_000 + k * 100_000).to_le_bytes());
+        payload.extend((k as f32).to_le_bytes());
+        payload.extend((-(k as f32)).to_le_bytes());
+        payload.extend((2.0 * k as f64).to_le_bytes());
+        push_ulog_message(&mut ulog, b'D', &payload);
+        if k % 2 == 0 {
+            let mut payload = 3_u16.to_le_bytes().to_vec();
+            payload.extend((1_000_000 + k * 100_000).to_le_bytes());
+            payload.extend((k as f32 * 0.25).to_le_bytes());
+            payload.extend(1.0_f32.to_le_bytes());
+            payload.extend((-(k as i16)).to_le_bytes());
+            payload.extend(0_i16.to_le_bytes());
+            payload.push((k % 4 == 0) as u8);
+            push_ulog_message(&mut ulog, b'D', &payload);
+        }
+        if k % 10 == 0 {
+            let mut payload = 2_u16.to_le_bytes().to_vec();
+            payload.extend((1_000_000 + k * 100_000).to_le_bytes());
+            payload.extend([0; 4]);
+            payload.extend(7.0_f32.to_le_bytes());
+            payload.extend([0; 8]);
+            push_ulog_message(&mut ulog, b'D', &payload);
+        }
+        push_ulog_message(&mut ulog, b'L', b"\x06\0\0\0\0\0\0\0\0message");
+    }
+    // The log ends within a message
+    ulog.extend([20, 0, b'D', 1, 0]);
+    let file = std::env::temp_dir().join(format!("tbt_ulog_reader_{}.ulg", std::process::id()));
+    std::fs::write(&file, &ulog).unwrap();
+    let file_name = file.to_str().unwrap();
+    let fields = [
+        ("position.x", "uas_x"),
+        ("position.z", "uas_z"),
+        ("position[1].y", "other_y"),
+        ("attitude.control.q[0]", "q0"),
+        ("attitude.rates[0]", "roll_rate"),
+        ("attitude.armed", "armed"),
+    ];
+    let read = |number_skipped_entries, interpolation, rate| {
+        read_ulog_trace(
+            file_name,
+            CsvColumns::Selected(&fields),
+            number_skipped_entries,
+            interpolation,
+            rate,
+        )
+    };
+    // Topics are aligned onto the time base of the topic with most messages
+    let trace = read(0, Interpolation::ZeroOrderHold, None).unwrap();
+    assert_eq!(trace.len(), 11);
+    assert_eq!(trace.signals().len(), 6);
+    assert_eq!(trace.timestamp(0), Some(0.0));
+    assert_eq!(trace.sampling_period(), Some(0.1));
+    assert_eq!(trace.get("uas_x").unwrap()[3], 3.0);
+    assert_eq!(trace.get("uas_z").unwrap()[3], 6.0);
+    assert_eq!(trace.get("other_y").unwrap()[5], 7.0);
+    assert_eq!(trace.get("q0").unwrap()[3], 0.5);
+    assert_eq!(trace.get("roll_rate").unwrap()[4], -4.0);
+    assert_eq!(trace.get("armed").unwrap()[..4], [1.0, 1.0, 0.0, 0.0]);
+    let trace = read(0, Interpolation::Linear, Some(5.0)).unwrap();
+    assert_eq!(trace.len(), 6);
+    assert_eq!(trace.get("uas_x").unwrap()[2], 4.0);
+    let trace = read(2, Interpolation::ZeroOrderHold, None).unwrap();
+    assert_eq!(trace.len(), 6);
+    assert_eq!(trace.get("uas_x").unwrap()[1], 2.0);
+    // All numeric fields except timestamps and padding, topics without data are skipped
+    let trace = read_ulog_trace(
+        file_name,
+        CsvColumns::AllNumeric,
+        0,
+        Interpolation::ZeroOrderHold,
+        None,
+    )
+    .unwrap();
+    assert_eq!(trace.signals().len(), 11);
+    assert!(trace.get("position[1].z").is_some());
+    assert!(trace.get("attitude.control.q[1]").is_some());
+    assert!(trace.get("position.timestamp").is_none());
+    assert!(read_ulog_trace(
+        file_name,
+        CsvColumns::Selected(&[("position.w", "w")]),
+        0,
+        Interpolation::ZeroOrderHold,
+        None
+    )
+    .unwrap_err()
+    .contains("field position.w not found"));
+    assert!(read_ulog_trace(
+        file_name,
+        CsvColumns::Selected(&[("status.v", "v")]),
+        0,
+        Interpolation::ZeroOrderHold,
+        None
+    )
+    .unwrap_err()
+    .contains("topic status has no data"));
+    // A format that contains itself is an error instead of a stack overflow
+    let mut cyclic = ulog[..16].to_vec();
+    push_ulog_message(&mut cyclic, b'F', b"loop:uint64_t timestamp;loop next;");
+    push_ulog_message(&mut cyclic, b'A', b"\0\x01\0loop");
+    std::fs::write(&file, &cyclic).unwrap();
+    assert!(read(0, Interpolation::ZeroOrderHold, None)
+        .unwrap_err()
+        .contains("format loop contains itself"));
+    std::fs::write(&file, b"time,a\n0,1\n").unwrap();
+    assert!(read(0, Interpolation::ZeroOrderHold, None)
+        .unwrap_err()
+        .contains("not a ULog file"));
+    std::fs::remove_file(file).unwrap();
+}
+
+/// Appends a record with a MAVLink 1 packet or a MAVLink 2 packet (optionally signed) to a telemetry log
+fn push_tlog_packet(
+    tlog: &mut Vec<u8>,
+    timestamp: u64,
+    version: u8,
+    system_id: u8,
+    message: (u32, u8),
+    payload: &[u8],
+) {
+    let (message_id, crc_extra) = message;
+    tlog.extend(timestamp.to_be_bytes());
+    let mut packet = match version {
+        1 => vec![0xFE, payload.len() as u8, 0, system_id, 1, message_id as u8],
+        _ => {
+            // MAVLink 2 removes trailing zeros of the payload
+            let length = payload
+                .iter()
+                .rposition(|byte| *byte != 0)
+                .map_or(1, |i| i + 1);
+            let mut packet = vec![0xFD, length as u8, (version == 3) as u8, 0, 0, system_id, 1];
+            packet.extend(&message_id.to_le_bytes()[..3]);
+            packet.extend(&payload[..length]);
+            packet
+        }
+    };
+    if version == 1 {
+        packet.extend(payload);
+    }
+    let mut checked = packet[1..].to_vec();
+    checked.push(crc_extra);
+    packet.extend(mavlink_crc(&checked).to_le_bytes());
+    if version == 3 {
+        packet.extend([0; 13]);
+    }
+    tlog.extend(packet);
+}
+
+#[test]
+fn test_tlog_reader() {
+    assert_eq!(mavlink_crc(b"123456789"), 0x6F91);
+    let (attitude, local_position, global_position) = ((30, 39), (32, 185), (33, 104));
+    // Synthetic log with position (MAVLink 1, 10 Hz), attitude (MAVLink 2, 5 Hz) and a signed global position (1 Hz)
+    let mut tlog = vec![];
+    let start = 1_700_000_000_000_000_u64;
+    for k in 0..=10_u32 {
+        let timestamp = start + k as u64 * 100_000;
+        let mut payload = (k * 100).to_le_bytes().to_vec();
+        for value in [k as f32, 0.0, -2.0 * k as f32, 1.0, 0.0, 0.0] {
+            payload.extend(value.to_le_bytes());
+        }
+        push_tlog_packet(&mut tlog, timestamp, 1, 1, local_position, &payload);
+        if k % 2 == 0 {
+            let mut payload = (k * 100).to_le_bytes().to_vec();
+            for value in [k as f32 * 0.5, 0.25, 0.0, 0.0, 0.0, 0.0] {
+                payload.extend(value.to_le_bytes());
+            }
+            push_tlog_packet(&mut tlog, timestamp, 2, 1, attitude, &payload);
+        }
+        if k % 10 == 0 {
+            let mut payload = (k * 100).to_le_bytes().to_vec();
+            for value in [473_977_420_i32, 85_455_940, 488_000, 5_000] {
+                payload.extend(value.to_le_bytes());
+            }
+            for value in [-150_i16, 20, 0] {
+                payload.extend(value.to_le_bytes());
+            }
+            payload.extend(u16::MAX.to_le_bytes());
+            push_tlog_packet(&mut tlog, timestamp, 3, 2, global_position, &payload);
+        }
+        if k == 5 {
+            // Attitude of another system, an unknown message, garbage, and a packet with a wrong checksum
+            let mut payload = 500_u32.to_le_bytes().to_vec();
+            payload.extend([0; 24]);
+            push_tlog_packet(&mut tlog, timestamp, 2, 3, attitude, &payload);
+            push_tlog_packet(&mut tlog, timestamp, 1, 1, (0, 50), &[0; 9]);
+            tlog.extend([0xFD, 0x01, 0x02]);
+            let mut corrupted = vec![];
+            push_tlog_packet(
+                &mut corrupted,
+                timestamp + 50_000,
+                1,
+                1,
+                local_position,
+                &[1; 28],
+            );
+            corrupted[20] ^= 0xFF;
+            tlog.extend(corrupted);
+        }
+    }
+    // The log ends within a record
+    tlog.extend(&start.to_be_bytes()[..5]);
+    tlog.extend([0xFE, 28]);
+    let file = std::env::temp_dir().join(format!("tbt_tlog_reader_{}.tlog", std::process::id()));
+    std::fs::write(&file, &tlog).unwrap();
+    let file_name = file.to_str().unwrap();
+    let fields = [
+        ("LOCAL_POSITION_NED.x", "uas_x"),
+        ("LOCAL_POSITION_NED.z", "uas_z"),
+        ("ATTITUDE.roll", "roll"),
+        ("GLOBAL_POSITION_INT[2].lat", "lat"),
+        ("GLOBAL_POSITION_INT.vx", "vx"),
+        ("GLOBAL_POSITION_INT.hdg", "hdg"),
+    ];
+    let read = |number_skipped_entries, rate| {
+        read_tlog_trace(
+            file_name,
+            CsvColumns::Selected(&fields),
+            number_skipped_entries,
+            Interpolation::ZeroOrderHold,
+            rate,
+        )
+    };
+    // Messages are aligned onto the time base of the message with most records
+    let trace = read(0, None).unwrap();
+    assert_eq!(trace.len(), 11);
+    assert_eq!(trace.timestamp(0), Some(0.0));
+    assert_eq!(trace.sampling_period(), Some(0.1));
+    assert_eq!(trace.get("uas_x").unwrap()[5..7], [5.0, 6.0]);
+    assert_eq!(trace.get("uas_z").unwrap()[3], -6.0);
+    assert_eq!(
+        trace.get("roll").unwrap()[..6],
+        [0.0, 0.0, 1.0, 1.0, 2.0, 2.0]
+    );
+    assert!((trace.get("lat").unwrap()[0] - 47.397742).abs() < 1e-4);
+    assert_eq!(trace.get("vx").unwrap()[0], -1.5);
+    assert!(trace.get("hdg").unwrap()[0].is_nan());
+    assert_eq!(read(0, Some(5.0)).unwrap().len(), 6);
+    let trace = read(2, None).unwrap();
+    assert_eq!(trace.len(), 6);
+    assert_eq!(trace.get("uas_x").unwrap()[1], 2.0);
+    // All fields of the decoded messages
+    let trace = read_tlog_trace(
+        file_name,
+        CsvColumns::AllNumeric,
+        0,
+        Interpolation::ZeroOrderHold,
+        None,
+    )
+    .unwrap();
+    assert_eq!(trace.signals().len(), 23);
+    assert_eq!(trace.get("ATTITUDE.pitch").unwrap()[0], 0.25);
+    assert_eq!(
+        trace.get("GLOBAL_POSITION_INT.relative_alt").unwrap()[0],
+        5.0
+    );
+    for (field, error) in [
+        ("ATTITUDE.heading", "message ATTITUDE has no field heading"),
+        ("HEARTBEAT.type", "message HEARTBEAT is not supported"),
+        ("ATTITUDE[4].roll", "no ATTITUDE messages found"),
+    ] {
+        assert!(read_tlog_trace(
+            file_name,
+            CsvColumns::Selected(&[(field, "a")]),
+            0,
+            Interpolation::ZeroOrderHold,
+            None
+        )
+        .unwrap_err()
+        .contains(error));
+    }
+    // A mapping selects the fields of the trace
+    let mapping = std::env::temp_dir().join(format!("tbt_tlog_mapping_{}.txt", std::process::id()));
+    std::fs::write(
+        &mapping,
+        "# signal=field\nnorth = LOCAL_POSITION_NED.x\n\nroll=ATTITUDE.roll\n",
+    )
+    .unwrap();
+    let columns = read_mapping(mapping.to_str().unwrap()).unwrap();
+    assert_eq!(
+        columns,
+        ["north=LOCAL_POSITION_NED.x", "roll=ATTITUDE.roll"]
+    );
+    let setting = TraceSetting {
+        columns,
+        ..TraceSetting::default()
+    };
+    let trace = read_trace::<ShipdeckLanding>(file_name, 0, &setting).unwrap();
+    assert_eq!(trace.signals().len(), 2);
+    assert_eq!(trace.get("north").unwrap()[4], 4.0);
+    std::fs::write(&mapping, "north=\n").unwrap();
+    assert!(read_mapping(mapping.to_str().unwrap())
+        .unwrap_err()
+        .contains("line 1"));
+    std::fs::remove_file(mapping).unwrap();
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_align() {
+    let fast = Trace::new(HashMap::from([(
+        "a".to_string(),
+        vec![0.0, 1.0, 2.0, 3.0, 4.0],
+    )]))
+    .unwrap()
+    .with_time(vec![0.0, 0.5, 1.0, 1.5, 2.0])
+    .unwrap();
+    let slow = Trace::new(HashMap::from([("b".to_string(), vec![10.0, 20.0, 30.0])]))
+        .unwrap()
+        .with_time(vec![0.25, 1.25, 2.25])
+        .unwrap();
+    assert_eq!(fast.sampling_period(), Some(0.5));
+    // The time axis of the fastest trace is used within the overlap
+    let trace = align(
+        vec![fast.clone(), slow.clone()],
+        Interpolation::ZeroOrderHold,
+        None,
+    )
+    .unwrap();
+    assert_eq!(trace.time(), Some(&[0.5, 1.0, 1.5, 2.0][..]));
+    assert_eq!(trace.get("a"), Some(&vec![1.0, 2.0, 3.0, 4.0]));
+    assert_eq!(trace.get("b"), Some(&vec![10.0, 10.0, 20.0, 20.0]));
+    let trace = align(
+        vec![fast.clone(), slow.clone()],
+        Interpolation::Linear,
+        Some(1.0),
+    )
+    .unwrap();
+    assert_eq!(trace.time(), Some(&[0.25, 1.25][..]));
+    assert_eq!(trace.get("a"), Some(&vec![0.5, 2.5]));
+    assert_eq!(trace.get("b"), Some(&vec![10.0, 20.0]));
+    // Signals must be unique and traces must have a time axis
+    assert!(align(vec![fast.clone(), fast], Interpolation::Linear, None).is_err());
+    let untimed = Trace::new(HashMap::from([("c".to_string(), vec![1.0])])).unwrap();
+    assert!(align(vec![slow, untimed], Interpolation::Linear, None).is_err());
+
+    // Logfiles are aligned at their full rate before subsampling, i.e., the rate is not undone
+    let folder = std::env::temp_dir().join(format!("tbt_align_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let rows: String = (0..20)
+        .map(|i| format!("{i},{i},0,0,0,0,0,{}\n", i as f32 * 0.1))
+        .collect();
+    for file in ["SIMOUT_Ship.csv", "SIMOUT_UAS.csv"] {
+        std::fs::write(
+            folder.join(file),
+            format!("xg,yg,zg,ug,vg,wg,psi,time\n{rows}"),
+        )
+        .unwrap();
+    }
+    let logfile = format!("{}/", folder.display());
+    let setting = TraceSetting {
+        rate: Some(10.0),
+        ..TraceSetting::default()
+    };
+    let full = ShipdeckLanding::get_trace(&logfile, 0, &setting).unwrap();
+    let subsampled = ShipdeckLanding::get_trace(&logfile, 2, &setting).unwrap();
+    std::fs::remove_dir_all(&folder).unwrap();
+    assert_eq!(subsampled.len(), full.len().div_ceil(2));
+    assert_eq!(
+        subsampled.get("uas_x"),
+        full.clone().subsample(2).get("uas_x")
+    );
+}
+
+#[test]
+fn test_time_bounds() {
+    let atomics = AtomicRegistry::new();
+    let spec = "Sequence([
+        Leaf(hold, GloballyInterval(0, 1s, Atomic(a))),
+        Timeout(1.5s, Leaf(rest, Globally(Atomic(a))))
+    ])";
+    let tbt = parse_tbt(spec, &atomics).unwrap();
+    assert!(tbt.tree.pretty_print(true, 0).contains("t=1.5s"));
+    // Serialized bounds keep their unit
+    let json = tbt_to_json(&tbt).unwrap();
+    assert!(json.contains("\"seconds\": 1.0"));
+    let restored = tbt_from_json(&json, &atomics).unwrap();
+    assert_eq!(
+        TbtDefinition::from_tbt(&restored),
+        TbtDefinition::from_tbt(&tbt)
+    );
+    // Bounds in seconds require a time axis
+    let values = vec![1.0, 2.0, 3.0, -1.0, 4.0, 5.0];
+    let trace = Trace::new(HashMap::from([("a".to_string(), values)])).unwrap();
+    assert!(tbt.clone().resolve_bounds(trace.sampling_period()).is_err());
+    assert!(evaluate(
+        tbt.clone(),
+        trace.clone(),
+        SystemTime::now(),
+        false,
+        false,
+        Semantics::Classic,
+        TableBackend::Dense,
+        1,
+        0.0,
+        false,
+        None,
+        false,
+    )
+    .is_err());
+    // At 2 Hz, one second is resolved to two samples and 1.5 seconds to three samples
+    let trace = trace.with_time(vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5]).unwrap();
+    let mut resolved = tbt.clone();
+    resolved.resolve_bounds(trace.sampling_period()).unwrap();
+    let printed = resolved.tree.pretty_print(true, 0);
+    assert!(printed.contains("G[0,2](a)"));
+    assert!(printed.contains("t=3"));
+    let result = evaluate(
+        tbt,
+        trace,
+        SystemTime::now(),
+        false,
+        false,
+        Semantics::Classic,
+        TableBackend::Dense,
+        1,
+        0.0,
+        false,
+        None,
+        false,
+    )
+    .unwrap();
+    assert_eq!(result.robustness, 1.0);
+}
@@ -0,0 +1,345 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    csv_reader::CsvColumns,
+    trace::{align, Interpolation},
+    Trace,
+};
+use std::{collections::HashMap, path::Path};
+
+/// First byte of a MAVLink 1 packet
+const MAVLINK_1: u8 = 0xFE;
+
+/// First byte of a MAVLink 2 packet
+const MAVLINK_2: u8 = 0xFD;
+
+/// Incompatible flag of MAVLink 2 packets that are followed by a signature of 13 bytes
+const MAVLINK_2_SIGNED: u8 = 0x01;
+
+/// Returns true if the logfile is a MAVLink telemetry log, i.e., it ends with .tlog
+pub fn is_tlog(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("tlog"))
+}
+
+/*******************************
+ * Message Definitions
+ *******************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Types of fields in the payload of a message, which are little endian
+enum FieldType {
+    UInt16,
+    Int16,
+    UInt32,
+    Int32,
+    Float,
+}
+
+/// A field of a message, its value in the payload is multiplied by the scale, e.g., to convert millimeters to meters
+struct FieldDefinition {
+    name: &'static str,
+    field_type: FieldType,
+    offset: usize,
+    scale: f32,
+}
+
+/// A MAVLink message that is decoded, fields are ordered as in the payload, i.e., by size
+struct MessageDefinition {
+    id: u32,
+    name: &'static str,
+    crc_extra: u8,
+    length: usize,
+    fields: &'static [FieldDefinition],
+}
+
+const fn field(
+    name: &'static str,
+    field_type: FieldType,
+    offset: usize,
+    scale: f32,
+) -> FieldDefinition {
+    FieldDefinition {
+        name,
+        field_type,
+        offset,
+        scale,
+    }
+}
+
+/// Decoded messages, angles are in radians (ATTITUDE) or degrees (GLOBAL_POSITION_INT), distances in meters,
+/// velocities in meters per second, and time_boot_ms in seconds
+const MESSAGES: [MessageDefinition; 3] = [
+    MessageDefinition {
+        id: 30,
+        name: "ATTITUDE",
+        crc_extra: 39,
+        length: 28,
+        fields: &[
+            field("time_boot_ms", FieldType::UInt32, 0, 1e-3),
+            field("roll", FieldType::Float, 4, 1.0),
+            field("pitch", FieldType::Float, 8, 1.0),
+            field("yaw", FieldType::Float, 12, 1.0),
+            field("rollspeed", FieldType::Float, 16, 1.0),
+            field("pitchspeed", FieldType::Float, 20, 1.0),
+            field("yawspeed", FieldType::Float, 24, 1.0),
+        ],
+    },
+    MessageDefinition {
+        id: 32,
+        name: "LOCAL_POSITION_NED",
+        crc_extra: 185,
+        length: 28,
+        fields: &[
+            field("time_boot_ms", FieldType::UInt32, 0, 1e-3),
+            field("x", FieldType::Float, 4, 1.0),
+            field("y", FieldType::Float, 8, 1.0),
+            field("z", FieldType::Float, 12, 1.0),
+            field("vx", FieldType::Float, 16, 1.0),
+            field("vy", FieldType::Float, 20, 1.0),
+            field("vz", FieldType::Float, 24, 1.0),
+        ],
+    },
+    MessageDefinition {
+        id: 33,
+        name: "GLOBAL_POSITION_INT",
+        crc_extra: 104,
+        length: 28,
+        fields: &[
+            field("time_boot_ms", FieldType::UInt32, 0, 1e-3),
+            field("lat", FieldType::Int32, 4, 1e-7),
+            field("lon", FieldType::Int32, 8, 1e-7),
+            field("alt", FieldType::Int32, 12, 1e-3),
+            field("relative_alt", FieldType::Int32, 16, 1e-3),
+            field("vx", FieldType::Int16, 20, 1e-2),
+            field("vy", FieldType::Int16, 22, 1e-2),
+            field("vz", FieldType::Int16, 24, 1e-2),
+            field("hdg", FieldType::UInt16, 26, 1e-2),
+        ],
+    },
+];
+
+impl FieldDefinition {
+    /// Reads the scaled value from a payload, an unknown heading (UINT16_MAX) is NaN
+    fn read(&self, payload: &[u8]) -> f32 {
+        let bytes = &payload[self.offset..];
+        let value = match self.field_type {
+            FieldType::UInt16 => match u16::from_le_bytes([bytes[0], bytes[1]]) {
+                u16::MAX => f32::NAN,
+                value => value as f32,
+            },
+            FieldType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+            FieldType::UInt32 => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f32,
+            FieldType::Int32 => i32::from_le_bytes(bytes[..4].try_into().unwrap()) as f32,
+            FieldType::Float => f32::from_le_bytes(bytes[..4].try_into().unwrap()),
+        };
+        value * self.scale
+    }
+}
+
+/// Returns the CRC-16/MCRF4XX of the bytes that is used by MAVLink, the CRC_EXTRA of the message is the last byte
+pub fn mavlink_crc(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |crc: u16, byte| {
+        let tmp = byte ^ (crc & 0xFF) as u8;
+        let tmp = tmp ^ (tmp << 4);
+        (crc >> 8) ^ ((tmp as u16) << 8) ^ ((tmp as u16) << 3) ^ ((tmp as u16) >> 4)
+    })
+}
+
+/*******************************
+ * Reader
+ *******************************/
+/// A MAVLink packet of the telemetry log
+struct Packet<'a> {
+    system_id: u8,
+    message_id: u32,
+    payload: &'a [u8],
+    /// Number of bytes of the packet
+    size: usize,
+}
+
+/// Parses the MAVLink 1 or 2 packet at the beginning of the bytes. Returns None if the packet is incomplete or its
+/// checksum is wrong, unknown messages are not checked.
+fn parse_packet(bytes: &[u8]) -> Option<Packet<'_>> {
+    let (header_size, system_id, message_id, signature_size) = match *bytes.first()? {
+        MAVLINK_1 => (6, *bytes.get(3)?, *bytes.get(5)? as u32, 0),
+        MAVLINK_2 => {
+            let id = bytes.get(7..10)?;
+            let signature_size = match bytes.get(2)? & MAVLINK_2_SIGNED {
+                0 => 0,
+                _ => 13,
+            };
+            (
+                10,
+                *bytes.get(5)?,
+                u32::from_le_bytes([id[0], id[1], id[2], 0]),
+                signature_size,
+            )
+        }
+        _ => return None,
+    };
+    let length = *bytes.get(1)? as usize;
+    let size = header_size + length + 2 + signature_size;
+    let checked = bytes.get(1..header_size + length)?;
+    let crc = bytes.get(header_size + length..header_size + length + 2)?;
+    bytes.get(..size)?;
+    if let Some(message) = MESSAGES.iter().find(|message| message.id == message_id) {
+        let mut checked = checked.to_vec();
+        checked.push(message.crc_extra);
+        if mavlink_crc(&checked).to_le_bytes() != crc {
+            return None;
+        }
+    }
+    Some(Packet {
+        system_id,
+        message_id,
+        payload: &bytes[header_size..header_size + length],
+        size,
+    })
+}
+
+/// A selected field and the messages it is read from
+struct Selection {
+    message: &'static MessageDefinition,
+    /// System that sends the messages, if None the first system that sends the message is used
+    system_id: Option<u8>,
+    field: &'static FieldDefinition,
+    signal: String,
+    time: Vec<f32>,
+    values: Vec<f32>,
+}
+
+/// Reads fields of ATTITUDE, LOCAL_POSITION_NED, and GLOBAL_POSITION_INT messages of a MAVLink telemetry log
+/// into a trace. Each record of the log is the time it was received (microseconds since the epoch in big endian)
+/// followed by a MAVLink 1 or 2 packet. Since messages are received at different times, all fields are aligned
+/// onto a common time base (in seconds since the first record). Corrupted records are skipped.
+/// # Arguments
+/// * `file_name` - Location of the telemetry log
+/// * `fields` - Fields that are read and their signal names, e.g., ``LOCAL_POSITION_NED.x``, where the system that
+///   sends the message is given by ``MESSAGE[1].field``; AllNumeric reads all fields of the decoded messages
+/// * `number_skipped_entries` - used for subsampling ie only every n-th sample of the common time base is kept
+/// * `interpolation` - Defines the values of fields between two messages
+/// * `rate` - Rate (in Hz) of the common time base, if None the times of the field with most messages are used
+pub fn read_tlog_trace(
+    file_name: &str,
+    fields: CsvColumns,
+    number_skipped_entries: usize,
+    interpolation: Interpolation,
+    rate: Option<f32>,
+) -> Result<Trace, String> {
+    let error = |message: String| format!("Could not read {file_name}: {message}");
+    let bytes = std::fs::read(file_name).map_err(|e| error(e.to_string()))?;
+    let selected: Vec<(String, String)> = match fields {
+        CsvColumns::Selected(fields) => fields
+            .iter()
+            .map(|(field, signal)| (field.to_string(), signal.to_string()))
+            .collect(),
+        CsvColumns::AllNumeric => MESSAGES
+            .iter()
+            .flat_map(|message| {
+                message.fields.iter().map(|field| {
+                    let name = format!("{}.{}", message.name, field.name);
+                    (name.clone(), name)
+                })
+            })
+            .collect(),
+    };
+    let mut selections = selected
+        .into_iter()
+        .map(|(name, signal)| {
+            let (message, field) = name
+                .split_once('.')
+                .ok_or(error(format!("field '{name}' has no message")))?;
+            let (message, system_id) =
+                match message.split_once('[') {
+                    Some((message, system_id)) => (
+                        message,
+                        Some(system_id.trim_end_matches(']').parse().map_err(|_| {
+                            error(format!("message '{message}' has invalid system"))
+                        })?),
+                    ),
+                    None => (message, None),
+                };
+            let message = MESSAGES
+                .iter()
+                .find(|definition| definition.name == message)
+                .ok_or(error(format!(
+                    "message {message} is not supported, expected one of {}",
+                    MESSAGES.map(|message| message.name).join(", ")
+                )))?;
+            let field = message
+                .fields
+                .iter()
+                .find(|definition| definition.name == field)
+                .ok_or(error(format!(
+                    "message {} has no field {field}",
+                    message.name
+                )))?;
+            Ok(Selection {
+                message,
+                system_id,
+                field,
+                signal,
+                time: vec![],
+                values: vec![],
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // Records are scanned byte by byte after a corrupted record until the next valid packet
+    let mut first_timestamp = None;
+    let mut position = 0;
+    let mut payload = vec![];
+    while position + 8 < bytes.len() {
+        let Some(packet) = parse_packet(&bytes[position + 8..]) else {
+            position += 1;
+            continue;
+        };
+        let timestamp = u64::from_be_bytes(bytes[position..position + 8].try_into().unwrap());
+        position += 8 + packet.size;
+        let first = *first_timestamp.get_or_insert(timestamp);
+        let time = (timestamp.saturating_sub(first) as f64 / 1e6) as f32;
+        for selection in selections
+            .iter_mut()
+            .filter(|selection| selection.message.id == packet.message_id)
+        {
+            if *selection.system_id.get_or_insert(packet.system_id) != packet.system_id {
+                continue;
+            }
+            // MAVLink 2 removes trailing zeros of the payload
+            payload.clear();
+            payload.extend_from_slice(packet.payload);
+            payload.resize(payload.len().max(selection.message.length), 0);
+            selection.time.push(time);
+            selection.values.push(selection.field.read(&payload));
+        }
+    }
+
+    // Fields without messages are only an error if they were selected
+    if let CsvColumns::AllNumeric = fields {
+        selections.retain(|selection| !selection.time.is_empty());
+    }
+    if let Some(selection) = selections
+        .iter()
+        .find(|selection| selection.time.is_empty())
+    {
+        return Err(error(format!(
+            "no {} messages found for {}",
+            selection.message.name, selection.signal
+        )));
+    }
+    if selections.is_empty() {
+        return Err(error("no supported messages found".to_string()));
+    }
+    let traces = selections
+        .into_iter()
+        .map(|selection| {
+            Trace::new(HashMap::from([(selection.signal, selection.values)]))
+                .and_then(|trace| trace.with_time(selection.time))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(error)?;
+    let trace = align(traces, interpolation, rate).map_err(error)?;
+    Ok(trace.subsample(number_skipped_entries))
+}
@@ -0,0 +1,327 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::csv_reader::BadValuePolicy;
+use std::{collections::HashMap, str::FromStr};
+
+/*******************************
+ * Trace
+ *******************************/
+/// Signal that is used as time axis (in seconds) if a logfile without fixed columns provides it, e.g., stdin
+pub const TIME_SIGNAL: &str = "time";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A trace maps variable names to streams of values that have the same length
+/// # Arguments
+/// * `length` - Number of samples of each stream
+/// * `time` - Time axis in seconds, i.e., the timestamp of each sample, if known
+/// * `signals` - Maps a variable name to its stream of values
+pub struct Trace {
+    length: usize,
+    time: Option<Vec<f32>>,
+    signals: HashMap<String, Vec<f32>>,
+}
+
+impl Trace {
+    /// Creates a trace without time axis, all streams must have the same length
+    pub fn new(signals: HashMap<String, Vec<f32>>) -> Result<Self, String> {
+        let length = signals.values().next().map_or(0, |values| values.len());
+        if let Some((name, values)) = signals.iter().find(|(_, v)| v.len() != length) {
+            return Err(format!(
+                "signal {name} has {} samples but expected {length} samples",
+                values.len()
+            ));
+        }
+        Ok(Trace {
+            length,
+            time: None,
+            signals,
+        })
+    }
+
+    /// Adds a time axis (in seconds) that must be non-decreasing and have one timestamp per sample
+    pub fn with_time(mut self, time: Vec<f32>) -> Result<Self, String> {
+        if time.len() != self.length {
+            return Err(format!(
+                "time axis has {} samples but trace has {} samples",
+                time.len(),
+                self.length
+            ));
+        }
+        if let Some(index) = time.windows(2).position(|t| t[1] < t[0]) {
+            return Err(format!("time axis decreases at sample {}", index + 1));
+        }
+        self.time = Some(time);
+        Ok(self)
+    }
+
+    /// Removes the signal from the trace and uses it as time axis
+    pub fn with_time_signal(mut self, name: &str) -> Result<Self, String> {
+        let time = self
+            .signals
+            .remove(name)
+            .ok_or(format!("trace has no signal {name}"))?;
+        self.with_time(time)
+    }
+
+    /// Appends a sample that contains a value for each signal, other values are ignored.
+    /// Samples cannot be appended to a trace with time axis.
+    pub fn push(&mut self, sample: &HashMap<String, f32>) -> Result<(), String> {
+        if self.time.is_some() {
+            return Err("samples cannot be appended to a trace with time axis".to_string());
+        }
+        if let Some(name) = self.signals.keys().find(|name| !sample.contains_key(*name)) {
+            return Err(format!(
+                "sample {} has no value for signal {name}",
+                self.length
+            ));
+        }
+        for (name, values) in self.signals.iter_mut() {
+            values.push(sample[name]);
+        }
+        self.length += 1;
+        Ok(())
+    }
+
+    /// Removes all samples after the first length samples
+    pub fn truncate(&mut self, length: usize) {
+        for values in self.signals.values_mut() {
+            values.truncate(length);
+        }
+        if let Some(time) = &mut self.time {
+            time.truncate(length);
+        }
+        self.length = self.length.min(length);
+    }
+
+    /// Keeps every n-th sample, e.g., of a trace that has been aligned to a common time base
+    /// # Arguments
+    /// * `number_skipped_entries` - used for subsampling ie only every n-th sample is kept
+    pub fn subsample(self, number_skipped_entries: usize) -> Trace {
+        if number_skipped_entries <= 1 {
+            return self;
+        }
+        let every = |values: Vec<f32>| -> Vec<f32> {
+            values.into_iter().step_by(number_skipped_entries).collect()
+        };
+        let signals: HashMap<String, Vec<f32>> = self
+            .signals
+            .into_iter()
+            .map(|(name, values)| (name, every(values)))
+            .collect();
+        let length = self.length.div_ceil(number_skipped_entries);
+        Trace {
+            length,
+            time: self.time.map(every),
+            signals,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Vec<f32>> {
+        self.signals.get(name)
+    }
+
+    /// Values of a signal can be changed but not their number
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut [f32]> {
+        self.signals
+            .get_mut(name)
+            .map(|values| values.as_mut_slice())
+    }
+
+    pub fn signals(&self) -> &HashMap<String, Vec<f32>> {
+        &self.signals
+    }
+
+    pub fn time(&self) -> Option<&[f32]> {
+        self.time.as_deref()
+    }
+
+    /// Returns the timestamp (in seconds) of a sample if the trace has a time axis
+    pub fn timestamp(&self, index: usize) -> Option<f32> {
+        self.time.as_ref().and_then(|time| time.get(index).copied())
+    }
+
+    /// Returns the mean time between two samples if the trace has a time axis and at least two samples
+    pub fn sampling_period(&self) -> Option<f32> {
+        match self.time.as_deref() {
+            Some([first, .., last]) if last > first => {
+                Some((last - first) / (self.length - 1) as f32)
+            }
+            _ => None,
+        }
+    }
+}
+
+/*******************************
+ * Alignment
+ *******************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Defines the value of a signal between two of its samples
+pub enum Interpolation {
+    /// The value of the previous sample is hold
+    ZeroOrderHold,
+    /// The values of the previous and next sample are interpolated linearly
+    Linear,
+}
+
+impl FromStr for Interpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zoh" => Ok(Interpolation::ZeroOrderHold),
+            "linear" => Ok(Interpolation::Linear),
+            _ => Err(format!(
+                "unknown interpolation '{s}', expected zoh or linear"
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Defines how a trace is read from a logfile
+/// # Arguments
+/// * `bad_value_policy` - Defines how values that cannot be parsed are handled
+/// * `interpolation` - Used to align several traces onto a common time base
+/// * `rate` - Rate (in Hz) of the common time base, if None the time axis of the fastest trace is used
+/// * `columns` - Columns of a Parquet or Arrow logfile or fields of a ULog file or MAVLink telemetry log that are read, signal=column renames a column, all numeric columns if empty
+/// * `time_column` - Column of a Parquet or Arrow logfile that is used as time axis, if None a numeric column time is used
+pub struct TraceSetting {
+    pub bad_value_policy: BadValuePolicy,
+    pub interpolation: Interpolation,
+    pub rate: Option<f32>,
+    pub columns: Vec<String>,
+    pub time_column: Option<String>,
+}
+
+impl Default for TraceSetting {
+    fn default() -> Self {
+        TraceSetting {
+            bad_value_policy: BadValuePolicy::Fail,
+            interpolation: Interpolation::ZeroOrderHold,
+            rate: None,
+            columns: vec![],
+            time_column: None,
+        }
+    }
+}
+
+/// Reads a mapping of fields to signal names, e.g., for the columns of the TraceSetting. Each line is SIGNAL=NAME or
+/// NAME, whitespace around them as well as empty lines and lines starting with # are ignored.
+/// # Arguments
+/// * `file_name` - Location of the mapping
+pub fn read_mapping(file_name: &str) -> Result<Vec<String>, String> {
+    let input = std::fs::read_to_string(file_name)
+        .map_err(|e| format!("Could not read mapping {file_name}: {e}"))?;
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| match line.split_once('=') {
+            Some((signal, name)) if !signal.trim().is_empty() && !name.trim().is_empty() => {
+                Ok(format!("{}={}", signal.trim(), name.trim()))
+            }
+            Some(_) => Err(format!(
+                "Malformed mapping {file_name} at line {line_number}: expected SIGNAL=NAME"
+            )),
+            None => Ok(line.to_string()),
+        })
+        .collect()
+}
+
+/// Merges traces, e.g., of several logfiles with different rates and offsets, into a single trace.
+/// The common time base covers the time where all traces have samples.
+/// # Arguments
+/// * `traces` - Traces with time axis, a signal name must not be used by several traces
+/// * `interpolation` - Defines the values of signals between their samples
+/// * `rate` - Rate (in Hz) of the common time base, if None the time axis of the trace with most samples is used
+pub fn align(
+    traces: Vec<Trace>,
+    interpolation: Interpolation,
+    rate: Option<f32>,
+) -> Result<Trace, String> {
+    let mut times = Vec::new();
+    for trace in &traces {
+        match trace.time() {
+            Some(time) if !time.is_empty() => times.push(time),
+            Some(_) => return Err("traces without samples cannot be aligned".to_string()),
+            None => return Err("traces without time axis cannot be aligned".to_string()),
+        }
+    }
+    let start = times.iter().map(|time| time[0]).fold(f32::MIN, f32::max);
+    let end = times
+        .iter()
+        .map(|time| time[time.len() - 1])
+        .fold(f32::MAX, f32::min);
+    if start > end {
+        return Err(format!(
+            "time axes do not overlap, i.e., the latest start {start} s is after the earliest end {end} s"
+        ));
+    }
+    let time_base: Vec<f32> = match rate {
+        Some(rate) if rate > 0.0 => {
+            let number_samples = ((end - start) as f64 * rate as f64 + 1e-6).floor() as usize + 1;
+            (0..number_samples)
+                .map(|i| (start as f64 + i as f64 / rate as f64) as f32)
+                .collect()
+        }
+        Some(rate) => return Err(format!("rate must be positive but was {rate}")),
+        None => times
+            .iter()
+            .map(|time| {
+                time.iter()
+                    .copied()
+                    .filter(|t| (start..=end).contains(t))
+                    .collect::<Vec<f32>>()
+            })
+            .max_by_key(|time| time.len())
+            .unwrap_or_default(),
+    };
+
+    let mut signals = HashMap::new();
+    for trace in traces {
+        let time = trace.time.unwrap_or_default();
+        for (name, values) in trace.signals {
+            let values = resample(&time, &values, &time_base, interpolation);
+            if signals.insert(name.clone(), values).is_some() {
+                return Err(format!("signal {name} is provided by several traces"));
+            }
+        }
+    }
+    Trace::new(signals)?.with_time(time_base)
+}
+
+/// Returns the values of a signal at the given timestamps, which must be within the time axis of the signal
+fn resample(
+    time: &[f32],
+    values: &[f32],
+    time_base: &[f32],
+    interpolation: Interpolation,
+) -> Vec<f32> {
+    let mut resampled = Vec::with_capacity(time_base.len());
+    let mut previous = 0;
+    for t in time_base {
+        while previous + 1 < time.len() && time[previous + 1] <= *t {
+            previous += 1;
+        }
+        let value = match interpolation {
+            Interpolation::Linear if previous + 1 < time.len() && time[previous] < *t => {
+                let next = previous + 1;
+                let ratio = (t - time[previous]) / (time[next] - time[previous]);
+                values[previous] + ratio * (values[next] - values[previous])
+            }
+            _ => values[previous],
+        };
+        resampled.push(value);
+    }
+    resampled
+}
@@ -4,13 +4,14 @@
 use super::atomics::combined::combined_movetp_ha;
 use super::atomics::in_position::in_position;
 use super::atomics::{combined::combined_inpos_ha_va, constants::Deg45};
-use crate::{behaviortree::TbtNode, stl::Stl};
-use std::rc::Rc;
+use crate::behaviortree::{TbtBuilder, TbtNode};
+use crate::stl::Bound;
+use std::sync::Arc;
 
 #[allow(dead_code)]
 #[allow(clippy::too_many_arguments)]
 pub fn get_45deg_maneuver(
-    events_per_second: &u64,
+    builder: &TbtBuilder,
     uas_x: &str,
     uas_y: &str,
     uas_z: &str,
@@ -26,8 +27,8 @@ pub fn get_45deg_maneuver(
     ship_w: &str,
     ship_heading: &str,
 ) -> TbtNode {
-    let move_to_position = TbtNode::leaf(
-        Stl::eventually(Stl::atomic(
+    let move_to_position = builder.leaf(
+        builder.eventually(builder.atomic(
             vec![
                 uas_x.to_owned(),
                 uas_y.to_owned(),
@@ -37,7 +38,7 @@ pub fn get_45deg_maneuver(
                 ship_z.to_owned(),
                 ship_heading.to_owned(),
             ],
-            Rc::new(|arguments| {
+            Arc::new(|arguments| {
                 in_position(
                     Deg45::HeightAboveShip.value(),
                     Deg45::DistanceToShip.value(),
@@ -49,11 +50,11 @@ pub fn get_45deg_maneuver(
         String::from("move_to_position_45deg"),
     );
 
-    let stay_in_position = TbtNode::leaf(
-        Stl::globally_interval(
-            0,
-            (events_per_second * 5).try_into().unwrap(), // for five seconds
-            Stl::atomic(
+    let stay_in_position = builder.leaf(
+        builder.globally_interval_bounds(
+            Bound::Samples(0),
+            Bound::Seconds(5.0), // for five seconds
+            builder.atomic(
                 vec![
                     uas_x.to_owned(),
                     uas_y.to_owned(),
@@ -70,7 +71,7 @@ pub fn get_45deg_maneuver(
                     ship_w.to_owned(),
                     ship_heading.to_owned(),
                 ],
-                Rc::new(|arguments| {
+                Arc::new(|arguments| {
                     combined_inpos_ha_va(
                         Deg45::HeightAboveShip.value(),
                         Deg45::DistanceToShip.value(),
@@ -83,8 +84,8 @@ pub fn get_45deg_maneuver(
         String::from("stay_in_position"),
     );
 
-    let move_to_touchdown = TbtNode::leaf(
-        Stl::eventually(Stl::atomic(
+    let move_to_touchdown = builder.leaf(
+        builder.eventually(builder.atomic(
             vec![
                 uas_x.to_owned(),
                 uas_y.to_owned(),
@@ -101,13 +102,13 @@ pub fn get_45deg_maneuver(
                 ship_w.to_owned(),
                 ship_heading.to_owned(),
             ],
-            Rc::new(|arguments| combined_movetp_ha(Deg45::AboveTouchdown.value(), arguments)),
+            Arc::new(|arguments| combined_movetp_ha(Deg45::AboveTouchdown.value(), arguments)),
         )),
         String::from("move_to_touchdown"),
     );
 
-    TbtNode::sequence(
+    builder.sequence(
         move_to_position,
-        TbtNode::sequence(stay_in_position, move_to_touchdown),
+        builder.sequence(stay_in_position, move_to_touchdown),
     )
 }
@@ -2,74 +2,92 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    behaviortree::{tbt_node_reset_count, Tbt, TbtNode},
-    csv_reader::read_csv_file,
-    stl::{stl_reset_count, Stl},
+    behaviortree::{Tbt, TbtBuilder},
+    csv_reader::{read_csv_trace, CsvColumns},
+    trace::align,
     tree::shipdeck_landing::{
-        atomics::descend_touchdown::descend_touchdown, deg45_maneuver::get_45deg_maneuver,
-        lateral_maneuver::get_lateral_maneuver, oblique_maneuver::get_oblique_maneuver,
+        atomics::{
+            combined::{
+                combined_inpos_ha_va, combined_inpos_ho_va, combined_moveto_ho, combined_movetp_ha,
+            },
+            constants::{Deg45, Lateral, Oblique, Straight},
+            descend_touchdown::descend_touchdown,
+            in_position::in_position,
+        },
+        deg45_maneuver::get_45deg_maneuver,
+        lateral_maneuver::get_lateral_maneuver,
+        oblique_maneuver::get_oblique_maneuver,
         straight_maneuver::get_straight_maneuver,
     },
-    ProvidesTraceAndTree, Trace, UserProvidedFunction,
+    AtomicRegistry, ProvidesTraceAndTree, Trace, TraceSetting,
 };
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, sync::Arc};
 
-impl ProvidesTraceAndTree for UserProvidedFunction {
-    fn get_trace(logfile: &str, number_skipped_entries: usize) -> Trace {
+/// Landing of an unmanned aircraft on a ship deck using one of four maneuvers
+pub struct ShipdeckLanding;
+
+impl ProvidesTraceAndTree for ShipdeckLanding {
+    fn get_trace(
+        logfile: &str,
+        number_skipped_entries: usize,
+        setting: &TraceSetting,
+    ) -> Result<Trace, String> {
+        // Both files provide the same columns
+        let columns = [
+            ("xg", "x"),
+            ("yg", "y"),
+            ("zg", "z"),
+            ("ug", "u"),
+            ("vg", "v"),
+            ("wg", "w"),
+            ("psi", "heading"),
+            ("time", "time"),
+        ];
+        let read = |file: &str, prefix: &str| {
+            read_csv_trace(
+                file,
+                CsvColumns::Selected(&columns),
+                prefix,
+                0,
+                setting.bad_value_policy,
+            )
+            .map_err(|e| e.to_string())?
+            // Time in seconds, used to align both files and to report segments in seconds
+            .with_time_signal(&format!("{prefix}time"))
+            .map_err(|e| format!("{file}: {e}"))
+        };
         // Ship
-        let ship_file = format!("{logfile}SIMOUT_Ship.csv");
-        let mut trace_ship_xg = read_csv_file(&ship_file, "xg", number_skipped_entries);
-        let mut trace_ship_yg = read_csv_file(&ship_file, "yg", number_skipped_entries);
-        let mut trace_ship_zg = read_csv_file(&ship_file, "zg", number_skipped_entries);
-        let trace_ship_ug = read_csv_file(&ship_file, "ug", number_skipped_entries);
-        let trace_ship_vg = read_csv_file(&ship_file, "vg", number_skipped_entries);
-        let trace_ship_wg = read_csv_file(&ship_file, "wg", number_skipped_entries);
-        let trace_ship_psi = read_csv_file(&ship_file, "psi", number_skipped_entries);
-        for i in 0..trace_ship_xg.len() {
-            // Compute position of touchdown point
-            trace_ship_zg[i] = trace_ship_zg[i] * -1.0 + 5.0;
-            let computed_angle_in_radian = f32::to_radians(180.0) + trace_ship_psi[i];
-            trace_ship_xg[i] += 60.0 * f32::cos(computed_angle_in_radian);
-            trace_ship_yg[i] += 60.0 * f32::sin(computed_angle_in_radian);
+        let mut ship = read(&format!("{logfile}SIMOUT_Ship.csv"), "ship_")?;
+        let trace_ship_heading = ship.get("ship_heading").unwrap().clone();
+        for zg in ship.get_mut("ship_z").unwrap() {
+            *zg = *zg * -1.0 + 5.0;
+        }
+        // Compute position of touchdown point
+        let computed_angles_in_radian = trace_ship_heading
+            .iter()
+            .map(|psi| f32::to_radians(180.0) + psi)
+            .collect::<Vec<f32>>();
+        let trace_ship_xg = ship.get_mut("ship_x").unwrap();
+        for (xg, angle) in trace_ship_xg.iter_mut().zip(&computed_angles_in_radian) {
+            *xg += 60.0 * f32::cos(*angle);
+        }
+        let trace_ship_yg = ship.get_mut("ship_y").unwrap();
+        for (yg, angle) in trace_ship_yg.iter_mut().zip(&computed_angles_in_radian) {
+            *yg += 60.0 * f32::sin(*angle);
         }
         // UAS
-        let uas_file = format!("{logfile}SIMOUT_UAS.csv");
-        let trace_uas_xg = read_csv_file(&uas_file, "xg", number_skipped_entries);
-        let trace_uas_yg = read_csv_file(&uas_file, "yg", number_skipped_entries);
-        let mut trace_uas_zg = read_csv_file(&uas_file, "zg", number_skipped_entries);
-        let trace_uas_ug = read_csv_file(&uas_file, "ug", number_skipped_entries);
-        let trace_uas_vg = read_csv_file(&uas_file, "vg", number_skipped_entries);
-        let trace_uas_wg = read_csv_file(&uas_file, "wg", number_skipped_entries);
-        let trace_uas_psi = read_csv_file(&uas_file, "psi", number_skipped_entries);
-        for zg in &mut trace_uas_zg {
+        let mut uas = read(&format!("{logfile}SIMOUT_UAS.csv"), "uas_")?;
+        for zg in uas.get_mut("uas_z").unwrap() {
             *zg = -*zg;
         }
-        assert_eq!(trace_ship_xg.len(), trace_uas_xg.len());
-        let trace: Trace = (
-            trace_ship_xg.len(),
-            HashMap::from([
-                ("ship_x".to_string(), trace_ship_xg),
-                ("ship_y".to_string(), trace_ship_yg),
-                ("ship_z".to_string(), trace_ship_zg),
-                ("ship_u".to_string(), trace_ship_ug),
-                ("ship_v".to_string(), trace_ship_vg),
-                ("ship_w".to_string(), trace_ship_wg),
-                ("ship_heading".to_string(), trace_ship_psi),
-                ("uas_x".to_string(), trace_uas_xg),
-                ("uas_y".to_string(), trace_uas_yg),
-                ("uas_z".to_string(), trace_uas_zg),
-                ("uas_u".to_string(), trace_uas_ug),
-                ("uas_v".to_string(), trace_uas_vg),
-                ("uas_w".to_string(), trace_uas_wg),
-                ("uas_heading".to_string(), trace_uas_psi),
-            ]),
-        );
-        trace
+        // Both files are aligned to a common time base at their full rate, subsampling
+        // before would be undone by resampling to the rate of the time base
+        let trace = align(vec![ship, uas], setting.interpolation, setting.rate)?;
+        Ok(trace.subsample(number_skipped_entries))
     }
 
-    fn get_tree(number_skipped_entries: usize) -> Tbt {
-        tbt_node_reset_count();
-        stl_reset_count();
+    fn get_tree(_number_skipped_entries: usize) -> Tbt {
+        let builder = TbtBuilder::new();
         let ship_x = "ship_x".to_string();
         let ship_y = "ship_y".to_string();
         let ship_z = "ship_z".to_string();
@@ -85,18 +103,11 @@ impl ProvidesTraceAndTree for UserProvidedFunction {
         let uas_w = "uas_w".to_string();
         let uas_heading = "uas_heading".to_string();
 
-        let frequency = if number_skipped_entries != 0 {
-            0.005 * number_skipped_entries as f32
-        } else {
-            0.005
-        };
-        let events_per_second = (1.0 / frequency) as u64;
-
         /*
            Get Maneuvers
         */
         let lateral_maneuver = get_lateral_maneuver(
-            &events_per_second,
+            &builder,
             &uas_x,
             &uas_y,
             &uas_z,
@@ -114,7 +125,7 @@ impl ProvidesTraceAndTree for UserProvidedFunction {
         );
 
         let straight_maneuver = get_straight_maneuver(
-            &events_per_second,
+            &builder,
             &uas_x,
             &uas_y,
             &uas_z,
@@ -132,7 +143,7 @@ impl ProvidesTraceAndTree for UserProvidedFunction {
         );
 
         let oblique_maneuver = get_oblique_maneuver(
-            &events_per_second,
+            &builder,
             &uas_x,
             &uas_y,
             &uas_z,
@@ -150,7 +161,7 @@ impl ProvidesTraceAndTree for UserProvidedFunction {
         );
 
         let deg45_maneuver = get_45deg_maneuver(
-            &events_per_second,
+            &builder,
             &uas_x,
             &uas_y,
             &uas_z,
@@ -169,21 +180,221 @@ impl ProvidesTraceAndTree for UserProvidedFunction {
         /*
             Build tree
         */
-        let maneuvers = TbtNode::fallback(vec![
+        let maneuvers = builder.fallback(vec![
             lateral_maneuver,
             straight_maneuver,
             oblique_maneuver,
             deg45_maneuver,
         ]);
 
-        let descend = TbtNode::leaf(
-            Stl::eventually(Stl::atomic(
+        let descend = builder.leaf(
+            builder.eventually(builder.atomic(
                 vec![uas_x, uas_y, uas_z, ship_x, ship_y, ship_z],
-                Rc::new(descend_touchdown),
+                Arc::new(descend_touchdown),
             )),
             String::from("descend"),
         );
-        let tbt_tree = TbtNode::sequence(maneuvers, descend);
-        Tbt::new(tbt_tree)
+        let tbt_tree = builder.sequence(maneuvers, descend);
+        builder.build(tbt_tree)
+    }
+
+    fn get_atomics() -> AtomicRegistry {
+        let to_strings = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let position = to_strings(&[
+            "uas_x",
+            "uas_y",
+            "uas_z",
+            "ship_x",
+            "ship_y",
+            "ship_z",
+            "ship_heading",
+        ]);
+        let touchdown = to_strings(&["uas_x", "uas_y", "uas_z", "ship_x", "ship_y", "ship_z"]);
+        let all = to_strings(&[
+            "uas_x",
+            "uas_y",
+            "uas_z",
+            "uas_u",
+            "uas_v",
+            "uas_w",
+            "uas_heading",
+            "ship_x",
+            "ship_y",
+            "ship_z",
+            "ship_u",
+            "ship_v",
+            "ship_w",
+            "ship_heading",
+        ]);
+
+        let mut atomics: AtomicRegistry = HashMap::new();
+        /*
+           Move to position
+        */
+        atomics.insert(
+            "in_position_lateral".to_string(),
+            (
+                position.clone(),
+                Arc::new(|arguments| {
+                    in_position(
+                        Lateral::HeightAboveShip.value(),
+                        Lateral::DistanceToShip.value(),
+                        Lateral::AngleToShip.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        atomics.insert(
+            "in_position_straight".to_string(),
+            (
+                position.clone(),
+                Arc::new(|arguments| {
+                    in_position(
+                        Straight::HeightAboveShip.value(),
+                        Straight::DistanceToShip.value(),
+                        Straight::AngleToShip.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        atomics.insert(
+            "in_position_oblique".to_string(),
+            (
+                position.clone(),
+                Arc::new(|arguments| {
+                    in_position(
+                        Oblique::HeightAboveShip.value(),
+                        Oblique::DistanceToShip.value(),
+                        Oblique::AngleToShip.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        atomics.insert(
+            "in_position_45deg".to_string(),
+            (
+                position,
+                Arc::new(|arguments| {
+                    in_position(
+                        Deg45::HeightAboveShip.value(),
+                        Deg45::DistanceToShip.value(),
+                        Deg45::AngleToShip.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        /*
+           Stay in position
+        */
+        atomics.insert(
+            "stay_in_position_lateral".to_string(),
+            (
+                all.clone(),
+                Arc::new(|arguments| {
+                    combined_inpos_ha_va(
+                        Lateral::HeightAboveShip.value(),
+                        Lateral::DistanceToShip.value(),
+                        Lateral::AngleToShip.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        atomics.insert(
+            "stay_in_position_straight".to_string(),
+            (
+                all.clone(),
+                Arc::new(|arguments| {
+                    combined_inpos_ha_va(
+                        Straight::HeightAboveShip.value(),
+                        Straight::DistanceToShip.value(),
+                        Straight::AngleToShip.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        atomics.insert(
+            "stay_in_position_oblique".to_string(),
+            (
+                all.clone(),
+                Arc::new(|arguments| {
+                    combined_inpos_ho_va(
+                        Oblique::HeightAboveShip.value(),
+                        Oblique::DistanceToShip.value(),
+                        Oblique::AngleToShip.value(),
+                        Oblique::AngleOblique.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        atomics.insert(
+            "stay_in_position_45deg".to_string(),
+            (
+                all.clone(),
+                Arc::new(|arguments| {
+                    combined_inpos_ha_va(
+                        Deg45::HeightAboveShip.value(),
+                        Deg45::DistanceToShip.value(),
+                        Deg45::AngleToShip.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        /*
+           Move to touchdown
+        */
+        atomics.insert(
+            "move_to_touchdown_lateral".to_string(),
+            (
+                all.clone(),
+                Arc::new(|arguments| {
+                    combined_movetp_ha(Lateral::AboveTouchdown.value(), arguments)
+                }),
+            ),
+        );
+        atomics.insert(
+            "move_to_touchdown_straight".to_string(),
+            (
+                all.clone(),
+                Arc::new(|arguments| {
+                    combined_movetp_ha(Straight::AboveTouchdown.value(), arguments)
+                }),
+            ),
+        );
+        atomics.insert(
+            "move_to_touchdown_oblique".to_string(),
+            (
+                all.clone(),
+                Arc::new(|arguments| {
+                    combined_moveto_ho(
+                        Oblique::AboveTouchdown.value(),
+                        Oblique::AngleOblique.value(),
+                        arguments,
+                    )
+                }),
+            ),
+        );
+        atomics.insert(
+            "move_to_touchdown_45deg".to_string(),
+            (
+                all,
+                Arc::new(|arguments| combined_movetp_ha(Deg45::AboveTouchdown.value(), arguments)),
+            ),
+        );
+        /*
+           Descend
+        */
+        atomics.insert(
+            "descend_touchdown".to_string(),
+            (touchdown, Arc::new(descend_touchdown)),
+        );
+        atomics
     }
 }
@@ -0,0 +1,455 @@
+// SPDX-FileCopyrightText: 2023 German Aerospace Center (DLR)
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    csv_reader::CsvColumns,
+    trace::{align, Interpolation},
+    Trace,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, ErrorKind, Read},
+    path::Path,
+};
+
+/// First bytes of each ULog file, followed by the version and the start time
+const MAGIC: [u8; 7] = [b'U', b'L', b'o', b'g', 0x01, 0x12, 0x35];
+
+/// Bit of the first incompatible flag that marks data appended to the log, which is read like other messages
+const INCOMPATIBLE_DATA_APPENDED: u8 = 0x01;
+
+/// Returns true if the logfile is a PX4 ULog file, i.e., it ends with .ulg
+pub fn is_ulog(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ulg"))
+}
+
+/*******************************
+ * Message Formats
+ *******************************/
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Types of fields that are converted to f32
+enum Primitive {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float,
+    Double,
+    Bool,
+}
+
+impl Primitive {
+    /// Returns the primitive and its size in bytes, char is not a number but occupies a byte
+    fn parse(type_name: &str) -> Option<(Option<Primitive>, usize)> {
+        match type_name {
+            "int8_t" => Some((Some(Primitive::Int8), 1)),
+            "uint8_t" => Some((Some(Primitive::UInt8), 1)),
+            "int16_t" => Some((Some(Primitive::Int16), 2)),
+            "uint16_t" => Some((Some(Primitive::UInt16), 2)),
+            "int32_t" => Some((Some(Primitive::Int32), 4)),
+            "uint32_t" => Some((Some(Primitive::UInt32), 4)),
+            "int64_t" => Some((Some(Primitive::Int64), 8)),
+            "uint64_t" => Some((Some(Primitive::UInt64), 8)),
+            "float" => Some((Some(Primitive::Float), 4)),
+            "double" => Some((Some(Primitive::Double), 8)),
+            "bool" => Some((Some(Primitive::Bool), 1)),
+            "char" => Some((None, 1)),
+            _ => None,
+        }
+    }
+
+    /// Reads the value from its little endian bytes, the number of bytes is the size of the primitive
+    fn read(&self, bytes: &[u8]) -> f32 {
+        match self {
+            Primitive::Int8 => bytes[0] as i8 as f32,
+            Primitive::UInt8 => bytes[0] as f32,
+            Primitive::Bool => (bytes[0] != 0) as u8 as f32,
+            Primitive::Int16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            Primitive::UInt16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            Primitive::Int32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            Primitive::UInt32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            Primitive::Float => f32::from_le_bytes(bytes.try_into().unwrap()),
+            Primitive::Int64 => i64::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            Primitive::UInt64 => u64::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            Primitive::Double => f64::from_le_bytes(bytes.try_into().unwrap()) as f32,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Primitive::Int8 | Primitive::UInt8 | Primitive::Bool => 1,
+            Primitive::Int16 | Primitive::UInt16 => 2,
+            Primitive::Int32 | Primitive::UInt32 | Primitive::Float => 4,
+            Primitive::Int64 | Primitive::UInt64 | Primitive::Double => 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A field of a format definition, e.g., ``float[4] q``
+struct FormatField {
+    type_name: String,
+    array_length: Option<usize>,
+    name: String,
+}
+
+#[derive(Clone, Debug)]
+/// A numeric field of a message with its name, e.g., ``x`` or ``q[0]``, and its position in the message
+struct FlatField {
+    name: String,
+    primitive: Primitive,
+    offset: usize,
+}
+
+/// Returns the numeric fields of a format, nested formats and arrays are flattened, e.g., ``control.q[0]``
+/// # Arguments
+/// * `formats` - Maps the name of a format to its fields
+/// * `format` - Name of the format that is flattened
+/// * `prefix` - Prefix of the field names, e.g., the name of the nested field
+/// * `offset` - Position of the first field in the message, the size of the format is added
+/// * `flat_fields` - Numeric fields are appended
+/// * `nested` - Formats that are being flattened, a format that contains itself is an error
+fn flatten(
+    formats: &HashMap<String, Vec<FormatField>>,
+    format: &str,
+    prefix: &str,
+    offset: &mut usize,
+    flat_fields: &mut Vec<FlatField>,
+    nested: &mut Vec<String>,
+) -> Result<(), String> {
+    let fields = formats
+        .get(format)
+        .ok_or(format!("format {format} is not defined"))?;
+    if nested.iter().any(|name| name == format) {
+        return Err(format!("format {format} contains itself"));
+    }
+    nested.push(format.to_string());
+    for field in fields {
+        let names: Vec<String> = match field.array_length {
+            Some(length) => (0..length)
+                .map(|i| format!("{prefix}{}[{i}]", field.name))
+                .collect(),
+            None => vec![format!("{prefix}{}", field.name)],
+        };
+        match Primitive::parse(&field.type_name) {
+            Some((primitive, size)) => {
+                for name in names {
+                    // Padding and characters are not numbers
+                    if let Some(primitive) = primitive.filter(|_| !name.starts_with("_padding")) {
+                        flat_fields.push(FlatField {
+                            name,
+                            primitive,
+                            offset: *offset,
+                        });
+                    }
+                    *offset += size;
+                }
+            }
+            None => {
+                for name in names {
+                    flatten(
+                        formats,
+                        &field.type_name,
+                        &format!("{name}."),
+                        offset,
+                        flat_fields,
+                        nested,
+                    )?;
+                }
+            }
+        }
+    }
+    nested.pop();
+    Ok(())
+}
+
+/// Parses the fields of a format message, e.g., ``float x;uint64_t timestamp;float[4] q;``
+fn parse_format(format: &str) -> Result<(String, Vec<FormatField>), String> {
+    let (name, fields) = format
+        .split_once(':')
+        .ok_or(format!("format '{format}' has no name"))?;
+    let fields = fields
+        .split(';')
+        .filter(|field| !field.trim().is_empty())
+        .map(|field| {
+            let (type_name, name) = field
+                .trim()
+                .split_once(' ')
+                .ok_or(format!("field '{field}' of format {name} has no type"))?;
+            let (type_name, array_length) = match type_name.split_once('[') {
+                Some((type_name, length)) => {
+                    let length = length
+                        .trim_end_matches(']')
+                        .parse()
+                        .map_err(|_| format!("field '{field}' has an invalid array length"))?;
+                    (type_name, Some(length))
+                }
+                None => (type_name, None),
+            };
+            Ok(FormatField {
+                type_name: type_name.to_string(),
+                array_length,
+                name: name.trim().to_string(),
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    Ok((name.to_string(), fields))
+}
+
+/*******************************
+ * Reader
+ *******************************/
+/// A logged topic, i.e., an instance of a format that is added by an 'A' message
+struct Subscription {
+    name: String,
+    multi_id: u8,
+    fields: Vec<FlatField>,
+    /// Position of the timestamp (uint64_t) in the message
+    timestamp_offset: usize,
+    /// Indices in fields that are read and their signal names
+    selected: Vec<(usize, String)>,
+    timestamps: Vec<u64>,
+    values: Vec<Vec<f32>>,
+}
+
+/// Reads fields of topics of a PX4 ULog file into a trace. Each topic has its own timestamps, hence, all topics are
+/// aligned onto a common time base (in seconds since the start of the log).
+/// # Arguments
+/// * `file_name` - Location of the ULog file
+/// * `fields` - Fields that are read and their signal names, e.g., ``vehicle_local_position.x``, where the instance
+///   of a topic that is logged several times is given by ``topic[1].field``; AllNumeric reads all numeric fields
+/// * `number_skipped_entries` - used for subsampling ie only every n-th sample of the common time base is kept
+/// * `interpolation` - Defines the values of fields between two messages of their topic
+/// * `rate` - Rate (in Hz) of the common time base, if None the timestamps of the topic with most messages are used
+pub fn read_ulog_trace(
+    file_name: &str,
+    fields: CsvColumns,
+    number_skipped_entries: usize,
+    interpolation: Interpolation,
+    rate: Option<f32>,
+) -> Result<Trace, String> {
+    let error = |message: String| format!("Could not read {file_name}: {message}");
+    let file = File::open(file_name).map_err(|e| error(e.to_string()))?;
+    let mut reader = BufReader::new(file);
+    let mut header = [0; 16];
+    match reader.read_exact(&mut header) {
+        Ok(()) if header[..7] == MAGIC => (),
+        Ok(()) => return Err(error("not a ULog file".to_string())),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+            return Err(error("not a ULog file".to_string()))
+        }
+        Err(e) => return Err(error(e.to_string())),
+    }
+    let start_time = u64::from_le_bytes(header[8..16].try_into().unwrap());
+
+    let mut formats = HashMap::new();
+    let mut subscriptions: HashMap<u16, Subscription> = HashMap::new();
+    // Selected fields that were not found yet, (topic, instance, field, signal)
+    let mut missing: Vec<(String, u8, String, String)> = match fields {
+        CsvColumns::Selected(fields) => fields
+            .iter()
+            .map(|(field, signal)| {
+                let (topic, field) = field
+                    .split_once('.')
+                    .ok_or(error(format!("field '{field}' has no topic")))?;
+                let (topic, instance) = match topic.split_once('[') {
+                    Some((topic, instance)) => (
+                        topic,
+                        instance
+                            .trim_end_matches(']')
+                            .parse()
+                            .map_err(|_| error(format!("topic '{topic}' has invalid instance")))?,
+                    ),
+                    None => (topic, 0),
+                };
+                Ok((
+                    topic.to_string(),
+                    instance,
+                    field.to_string(),
+                    signal.to_string(),
+                ))
+            })
+            .collect::<Result<_, String>>()?,
+        CsvColumns::AllNumeric => vec![],
+    };
+    let mut message_header = [0; 3];
+    let mut message = vec![];
+    loop {
+        // A log that ends within a message, e.g., due to a power loss, is read until the last complete message
+        match reader.read_exact(&mut message_header) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(error(e.to_string())),
+        }
+        let size = u16::from_le_bytes([message_header[0], message_header[1]]) as usize;
+        message.resize(size, 0);
+        match reader.read_exact(&mut message) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(error(e.to_string())),
+        }
+        match message_header[2] {
+            // Flag bits
+            b'B' if message.len() >= 16
+                && (message[8] & !INCOMPATIBLE_DATA_APPENDED != 0
+                    || message[9..16].iter().any(|flag| *flag != 0)) =>
+            {
+                return Err(error("unknown incompatible flags".to_string()));
+            }
+            // Format definition
+            b'F' => {
+                let (name, fields) =
+                    parse_format(&String::from_utf8_lossy(&message)).map_err(error)?;
+                formats.insert(name, fields);
+            }
+            // Subscription of a topic
+            b'A' => {
+                if message.len() < 3 {
+                    return Err(error("add message is too short".to_string()));
+                }
+                let multi_id = message[0];
+                let msg_id = u16::from_le_bytes([message[1], message[2]]);
+                let name = String::from_utf8_lossy(&message[3..]).to_string();
+                let mut flat_fields = vec![];
+                flatten(&formats, &name, "", &mut 0, &mut flat_fields, &mut vec![])
+                    .map_err(error)?;
+                let timestamp_offset = flat_fields
+                    .iter()
+                    .find(|field| field.name == "timestamp" && field.primitive == Primitive::UInt64)
+                    .map(|field| field.offset)
+                    .ok_or(error(format!("topic {name} has no timestamp")))?;
+                let selected: Vec<(usize, String)> = match fields {
+                    CsvColumns::Selected(_) => {
+                        let mut selected = vec![];
+                        missing.retain(|(topic, instance, field, signal)| {
+                            match flat_fields.iter().position(|flat| &flat.name == field) {
+                                Some(index) if *topic == name && *instance == multi_id => {
+                                    selected.push((index, signal.clone()));
+                                    false
+                                }
+                                _ => true,
+                            }
+                        });
+                        selected
+                    }
+                    CsvColumns::AllNumeric => {
+                        let topic = match multi_id {
+                            0 => name.clone(),
+                            _ => format!("{name}[{multi_id}]"),
+                        };
+                        flat_fields
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, field)| field.name != "timestamp")
+                            .map(|(index, field)| (index, format!("{topic}.{}", field.name)))
+                            .collect()
+                    }
+                };
+                subscriptions.insert(
+                    msg_id,
+                    Subscription {
+                        name,
+                        multi_id,
+                        values: vec![vec![]; selected.len()],
+                        fields: flat_fields,
+                        timestamp_offset,
+                        selected,
+                        timestamps: vec![],
+                    },
+                );
+            }
+            // Data of a subscribed topic
+            b'D' => {
+                if message.len() < 2 {
+                    return Err(error("data message is too short".to_string()));
+                }
+                let msg_id = u16::from_le_bytes([message[0], message[1]]);
+                let Some(subscription) = subscriptions.get_mut(&msg_id) else {
+                    continue;
+                };
+                if subscription.selected.is_empty() {
+                    continue;
+                }
+                let data = &message[2..];
+                let read = |offset: usize, size: usize| {
+                    data.get(offset..offset + size).ok_or_else(|| {
+                        error(format!(
+                            "data of topic {} is shorter than its format",
+                            subscription.name
+                        ))
+                    })
+                };
+                let timestamp =
+                    u64::from_le_bytes(read(subscription.timestamp_offset, 8)?.try_into().unwrap());
+                let row = subscription
+                    .selected
+                    .iter()
+                    .map(|(index, _)| {
+                        let field = &subscription.fields[*index];
+                        read(field.offset, field.primitive.size())
+                            .map(|bytes| field.primitive.read(bytes))
+                    })
+                    .collect::<Result<Vec<f32>, String>>()?;
+                subscription.timestamps.push(timestamp);
+                for (values, value) in subscription.values.iter_mut().zip(row) {
+                    values.push(value);
+                }
+            }
+            // Information, parameters, logged strings, synchronization, and dropouts are not needed
+            _ => (),
+        }
+    }
+    if let Some((topic, instance, field, _)) = missing.first() {
+        let topic = match instance {
+            0 => topic.clone(),
+            _ => format!("{topic}[{instance}]"),
+        };
+        return Err(error(format!("field {topic}.{field} not found")));
+    }
+
+    // Each topic is a trace with its own time axis
+    let mut subscriptions: Vec<Subscription> = subscriptions
+        .into_values()
+        .filter(|subscription| !subscription.selected.is_empty())
+        .collect();
+    subscriptions.sort_by(|a, b| (&a.name, a.multi_id).cmp(&(&b.name, b.multi_id)));
+    let mut traces = vec![];
+    // Topics without messages are only an error if their fields were selected
+    if let CsvColumns::AllNumeric = fields {
+        subscriptions.retain(|subscription| !subscription.timestamps.is_empty());
+    }
+    for subscription in subscriptions {
+        if subscription.timestamps.is_empty() {
+            // Topics without messages cannot be aligned
+            return Err(error(format!("topic {} has no data", subscription.name)));
+        }
+        let time = subscription
+            .timestamps
+            .iter()
+            .map(|timestamp| (timestamp.saturating_sub(start_time) as f64 / 1e6) as f32)
+            .collect();
+        let signals = subscription
+            .selected
+            .into_iter()
+            .map(|(_, signal)| signal)
+            .zip(subscription.values)
+            .collect();
+        traces.push(
+            Trace::new(signals)
+                .and_then(|trace| trace.with_time(time))
+                .map_err(|e| error(format!("topic {}: {e}", subscription.name)))?,
+        );
+    }
+    if traces.is_empty() {
+        return Err(error("no fields selected".to_string()));
+    }
+    let trace = align(traces, interpolation, rate).map_err(error)?;
+    Ok(trace.subsample(number_skipped_entries))
+}